@@ -28,8 +28,15 @@ fn show_state(game: &ZingGame) {
 }
 
 fn main() {
-    let mut game = ZingGame::new_with_player_names(vec!["Hans".into(), "Darko".into()], 1);
-    game.setup_game();
+    // an optional seed as first argument makes it possible to reproduce a deal
+    let names = vec!["Hans".into(), "Darko".into()];
+    let mut game = match std::env::args().nth(1) {
+        Some(seed) => ZingGame::new_with_seed(names, 1, seed.parse().expect("invalid seed")),
+        None => ZingGame::new_with_player_names(names, 1),
+    }
+    .unwrap();
+    game.setup_game().unwrap();
+    println!("Seed: {}", game.seed().unwrap());
 
     let players = [RandomPlayer::new(0), RandomPlayer::new(1)];
 
@@ -0,0 +1,297 @@
+//! Plays two Zing AIs against each other over many seeded games and reports
+//! statistics, e.g. in order to check whether a change to an AI is a real
+//! improvement.
+//!
+//! Games are played in pairs: both games of a pair use the same deal, with
+//! the AIs swapping seats, and the dealer alternates between pairs.  This
+//! removes most of the luck of the deal from the comparison.
+
+use std::process::ExitCode;
+
+use serde::Serialize;
+use zing_game::zing_ai::{ai_by_name, ZingAI, AI_NAMES};
+use zing_game::zing_game::ZingGame;
+
+const USAGE: &str = "usage: zing-arena <AI> <AI> [--games N] [--seed SEED] [--json]";
+
+/// z value for 95% confidence intervals (normal approximation)
+const Z_95: f64 = 1.96;
+
+struct Options {
+    ai_names: [String; 2],
+    games: usize,
+    seed: u64,
+    json: bool,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut ai_names = Vec::new();
+    let mut games = 1000;
+    let mut seed = 0;
+    let mut json = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => {
+                games = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|games| *games > 0)
+                    .ok_or("--games requires a positive number")?;
+            }
+            "--seed" => {
+                seed = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--seed requires a number")?;
+            }
+            "--json" => json = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => {
+                if !AI_NAMES.contains(&arg.as_str()) {
+                    return Err(format!(
+                        "unknown AI {arg:?} (available: {})",
+                        AI_NAMES.join(", ")
+                    ));
+                }
+                ai_names.push(arg);
+            }
+        }
+    }
+
+    let ai_names: [String; 2] = ai_names
+        .try_into()
+        .map_err(|_| "exactly two AIs must be given")?;
+
+    Ok(Options {
+        ai_names,
+        games,
+        seed,
+        json,
+    })
+}
+
+/// Sample mean with the half width of its 95% confidence interval.
+#[derive(Serialize)]
+struct Estimate {
+    mean: f64,
+    confidence_interval: f64,
+}
+
+impl Estimate {
+    fn from_samples(samples: &[f64]) -> Self {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = if samples.len() > 1 {
+            samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.)
+        } else {
+            0.
+        };
+        Self {
+            mean,
+            confidence_interval: Z_95 * (variance / n).sqrt(),
+        }
+    }
+}
+
+/// Per-game results of one AI.
+#[derive(Default)]
+struct Samples {
+    /// 1 for a win, 0.5 for a tie, 0 for a loss
+    score: Vec<f64>,
+    card_points: Vec<f64>,
+    card_count_points: Vec<f64>,
+    zing_points: Vec<f64>,
+    total_points: Vec<f64>,
+    point_difference: Vec<f64>,
+    zings: Vec<f64>,
+}
+
+#[derive(Serialize)]
+struct AiReport {
+    name: String,
+    wins: usize,
+    ties: usize,
+    losses: usize,
+    /// ties count as half a win
+    win_rate: Estimate,
+    card_points: Estimate,
+    card_count_points: Estimate,
+    zing_points: Estimate,
+    total_points: Estimate,
+    point_difference: Estimate,
+    zings_per_game: Estimate,
+}
+
+impl AiReport {
+    fn new(name: &str, samples: &Samples) -> Self {
+        let count = |score: f64| samples.score.iter().filter(|s| **s == score).count();
+        Self {
+            name: name.to_owned(),
+            wins: count(1.),
+            ties: count(0.5),
+            losses: count(0.),
+            win_rate: Estimate::from_samples(&samples.score),
+            card_points: Estimate::from_samples(&samples.card_points),
+            card_count_points: Estimate::from_samples(&samples.card_count_points),
+            zing_points: Estimate::from_samples(&samples.zing_points),
+            total_points: Estimate::from_samples(&samples.total_points),
+            point_difference: Estimate::from_samples(&samples.point_difference),
+            zings_per_game: Estimate::from_samples(&samples.zings),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ArenaReport {
+    games: usize,
+    seed: u64,
+    players: [AiReport; 2],
+}
+
+fn play_game(ais: [&(dyn ZingAI + Send + Sync); 2], game: &mut ZingGame) {
+    game.setup_game().unwrap();
+    while let Some(current) = game.current_player() {
+        let ai = if ais[0].player_index() == current {
+            ais[0]
+        } else {
+            ais[1]
+        };
+        ai.auto_play(game);
+    }
+}
+
+fn run_arena(options: &Options) -> ArenaReport {
+    let mut samples: [Samples; 2] = Default::default();
+
+    for i in 0..options.games {
+        let pair = i / 2;
+        let deal_seed = options.seed.wrapping_add(pair as u64);
+        let dealer = pair % 2;
+        // seat of each AI; swapped for the second game of each pair
+        let seats = [i % 2, 1 - i % 2];
+
+        let ais = [0, 1].map(|ai| {
+            ai_by_name(&options.ai_names[ai], seats[ai]).expect("AI names have been checked")
+        });
+        let mut names = vec![String::new(); 2];
+        for ai in 0..2 {
+            names[seats[ai]] = options.ai_names[ai].clone();
+        }
+
+        let mut game = ZingGame::new_with_seed(names, dealer, deal_seed).unwrap();
+        play_game([ais[0].as_ref(), ais[1].as_ref()], &mut game);
+
+        let points = game.points();
+        let zings = game.zing_count();
+        let team_value = |(value0, value1): (u32, u32), seat: usize| {
+            f64::from(if ZingGame::team_of_player(seat) == 0 {
+                value0
+            } else {
+                value1
+            })
+        };
+        for ai in 0..2 {
+            let own = team_value(points.total_points(), seats[ai]);
+            let other = team_value(points.total_points(), seats[1 - ai]);
+            let sample = &mut samples[ai];
+            sample.score.push(match own.total_cmp(&other) {
+                std::cmp::Ordering::Greater => 1.,
+                std::cmp::Ordering::Equal => 0.5,
+                std::cmp::Ordering::Less => 0.,
+            });
+            sample
+                .card_points
+                .push(team_value(points.card_points, seats[ai]));
+            sample
+                .card_count_points
+                .push(team_value(points.card_count_points, seats[ai]));
+            sample
+                .zing_points
+                .push(team_value(points.zing_points, seats[ai]));
+            sample.total_points.push(own);
+            sample.point_difference.push(own - other);
+            sample.zings.push(team_value(zings, seats[ai]));
+        }
+    }
+
+    ArenaReport {
+        games: options.games,
+        seed: options.seed,
+        players: [0, 1].map(|ai| AiReport::new(&options.ai_names[ai], &samples[ai])),
+    }
+}
+
+fn print_report(report: &ArenaReport) {
+    println!(
+        "{} vs. {}: {} games (seeds {}..)",
+        report.players[0].name, report.players[1].name, report.games, report.seed
+    );
+    println!();
+    println!(
+        "{:<20} {:>18} {:>18}",
+        "", report.players[0].name, report.players[1].name
+    );
+
+    let wins = |player: &AiReport| format!("{}/{}/{}", player.wins, player.ties, player.losses);
+    println!(
+        "{:<20} {:>18} {:>18}",
+        "wins/ties/losses",
+        wins(&report.players[0]),
+        wins(&report.players[1])
+    );
+
+    // label, selected estimate, and scale factor for display
+    type Row = (&'static str, fn(&AiReport) -> &Estimate, f64);
+    let rows: [Row; 7] = [
+        ("win rate (%)", |player| &player.win_rate, 100.),
+        ("card points", |player| &player.card_points, 1.),
+        ("card count points", |player| &player.card_count_points, 1.),
+        ("zing points", |player| &player.zing_points, 1.),
+        ("total points", |player| &player.total_points, 1.),
+        ("point difference", |player| &player.point_difference, 1.),
+        ("zings per game", |player| &player.zings_per_game, 1.),
+    ];
+    for (label, estimate, scale) in rows {
+        let format = |player: &AiReport| {
+            let estimate = estimate(player);
+            format!(
+                "{:.2} ± {:.2}",
+                scale * estimate.mean,
+                scale * estimate.confidence_interval
+            )
+        };
+        println!(
+            "{:<20} {:>18} {:>18}",
+            label,
+            format(&report.players[0]),
+            format(&report.players[1])
+        );
+    }
+    println!();
+    println!("(averages per game with 95% confidence intervals)");
+}
+
+fn main() -> ExitCode {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(msg) => {
+            eprintln!("{msg}\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let report = run_arena(&options);
+
+    if options.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("report is serializable")
+        );
+    } else {
+        print_report(&report);
+    }
+
+    ExitCode::SUCCESS
+}
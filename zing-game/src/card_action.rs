@@ -1,7 +1,7 @@
 use bevy_reflect::Reflect;
 use serde::{Deserialize, Serialize};
 
-use crate::game::{CardState, GameState};
+use crate::game::{CardGameError, CardState, GameState};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Reflect)]
 pub enum CardLocation {
@@ -18,7 +18,7 @@ pub enum CardRotation {
 /// Generic action moving cards from one stack to another, from a stack to a
 /// hand or vice versa.  Cards can also be rotated or placed in a particular
 /// location in the stack (e.g., from top to bottom of the same stack).
-#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CardAction {
     pub source_location: Option<CardLocation>,
     pub source_index: usize,
@@ -31,6 +31,26 @@ pub struct CardAction {
     pub rotation: Option<CardRotation>,
 
     pub resulting_card_states: Vec<CardState>,
+
+    /// States of the moved cards before the action, remembered by
+    /// [CardAction::apply_and_remember_cards] (needed for [CardAction::invert])
+    #[serde(default)]
+    pub source_card_states: Vec<CardState>,
+}
+
+/// Rules of a card game deciding which [CardAction]s players may perform
+/// themselves (as opposed to the actions following automatically, e.g. taking
+/// a trick).  This allows servers to accept actions built by clients without
+/// trusting them.
+pub trait ActionRules {
+    type Error;
+
+    /// Checks that the given player may currently perform the action.
+    fn validate_action(&self, player: usize, action: &CardAction) -> Result<(), Self::Error>;
+
+    /// Performs a valid action together with all resulting automatic actions.
+    /// Invalid actions are rejected without changing the game.
+    fn perform_action(&mut self, player: usize, action: &CardAction) -> Result<(), Self::Error>;
 }
 
 impl CardAction {
@@ -39,36 +59,170 @@ impl CardAction {
     }
 
     /// Returns a copy of this CardAction, using CardState::covered() to hide
-    /// cards on other player's hands.
-    pub fn new_view_for_player(&self, player_index: usize) -> Self {
-        match self.dest_location.expect("destination not set up") {
-            CardLocation::PlayerHand => {
-                if self.dest_index != player_index {
-                    CardAction {
-                        resulting_card_states: self
-                            .resulting_card_states
-                            .iter()
-                            .map(CardState::covered)
-                            .collect(),
-                        ..self.clone()
-                    }
-                } else {
-                    self.clone()
+    /// cards on other player's hands (and face down cards on stacks).  Fails
+    /// for actions whose source or destination has not been set up.
+    pub fn new_view_for_player(&self, player_index: usize) -> Result<Self, CardGameError> {
+        let view = |location: Option<CardLocation>, index: usize, card_states: &[CardState]| {
+            Ok(match location.ok_or(CardGameError::IncompleteAction)? {
+                CardLocation::PlayerHand if index != player_index => {
+                    card_states.iter().map(CardState::covered).collect()
                 }
-            }
-            CardLocation::Stack => self.clone(),
+                CardLocation::PlayerHand => card_states.to_vec(),
+                CardLocation::Stack => card_states
+                    .iter()
+                    .map(CardState::covered_if_face_down)
+                    .collect(),
+            })
+        };
+
+        Ok(CardAction {
+            resulting_card_states: view(
+                self.dest_location,
+                self.dest_index,
+                &self.resulting_card_states,
+            )?,
+            source_card_states: view(
+                self.source_location,
+                self.source_index,
+                &self.source_card_states,
+            )?,
+            ..self.clone()
+        })
+    }
+
+    /// Returns the action reverting this one, i.e. moving the cards back to
+    /// their original positions and restoring their original states.  Only
+    /// possible for actions applied via [CardAction::apply_and_remember_cards].
+    pub fn invert(&self) -> Result<Self, CardGameError> {
+        if self.source_card_states.len() != self.source_card_indices.len() {
+            return Err(CardGameError::CardStatesNotRemembered);
+        }
+        self.check_index_counts()?;
+
+        let mut moves: Vec<_> = self
+            .source_card_indices
+            .iter()
+            .zip(&self.dest_card_indices)
+            .zip(
+                self.source_card_states
+                    .iter()
+                    .zip(&self.resulting_card_states),
+            )
+            .collect();
+        // cards are inserted one after another, so they have to be restored in
+        // the order of their original positions
+        moves.sort_by_key(|((source_index, _), _)| **source_index);
+
+        Ok(CardAction {
+            source_location: self.dest_location,
+            source_index: self.dest_index,
+            source_card_indices: moves.iter().map(|((_, dest), _)| **dest).collect(),
+
+            dest_location: self.source_location,
+            dest_index: self.source_index,
+            dest_card_indices: moves.iter().map(|((source, _), _)| **source).collect(),
+
+            rotation: None,
+
+            resulting_card_states: moves
+                .iter()
+                .map(|(_, (before, _))| (*before).clone())
+                .collect(),
+            source_card_states: moves
+                .iter()
+                .map(|(_, (_, after))| (*after).clone())
+                .collect(),
+        })
+    }
+
+    fn cards(
+        game: &GameState,
+        location: CardLocation,
+        index: usize,
+    ) -> Result<&Vec<CardState>, CardGameError> {
+        match location {
+            CardLocation::PlayerHand => game.players.get(index).map(|player| &player.hand),
+            CardLocation::Stack => game.stacks.get(index).map(|stack| &stack.cards),
         }
+        .ok_or(CardGameError::InvalidStackIndex(location, index))
     }
 
-    fn stack_mut(
+    fn cards_mut(
         game: &mut GameState,
         location: CardLocation,
         index: usize,
-    ) -> &mut Vec<CardState> {
+    ) -> Result<&mut Vec<CardState>, CardGameError> {
         match location {
-            CardLocation::PlayerHand => &mut game.players[index].hand,
-            CardLocation::Stack => &mut game.stacks[index].cards,
+            CardLocation::PlayerHand => game.players.get_mut(index).map(|player| &mut player.hand),
+            CardLocation::Stack => game.stacks.get_mut(index).map(|stack| &mut stack.cards),
         }
+        .ok_or(CardGameError::InvalidStackIndex(location, index))
+    }
+
+    /// Checks that all indices refer to different cards of the given hand or
+    /// stack.
+    fn check_card_indices(
+        cards: &[CardState],
+        location: CardLocation,
+        index: usize,
+        card_indices: &[usize],
+    ) -> Result<(), CardGameError> {
+        for (i, card_index) in card_indices.iter().enumerate() {
+            if *card_index >= cards.len() || card_indices[..i].contains(card_index) {
+                return Err(CardGameError::InvalidCardIndex(
+                    location,
+                    index,
+                    *card_index,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_index_counts(&self) -> Result<(), CardGameError> {
+        let count = self.source_card_indices.len();
+        if self.dest_card_indices.len() != count {
+            return Err(CardGameError::MismatchedIndexCounts(
+                count,
+                self.dest_card_indices.len(),
+            ));
+        }
+        if !self.resulting_card_states.is_empty() && self.resulting_card_states.len() != count {
+            return Err(CardGameError::MismatchedIndexCounts(
+                count,
+                self.resulting_card_states.len(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn set_source<'a>(
+        &'a mut self,
+        game: &GameState,
+        location: CardLocation,
+        index: usize,
+        card_indices: Vec<usize>,
+    ) -> Result<&'a mut Self, CardGameError> {
+        let cards = Self::cards(game, location, index)?;
+        Self::check_card_indices(cards, location, index, &card_indices)?;
+        self.source_location = Some(location);
+        self.source_index = index;
+        self.source_card_indices = card_indices;
+        Ok(self)
+    }
+
+    /// Sets the destination to the end of the given hand or stack.
+    fn set_dest_to_end<'a>(
+        &'a mut self,
+        game: &GameState,
+        location: CardLocation,
+        index: usize,
+    ) -> Result<&'a mut Self, CardGameError> {
+        let len = Self::cards(game, location, index)?.len();
+        self.dest_location = Some(location);
+        self.dest_index = index;
+        self.dest_card_indices = (len..len + self.source_card_indices.len()).collect();
+        Ok(self)
     }
 
     pub fn from_hand<'a>(
@@ -76,24 +230,16 @@ impl CardAction {
         game: &GameState,
         player: usize,
         card_indices: Vec<usize>,
-    ) -> &'a mut Self {
-        let hand = &game.players.get(player).unwrap().hand;
-        self.source_location = Some(CardLocation::PlayerHand);
-        self.source_index = player;
-        for index in card_indices.iter() {
-            assert!(*index < hand.len());
-        }
-        self.source_card_indices = card_indices;
-        self
+    ) -> Result<&'a mut Self, CardGameError> {
+        self.set_source(game, CardLocation::PlayerHand, player, card_indices)
     }
 
-    pub fn to_hand<'a>(&'a mut self, game: &GameState, player: usize) -> &'a mut Self {
-        let hand = &game.players.get(player).unwrap().hand;
-        self.dest_location = Some(CardLocation::PlayerHand);
-        self.dest_index = player;
-        self.dest_card_indices =
-            (hand.len()..hand.len() + self.source_card_indices.len()).collect();
-        self
+    pub fn to_hand<'a>(
+        &'a mut self,
+        game: &GameState,
+        player: usize,
+    ) -> Result<&'a mut Self, CardGameError> {
+        self.set_dest_to_end(game, CardLocation::PlayerHand, player)
     }
 
     pub fn from_stack<'a>(
@@ -101,15 +247,8 @@ impl CardAction {
         game: &GameState,
         stack: usize,
         card_indices: Vec<usize>,
-    ) -> &'a mut Self {
-        let cards = &game.stacks.get(stack).unwrap().cards;
-        self.source_location = Some(CardLocation::Stack);
-        self.source_index = stack;
-        for index in card_indices.iter() {
-            assert!(*index < cards.len());
-        }
-        self.source_card_indices = card_indices;
-        self
+    ) -> Result<&'a mut Self, CardGameError> {
+        self.set_source(game, CardLocation::Stack, stack, card_indices)
     }
 
     pub fn from_stack_top<'a>(
@@ -117,30 +256,32 @@ impl CardAction {
         game: &GameState,
         stack: usize,
         card_count: usize,
-    ) -> &'a mut Self {
-        let cards = &game.stacks.get(stack).unwrap().cards;
-        self.from_stack(
-            game,
-            stack,
-            (cards.len() - card_count..cards.len()).collect(),
-        )
+    ) -> Result<&'a mut Self, CardGameError> {
+        let len = Self::cards(game, CardLocation::Stack, stack)?.len();
+        let first = len
+            .checked_sub(card_count)
+            .ok_or(CardGameError::DrawingStackEmpty(stack))?;
+        self.from_stack(game, stack, (first..len).collect())
     }
 
-    pub fn to_stack_top<'a>(&'a mut self, game: &GameState, stack: usize) -> &'a mut Self {
-        let cards = &game.stacks.get(stack).unwrap().cards;
-        self.dest_location = Some(CardLocation::Stack);
-        self.dest_index = stack;
-        self.dest_card_indices =
-            (cards.len()..cards.len() + self.source_card_indices.len()).collect();
-        self
+    pub fn to_stack_top<'a>(
+        &'a mut self,
+        game: &GameState,
+        stack: usize,
+    ) -> Result<&'a mut Self, CardGameError> {
+        self.set_dest_to_end(game, CardLocation::Stack, stack)
     }
 
-    pub fn to_stack_bottom<'a>(&'a mut self, game: &GameState, stack: usize) -> &'a mut Self {
-        assert!(stack < game.stacks.len());
+    pub fn to_stack_bottom<'a>(
+        &'a mut self,
+        game: &GameState,
+        stack: usize,
+    ) -> Result<&'a mut Self, CardGameError> {
+        Self::cards(game, CardLocation::Stack, stack)?;
         self.dest_location = Some(CardLocation::Stack);
         self.dest_index = stack;
         self.dest_card_indices = (0..self.source_card_indices.len()).collect();
-        self
+        Ok(self)
     }
 
     pub fn rotate(&mut self, target_rotation: CardRotation) -> &mut Self {
@@ -148,20 +289,75 @@ impl CardAction {
         self
     }
 
-    pub fn apply_and_remember_cards(&mut self, game: &mut GameState) {
-        self.resulting_card_states = self.apply(game);
+    /// Checks that the action can be applied to the given state, i.e. that
+    /// it moves at least one card, and that all locations and card indices
+    /// are valid.
+    pub fn validate(&self, game: &GameState) -> Result<(), CardGameError> {
+        let (Some(source_location), Some(dest_location)) =
+            (self.source_location, self.dest_location)
+        else {
+            return Err(CardGameError::IncompleteAction);
+        };
+        if self.source_card_indices.is_empty() {
+            return Err(CardGameError::EmptySource);
+        }
+        self.check_index_counts()?;
+
+        let source_cards = Self::cards(game, source_location, self.source_index)?;
+        Self::check_card_indices(
+            source_cards,
+            source_location,
+            self.source_index,
+            &self.source_card_indices,
+        )?;
+
+        // the moved cards are removed before being inserted one after another
+        let mut dest_len = Self::cards(game, dest_location, self.dest_index)?.len();
+        if (source_location, self.source_index) == (dest_location, self.dest_index) {
+            dest_len -= self.source_card_indices.len();
+        }
+        for dest_card_index in &self.dest_card_indices {
+            if *dest_card_index > dest_len {
+                return Err(CardGameError::InvalidCardIndex(
+                    dest_location,
+                    self.dest_index,
+                    *dest_card_index,
+                ));
+            }
+            dest_len += 1;
+        }
+        Ok(())
+    }
+
+    pub fn apply_and_remember_cards(&mut self, game: &mut GameState) -> Result<(), CardGameError> {
+        self.validate(game)?;
+        let source_cards = Self::cards(
+            game,
+            self.source_location
+                .ok_or(CardGameError::IncompleteAction)?,
+            self.source_index,
+        )?;
+        self.source_card_states = self
+            .source_card_indices
+            .iter()
+            .map(|index| source_cards[*index].clone())
+            .collect();
+        self.resulting_card_states = self.apply(game)?;
+        Ok(())
     }
 
-    pub fn apply(&self, game: &mut GameState) -> Vec<CardState> {
-        assert_eq!(self.source_card_indices.len(), self.dest_card_indices.len());
-        assert!(!self.source_card_indices.is_empty());
+    /// Applies the action to the given state and returns the resulting states
+    /// of the moved cards.  The state is not modified if the action is
+    /// invalid (see [CardAction::validate]).
+    pub fn apply(&self, game: &mut GameState) -> Result<Vec<CardState>, CardGameError> {
+        self.validate(game)?;
+        let source_location = self
+            .source_location
+            .ok_or(CardGameError::IncompleteAction)?;
+        let dest_location = self.dest_location.ok_or(CardGameError::IncompleteAction)?;
 
         let source_cards: Vec<CardState> = {
-            let source_stack = Self::stack_mut(
-                game,
-                self.source_location.expect("CardAction source not set up"),
-                self.source_index,
-            );
+            let source_stack = Self::cards_mut(game, source_location, self.source_index)?;
 
             let rotated_cards = if !self.resulting_card_states.is_empty() {
                 self.resulting_card_states.clone()
@@ -188,17 +384,125 @@ impl CardAction {
             rotated_cards
         };
 
-        let dest_stack = Self::stack_mut(
-            game,
-            self.dest_location
-                .expect("CardAction destination not set up"),
-            self.dest_index,
-        );
+        let dest_stack = Self::cards_mut(game, dest_location, self.dest_index)?;
 
         for (dest_index, card_state) in self.dest_card_indices.iter().zip(&source_cards) {
             dest_stack.insert(*dest_index, card_state.clone());
         }
 
-        source_cards
+        Ok(source_cards)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CardAction, CardLocation, CardRotation};
+    use crate::decks::deck;
+    use crate::game::{CardGameError, GameState, StackState};
+    use crate::Back;
+
+    fn game_with_stock() -> GameState {
+        let mut game = GameState::new_with_player_names(vec!["A".into(), "B".into()]);
+        game.stacks.push(StackState::new_from_deck(
+            "stock".into(),
+            deck(Back::Blue)[..4].to_vec(),
+            false,
+        ));
+        game.stacks.push(StackState::new("table".into()));
+        game
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let game = game_with_stock();
+        assert_eq!(
+            CardAction::new().from_hand(&game, 2, vec![0]).err(),
+            Some(CardGameError::InvalidStackIndex(
+                CardLocation::PlayerHand,
+                2
+            ))
+        );
+        assert_eq!(
+            CardAction::new().from_hand(&game, 0, vec![0]).err(),
+            Some(CardGameError::InvalidCardIndex(
+                CardLocation::PlayerHand,
+                0,
+                0
+            ))
+        );
+        assert_eq!(
+            CardAction::new().from_stack(&game, 0, vec![1, 1]).err(),
+            Some(CardGameError::InvalidCardIndex(CardLocation::Stack, 0, 1))
+        );
+        assert_eq!(
+            CardAction::new().from_stack_top(&game, 0, 5).err(),
+            Some(CardGameError::DrawingStackEmpty(0))
+        );
+        assert_eq!(
+            CardAction::new()
+                .from_stack_top(&game, 0, 2)
+                .and_then(|action| action.to_stack_top(&game, 7))
+                .err(),
+            Some(CardGameError::InvalidStackIndex(CardLocation::Stack, 7))
+        );
+    }
+
+    #[test]
+    fn test_apply_errors_leave_state_unchanged() {
+        let mut game = game_with_stock();
+        let valid = CardAction::new()
+            .from_stack_top(&game, 0, 2)
+            .unwrap()
+            .to_hand(&game, 1)
+            .unwrap()
+            .rotate(CardRotation::FaceUp)
+            .clone();
+
+        let mut invalid_actions = vec![CardAction::new()];
+        invalid_actions.push(CardAction {
+            source_card_indices: Vec::new(),
+            dest_card_indices: Vec::new(),
+            ..valid.clone()
+        });
+        invalid_actions.push(CardAction {
+            dest_card_indices: vec![0],
+            ..valid.clone()
+        });
+        invalid_actions.push(CardAction {
+            source_card_indices: vec![2, 9],
+            ..valid.clone()
+        });
+        invalid_actions.push(CardAction {
+            dest_card_indices: vec![0, 2],
+            ..valid.clone()
+        });
+        let expected = [
+            CardGameError::IncompleteAction,
+            CardGameError::EmptySource,
+            CardGameError::MismatchedIndexCounts(2, 1),
+            CardGameError::InvalidCardIndex(CardLocation::Stack, 0, 9),
+            CardGameError::InvalidCardIndex(CardLocation::PlayerHand, 1, 2),
+        ];
+        for (action, error) in invalid_actions.iter().zip(expected) {
+            assert_eq!(action.apply(&mut game), Err(error));
+            assert_eq!(game.stacks[0].cards.len(), 4);
+            assert!(game.players[1].hand.is_empty());
+        }
+        assert_eq!(
+            valid.invert().err(),
+            Some(CardGameError::CardStatesNotRemembered)
+        );
+        assert_eq!(
+            CardAction::new().new_view_for_player(0).err(),
+            Some(CardGameError::IncompleteAction)
+        );
+        assert!(valid.new_view_for_player(0).is_ok());
+
+        let mut applied = valid.clone();
+        applied.apply_and_remember_cards(&mut game).unwrap();
+        assert_eq!(game.players[1].hand.len(), 2);
+        applied.invert().unwrap().apply(&mut game).unwrap();
+        assert_eq!(game.stacks[0].cards.len(), 4);
+        assert!(game.players[1].hand.is_empty());
     }
 }
@@ -33,7 +33,7 @@ pub enum Back {
     Blue,
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, Reflect)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Reflect)]
 pub struct Card {
     pub rank: Rank,
     pub suit: Suit,
@@ -0,0 +1,93 @@
+//! Table chat: free text messages and predefined emote reactions, exchanged
+//! via the table WebSocket (see [crate::client_command::Command::Chat]).
+
+use serde::{Deserialize, Serialize};
+
+/// Maximum length of chat messages (in characters)
+pub const MAX_CHAT_MESSAGE_CHARS: usize = 280;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emote {
+    Zing,
+    Nice,
+    WellPlayed,
+    Oops,
+    GoodGame,
+}
+
+impl Emote {
+    pub const ALL: [Emote; 5] = [
+        Emote::Zing,
+        Emote::Nice,
+        Emote::WellPlayed,
+        Emote::Oops,
+        Emote::GoodGame,
+    ];
+
+    /// Text displayed for this emote
+    pub fn text(self) -> &'static str {
+        match self {
+            Emote::Zing => "Zing!",
+            Emote::Nice => "nice",
+            Emote::WellPlayed => "well played",
+            Emote::Oops => "oops",
+            Emote::GoodGame => "good game",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum ChatContent {
+    Text(String),
+    Emote(Emote),
+}
+
+impl ChatContent {
+    /// Checks that a text message is neither empty nor too long.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        match self {
+            ChatContent::Text(text) if text.trim().is_empty() => {
+                Err("chat message must not be empty")
+            }
+            ChatContent::Text(text) if text.chars().count() > MAX_CHAT_MESSAGE_CHARS => {
+                Err("chat message is too long")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Text displayed for this message
+    pub fn text(&self) -> &str {
+        match self {
+            ChatContent::Text(text) => text,
+            ChatContent::Emote(emote) => emote.text(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub user_name: String,
+    /// Position of the sender at the table (None for spectators)
+    pub player: Option<usize>,
+    /// Unix time in milliseconds
+    pub sent_at: u64,
+    pub content: ChatContent,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChatContent, Emote, MAX_CHAT_MESSAGE_CHARS};
+
+    #[test]
+    fn test_validate_chat_content() {
+        assert!(ChatContent::Text("hi!".into()).validate().is_ok());
+        assert!(ChatContent::Text(" \n".into()).validate().is_err());
+        assert!(ChatContent::Emote(Emote::Zing).validate().is_ok());
+
+        // the limit is about characters, not bytes
+        let long_text = "ä".repeat(MAX_CHAT_MESSAGE_CHARS);
+        assert!(ChatContent::Text(long_text.clone()).validate().is_ok());
+        assert!(ChatContent::Text(long_text + "!").validate().is_err());
+    }
+}
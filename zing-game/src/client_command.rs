@@ -0,0 +1,87 @@
+//! Commands sent by clients over the table WebSocket.  Each command carries a
+//! request id chosen by the client, which the server echoes in the
+//! [ClientNotification::CommandResult](crate::client_notification::ClientNotification::CommandResult)
+//! sent after the command has been executed (and after all notifications
+//! resulting from it).
+
+use serde::{Deserialize, Serialize};
+
+use crate::{card_action::CardAction, chat::ChatContent, error_code::ErrorCode};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClientCommand {
+    pub request_id: u64,
+    pub command: Command,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    PlayCard {
+        card_index: usize,
+    },
+    /// Plays a card described by a full card action, which the server checks
+    /// against the rules (see [ActionRules](crate::card_action::ActionRules))
+    PerformAction {
+        action: CardAction,
+    },
+    StartGame,
+    FinishGame,
+    /// Sends a chat message or emote to everybody at the table
+    Chat(ChatContent),
+    /// Does nothing but get acknowledged (e.g. for checking the connection)
+    Ping,
+}
+
+/// Category of a failed command, corresponding to the HTTP status codes
+/// returned by the REST API.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandErrorKind {
+    Unauthorized,
+    NotFound,
+    BadRequest,
+    Conflict,
+    /// Too many requests in a short time (e.g. chat messages)
+    RateLimited,
+    Internal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CommandError {
+    pub kind: CommandErrorKind,
+    #[serde(default)]
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.kind, self.message)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClientCommand, Command};
+    use serde_json::json;
+
+    #[test]
+    fn test_command_json() {
+        let command = ClientCommand {
+            request_id: 7,
+            command: Command::PlayCard { card_index: 2 },
+        };
+        let command_json =
+            json!({ "request_id": 7, "command": { "PlayCard": { "card_index": 2 } } });
+        assert_eq!(serde_json::to_value(&command).unwrap(), command_json);
+        assert_eq!(
+            serde_json::from_value::<ClientCommand>(command_json).unwrap(),
+            command
+        );
+
+        let ping: ClientCommand =
+            serde_json::from_value(json!({ "request_id": 8, "command": "Ping" })).unwrap();
+        assert_eq!(ping.command, Command::Ping);
+    }
+}
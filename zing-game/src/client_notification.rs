@@ -1,9 +1,59 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{card_action::CardAction, game::GameState};
+use crate::{
+    card_action::CardAction, chat::ChatMessage, client_command::CommandError, game::GameState,
+    zing_game::MoveHint,
+};
+
+/// Version of the notification protocol; clients announce the version they
+/// speak when connecting to a table, and every notification carries it.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Envelope of every notification sent over a table WebSocket.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NotificationMessage<N = ClientNotification> {
+    pub version: u32,
+    /// Increases with every notification sent at the table (a connection may
+    /// see gaps, e.g. for notifications sent to other users only).  After
+    /// reconnecting, clients can resume from the last sequence number they
+    /// received in order to get only the card actions they missed.
+    pub seq: u64,
+    pub notification: N,
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ClientNotification {
+    /// Game state as visible to the receiving client, the player displayed in
+    /// front (the client's own position, or 0 for spectators) and the player
+    /// at turn.
     GameStatus(GameState, usize, Option<usize>),
     CardActions(Vec<CardAction>, Option<usize>),
+    /// Total match points per team and the winning team (if decided), sent
+    /// after each finished game.
+    MatchStatus((u32, u32), Option<usize>),
+    /// Time limit for the current turn, sent whenever a new turn begins at a
+    /// table with turn time limits (None if no timer is running).
+    TurnTimer(Option<TurnTimer>),
+    /// Cards the receiving player may play and their outcomes, sent to the
+    /// player at turn if the table allows move hints.
+    MoveHints(Vec<MoveHint>),
+    /// Acknowledgement of the [ClientCommand](crate::client_command::ClientCommand)
+    /// with the given request id, or the reason why it failed.
+    CommandResult(u64, Result<(), CommandError>),
+    /// New chat message or emote at the table
+    Chat(ChatMessage),
+    /// Recent chat messages (oldest first), sent when connecting to a table
+    ChatHistory(Vec<ChatMessage>),
+}
+
+/// Deadline for the current player, after which the server plays a card for
+/// them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TurnTimer {
+    pub player: usize,
+    /// Unix time in milliseconds
+    pub deadline: u64,
+    /// Time left when the notification was sent (useful if the client's clock
+    /// is not in sync with the server)
+    pub remaining_millis: u64,
 }
@@ -1,5 +1,6 @@
-use rand::rng;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use rand::{rng, SeedableRng};
 use strum::IntoEnumIterator;
 
 use crate::cards::{Back, Card, Rank, Suit};
@@ -24,9 +25,17 @@ pub fn shuffled_deck(backs: Back) -> Vec<Card> {
     result
 }
 
+/// Returns a [deck] shuffled deterministically, i.e. the same seed will
+/// always produce the same order of cards.
+pub fn shuffled_deck_with_seed(backs: Back, seed: u64) -> Vec<Card> {
+    let mut result = deck(backs);
+    result.shuffle(&mut StdRng::seed_from_u64(seed));
+    result
+}
+
 #[cfg(test)]
 mod tests {
-    use super::deck;
+    use super::{deck, shuffled_deck_with_seed};
 
     #[test]
     fn test_deck() {
@@ -40,4 +49,18 @@ mod tests {
         assert_eq!(first.suit, crate::Suit::Diamonds);
         assert_eq!(first.rank, crate::Rank::Two);
     }
+
+    #[test]
+    fn test_shuffled_deck_with_seed() {
+        let short_strs = |seed| -> Vec<String> {
+            shuffled_deck_with_seed(crate::Back::Blue, seed)
+                .iter()
+                .map(|card| card.short_str())
+                .collect()
+        };
+
+        assert_eq!(short_strs(42).len(), 52);
+        assert_eq!(short_strs(42), short_strs(42));
+        assert_ne!(short_strs(42), short_strs(43));
+    }
 }
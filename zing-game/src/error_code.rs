@@ -0,0 +1,143 @@
+//! Machine-readable error codes shared by the REST API (as part of the JSON
+//! [ErrorBody] of failed requests) and the table WebSocket (in
+//! [CommandError](crate::client_command::CommandError)s).  Clients should
+//! decide what to do based on the code, not on the human-readable message,
+//! which may change at any time.
+
+use serde::{Deserialize, Serialize};
+
+use crate::client_command::CommandErrorKind;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    // authentication
+    NotLoggedIn,
+    SessionExpired,
+    InvalidCredentials,
+    NotTableOwner,
+
+    // things that do not exist
+    NotFound,
+    TableNotFound,
+    NotAtTable,
+    UserNotFound,
+    BotNotFound,
+    NoActiveGame,
+    GameNotFound,
+    ReplayNotAvailable,
+
+    // invalid input
+    InvalidRequest,
+    InvalidName,
+    PasswordTooShort,
+    InvalidTurnTimeLimit,
+    UnknownAiStrategy,
+    InvalidChatMessage,
+    InvalidReplay,
+    UnsupportedProtocolVersion,
+    IllegalAction,
+
+    // not possible in the current state of the table or game
+    NameTaken,
+    AlreadyRegistered,
+    AlreadyJoined,
+    TableFull,
+    GameAlreadyStarted,
+    GameNotStarted,
+    GameStillRunning,
+    GameFinished,
+    InvalidPlayerCount,
+    MatchDecided,
+    NotYourTurn,
+    InvalidCardIndex,
+    TakeBacksNotAllowed,
+    NothingToTakeBack,
+    MoveHintsDisabled,
+
+    RateLimited,
+
+    DatabaseError,
+    InternalError,
+    /// code introduced by a newer server version (or missing because of an
+    /// older one)
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+impl ErrorCode {
+    /// Category of the error, which determines the HTTP status code.
+    pub fn kind(&self) -> CommandErrorKind {
+        use ErrorCode::*;
+        match self {
+            NotLoggedIn | SessionExpired | InvalidCredentials | NotTableOwner => {
+                CommandErrorKind::Unauthorized
+            }
+            NotFound | TableNotFound | NotAtTable | UserNotFound | BotNotFound | NoActiveGame
+            | GameNotFound | ReplayNotAvailable => CommandErrorKind::NotFound,
+            InvalidRequest
+            | InvalidName
+            | PasswordTooShort
+            | InvalidTurnTimeLimit
+            | UnknownAiStrategy
+            | InvalidChatMessage
+            | InvalidReplay
+            | UnsupportedProtocolVersion
+            | IllegalAction => CommandErrorKind::BadRequest,
+            NameTaken | AlreadyRegistered | AlreadyJoined | TableFull | GameAlreadyStarted
+            | GameNotStarted | GameStillRunning | GameFinished | InvalidPlayerCount
+            | MatchDecided | NotYourTurn | InvalidCardIndex | TakeBacksNotAllowed
+            | NothingToTakeBack | MoveHintsDisabled => CommandErrorKind::Conflict,
+            RateLimited => CommandErrorKind::RateLimited,
+            DatabaseError | InternalError | Unknown => CommandErrorKind::Internal,
+        }
+    }
+}
+
+/// JSON body of failed REST requests.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+    /// id of the request (also sent in the `x-request-id` header), for
+    /// finding it in the server logs
+    pub request_id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorBody, ErrorCode};
+    use crate::client_command::CommandErrorKind;
+    use serde_json::json;
+
+    #[test]
+    fn test_error_code_json() {
+        assert_eq!(
+            serde_json::to_value(ErrorCode::NotYourTurn).unwrap(),
+            json!("NOT_YOUR_TURN")
+        );
+        assert_eq!(
+            serde_json::from_value::<ErrorCode>(json!("TABLE_FULL")).unwrap(),
+            ErrorCode::TableFull
+        );
+        // codes added in the future must not break older clients
+        assert_eq!(
+            serde_json::from_value::<ErrorCode>(json!("SOMETHING_NEW")).unwrap(),
+            ErrorCode::Unknown
+        );
+        assert_eq!(
+            ErrorCode::GameAlreadyStarted.kind(),
+            CommandErrorKind::Conflict
+        );
+
+        let body: ErrorBody = serde_json::from_value(json!({
+            "code": "TABLE_NOT_FOUND",
+            "message": "table id not found",
+            "request_id": "abc",
+        }))
+        .unwrap();
+        assert_eq!(body.code, ErrorCode::TableNotFound);
+        assert_eq!(body.request_id.as_deref(), Some("abc"));
+    }
+}
@@ -1,6 +1,7 @@
 use bevy_reflect::Reflect;
 use serde::{Deserialize, Serialize};
 
+use crate::card_action::CardLocation;
 use crate::Card;
 
 /// Represents a named player.
@@ -24,7 +25,7 @@ impl Player {
 
 /// Represents a single card (without position) with front and back side and
 /// whether it is face up or face down.
-#[derive(Clone, Debug, Serialize, Deserialize, Reflect)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Reflect)]
 pub struct CardState {
     pub card: Card,
     pub face_up: bool,
@@ -123,10 +124,73 @@ pub enum GamePhase {
     Finished,
 }
 
+/// Reason why a [CardAction](crate::card_action::CardAction) cannot be set
+/// up or applied to a [GameState], or why a game cannot be set up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CardGameError {
-    DrawingStackEmpty,
+    /// There is no player (for hands) or stack with the given index.
+    InvalidStackIndex(CardLocation, usize),
+    /// The hand or stack with the given index has no card at the given
+    /// position (or the position is given twice).
+    InvalidCardIndex(CardLocation, usize, usize),
+    /// The stack with the given index contains fewer cards than should be
+    /// drawn from it.
+    DrawingStackEmpty(usize),
+    /// The action does not move any cards.
+    EmptySource,
+    /// The numbers of source card indices and of destination card indices
+    /// (or card states) differ.
+    MismatchedIndexCounts(usize, usize),
+    /// Source or destination of the action have not been set up.
+    IncompleteAction,
+    /// The action cannot be inverted, since the states of the moved cards
+    /// have not been remembered.
+    CardStatesNotRemembered,
+    /// The game cannot be played by the given number of players.
+    InvalidPlayerCount(usize),
+    /// The deck does not have the number of cards the game requires.
+    InvalidDeckSize(usize),
+    /// The operation is not possible in the current phase of the game.
+    WrongPhase(GamePhase),
 }
 
+impl std::fmt::Display for CardGameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CardGameError::InvalidStackIndex(location, index) => {
+                write!(f, "invalid {location:?} index {index}")
+            }
+            CardGameError::InvalidCardIndex(location, index, card_index) => {
+                write!(
+                    f,
+                    "invalid card index {card_index} for {location:?} {index}"
+                )
+            }
+            CardGameError::DrawingStackEmpty(stack) => {
+                write!(f, "not enough cards on stack {stack}")
+            }
+            CardGameError::EmptySource => write!(f, "action does not move any cards"),
+            CardGameError::MismatchedIndexCounts(source, dest) => write!(
+                f,
+                "action moves {source} cards, but has {dest} destination indices or card states"
+            ),
+            CardGameError::IncompleteAction => {
+                write!(f, "action source or destination not set up")
+            }
+            CardGameError::CardStatesNotRemembered => {
+                write!(f, "cannot invert action without remembered card states")
+            }
+            CardGameError::InvalidPlayerCount(count) => {
+                write!(f, "game cannot be played by {count} players")
+            }
+            CardGameError::InvalidDeckSize(size) => write!(f, "invalid deck size {size}"),
+            CardGameError::WrongPhase(phase) => write!(f, "not possible in game phase {phase:?}"),
+        }
+    }
+}
+
+impl std::error::Error for CardGameError {}
+
 impl GameState {
     pub fn player_count(&self) -> usize {
         self.players.len()
@@ -1,9 +1,16 @@
 pub mod card_action;
 pub mod cards;
+pub mod chat;
+pub mod client_command;
 pub mod client_notification;
 pub mod decks;
+pub mod error_code;
 pub mod game;
+pub mod rating;
+pub mod replay;
 pub mod zing_ai;
 pub mod zing_game;
+pub mod zing_match;
+pub mod zing_mcts;
 
 pub use cards::{Back, Card, Rank, Suit};
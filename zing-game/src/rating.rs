@@ -0,0 +1,71 @@
+//! Elo ratings of players, updated after each finished game.  In four player
+//! games, each team is rated with the average rating of its two players, and
+//! both players' ratings change by the same amount.
+
+/// Rating of players who have not played any rated game yet
+pub const INITIAL_RATING: f64 = 1500.;
+
+/// Maximum rating change per game
+pub const K_FACTOR: f64 = 32.;
+
+/// Expected score (between 0 and 1) of a team with the given rating against
+/// an opponent team.
+pub fn expected_score(rating: f64, opponent_rating: f64) -> f64 {
+    1. / (1. + 10f64.powf((opponent_rating - rating) / 400.))
+}
+
+/// Rating changes of both teams after a game, given the team ratings and the
+/// winning team (None for a draw).
+pub fn rating_changes(team_ratings: (f64, f64), winner: Option<usize>) -> (f64, f64) {
+    let score0 = match winner {
+        Some(0) => 1.,
+        Some(_) => 0.,
+        None => 0.5,
+    };
+    let change0 = K_FACTOR * (score0 - expected_score(team_ratings.0, team_ratings.1));
+    (change0, -change0)
+}
+
+/// Rating of a team, given the ratings of its players.
+pub fn team_rating(player_ratings: &[f64]) -> f64 {
+    if player_ratings.is_empty() {
+        return INITIAL_RATING;
+    }
+    player_ratings.iter().sum::<f64>() / player_ratings.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expected_score, rating_changes, team_rating, INITIAL_RATING, K_FACTOR};
+
+    #[test]
+    fn test_rating_changes() {
+        assert_eq!(expected_score(INITIAL_RATING, INITIAL_RATING), 0.5);
+        assert!(expected_score(1800., 1400.) > 0.9);
+
+        // equally rated players win or lose half of the K factor
+        assert_eq!(
+            rating_changes((INITIAL_RATING, INITIAL_RATING), Some(0)),
+            (K_FACTOR / 2., -K_FACTOR / 2.)
+        );
+        assert_eq!(
+            rating_changes((INITIAL_RATING, INITIAL_RATING), Some(1)),
+            (-K_FACTOR / 2., K_FACTOR / 2.)
+        );
+        assert_eq!(
+            rating_changes((INITIAL_RATING, INITIAL_RATING), None),
+            (0., 0.)
+        );
+
+        // beating a much weaker opponent gains little, losing costs much
+        let (win, _) = rating_changes((1800., 1400.), Some(0));
+        let (loss, _) = rating_changes((1800., 1400.), Some(1));
+        assert!(win > 0. && win < 4.);
+        assert!(loss < -28.);
+        // a draw against a weaker opponent costs rating
+        assert!(rating_changes((1800., 1400.), None).0 < 0.);
+
+        assert_eq!(team_rating(&[1400., 1600.]), 1500.);
+        assert_eq!(team_rating(&[]), INITIAL_RATING);
+    }
+}
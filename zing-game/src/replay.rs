@@ -0,0 +1,370 @@
+//! Stable file format for recording games and matches, e.g. for sharing them
+//! or watching them again later.
+//!
+//! A replay contains everything needed to re-create a game: the player
+//! names, dealer, the deck in its original order, and the sequence of moves.
+//! The resulting card actions are included as well, so that replays can be
+//! watched without re-implementing the rules, but they are never trusted: a
+//! replay is only accepted if playing its moves through [ZingGame] produces
+//! exactly the recorded actions (and points).
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::card_action::CardAction;
+use crate::decks::deck;
+use crate::zing_game::{ZingGame, ZingGamePoints, ZingMove};
+use crate::zing_match::ZingMatch;
+use crate::{Back, Card};
+
+/// Version of the replay format written by this version of the library.  It
+/// must be increased whenever the format changes incompatibly.
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The replay file could not be parsed.
+    Format(String),
+    /// The replay was written in a format version that is not supported.
+    UnsupportedVersion(u32),
+    /// Players, dealer or deck are not valid for a game of Zing.
+    InvalidSetup(&'static str),
+    /// The move with the given index could not be performed.
+    InvalidMove(usize, &'static str),
+    /// Replaying the moves does not lead to the recorded result.
+    Mismatch(&'static str),
+    /// The game with the given index of a match replay is invalid.
+    InvalidGame(usize, Box<ReplayError>),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Format(msg) => write!(f, "invalid replay file: {msg}"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "unsupported replay format version {version}")
+            }
+            ReplayError::InvalidSetup(msg) => write!(f, "invalid game setup: {msg}"),
+            ReplayError::InvalidMove(index, msg) => write!(f, "invalid move #{index}: {msg}"),
+            ReplayError::Mismatch(msg) => write!(f, "replay does not match game: {msg}"),
+            ReplayError::InvalidGame(index, error) => write!(f, "game #{index}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+fn check_version(version: u32) -> Result<(), ReplayError> {
+    if version != REPLAY_FORMAT_VERSION {
+        return Err(ReplayError::UnsupportedVersion(version));
+    }
+    Ok(())
+}
+
+/// Replay of a single game.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameReplay {
+    pub version: u32,
+    pub player_names: Vec<String>,
+    pub dealer: usize,
+    /// deck before dealing; cards are dealt from the end (see
+    /// [ZingGame::new_with_deck])
+    pub deck: Vec<Card>,
+    pub moves: Vec<ZingMove>,
+    /// all card actions resulting from dealing and the moves (see
+    /// [ZingGame::history])
+    pub actions: Vec<CardAction>,
+    /// final points, if the game was finished
+    pub points: Option<ZingGamePoints>,
+}
+
+impl GameReplay {
+    /// Records the given game (which may still be running).  Fails for games
+    /// stored before the initial deck was remembered.
+    pub fn new(game: &ZingGame) -> Result<Self, &'static str> {
+        if game.initial_deck().is_empty() {
+            return Err("initial deck of game is unknown");
+        }
+        Ok(Self {
+            version: REPLAY_FORMAT_VERSION,
+            player_names: game
+                .state()
+                .players
+                .iter()
+                .map(|player| player.name.clone())
+                .collect(),
+            dealer: game.dealer(),
+            deck: game.initial_deck().clone(),
+            moves: game.moves().clone(),
+            actions: game.history().clone(),
+            points: game.finished().then(|| game.points()),
+        })
+    }
+
+    /// Parses and [verifies](GameReplay::verify) a replay.
+    pub fn from_json(json: &str) -> Result<Self, ReplayError> {
+        let result: Self =
+            serde_json::from_str(json).map_err(|err| ReplayError::Format(err.to_string()))?;
+        result.verify()?;
+        Ok(result)
+    }
+
+    /// Re-creates the game by dealing the recorded deck and performing all
+    /// moves, checking that this leads to exactly the recorded actions and
+    /// points.
+    pub fn verify(&self) -> Result<ZingGame, ReplayError> {
+        check_version(self.version)?;
+
+        let player_count = self.player_names.len();
+        if player_count != 2 && player_count != 4 {
+            return Err(ReplayError::InvalidSetup(
+                "Zing can only be played by two or four players",
+            ));
+        }
+        if self.dealer >= player_count {
+            return Err(ReplayError::InvalidSetup("invalid dealer"));
+        }
+        let full_deck = self.deck.len() == 52
+            && deck(Back::Blue).iter().all(|expected| {
+                self.deck
+                    .iter()
+                    .any(|card| card.rank == expected.rank && card.suit == expected.suit)
+            });
+        if !full_deck {
+            return Err(ReplayError::InvalidSetup(
+                "deck must contain each card exactly once",
+            ));
+        }
+
+        let mut game =
+            ZingGame::new_with_deck(self.player_names.clone(), self.dealer, self.deck.clone())
+                .map_err(|_| ReplayError::InvalidSetup("game could not be set up"))?;
+        game.setup_game()
+            .map_err(|_| ReplayError::InvalidSetup("cards could not be dealt"))?;
+
+        for (index, zing_move) in self.moves.iter().enumerate() {
+            match *zing_move {
+                ZingMove::PlayCard {
+                    player,
+                    card_index,
+                    card,
+                } => {
+                    let played_card = game
+                        .state()
+                        .players
+                        .get(player)
+                        .and_then(|player| player.hand.get(card_index))
+                        .map(|card_state| card_state.card);
+                    if played_card != Some(card) {
+                        return Err(ReplayError::InvalidMove(
+                            index,
+                            "card is not in player's hand",
+                        ));
+                    }
+                    game.play_card(player, card_index)
+                        .map_err(|err| ReplayError::InvalidMove(index, err.message()))?;
+                }
+                ZingMove::TakeBack => {
+                    game.undo_last_turn()
+                        .map_err(|err| ReplayError::InvalidMove(index, err.message()))?;
+                }
+            }
+        }
+
+        if game.history() != &self.actions {
+            return Err(ReplayError::Mismatch("actions differ"));
+        }
+        if game.finished().then(|| game.points()) != self.points {
+            return Err(ReplayError::Mismatch("points differ"));
+        }
+
+        Ok(game)
+    }
+}
+
+/// Replay of all finished games of a match.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchReplay {
+    pub version: u32,
+    pub games: Vec<GameReplay>,
+}
+
+impl MatchReplay {
+    pub fn new(games: Vec<GameReplay>) -> Self {
+        Self {
+            version: REPLAY_FORMAT_VERSION,
+            games,
+        }
+    }
+
+    /// Parses and [verifies](MatchReplay::verify) a replay.
+    pub fn from_json(json: &str) -> Result<Self, ReplayError> {
+        let result: Self =
+            serde_json::from_str(json).map_err(|err| ReplayError::Format(err.to_string()))?;
+        result.verify()?;
+        Ok(result)
+    }
+
+    /// Verifies all games (see [GameReplay::verify]) and that they form a
+    /// match, i.e. they are finished, played by the same players with
+    /// rotating dealers, and no game was played after the match had been
+    /// decided.  Returns the resulting match.
+    pub fn verify(&self) -> Result<ZingMatch, ReplayError> {
+        check_version(self.version)?;
+
+        let mut zing_match = ZingMatch::new();
+        for (index, game_replay) in self.games.iter().enumerate() {
+            let invalid_game = |error| ReplayError::InvalidGame(index, Box::new(error));
+
+            let game = game_replay.verify().map_err(invalid_game)?;
+            if game_replay.player_names != self.games[0].player_names {
+                return Err(invalid_game(ReplayError::InvalidSetup(
+                    "players differ from first game",
+                )));
+            }
+            if game.dealer() != zing_match.next_dealer(game_replay.player_names.len()) {
+                return Err(invalid_game(ReplayError::InvalidSetup("wrong dealer")));
+            }
+            if !game.finished() {
+                return Err(invalid_game(ReplayError::Mismatch("game is not finished")));
+            }
+            zing_match
+                .add_game_result(game.points())
+                .map_err(|msg| invalid_game(ReplayError::InvalidSetup(msg)))?;
+        }
+
+        Ok(zing_match)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameReplay, MatchReplay, ReplayError, REPLAY_FORMAT_VERSION};
+    use crate::zing_game::{ZingGame, ZingMove};
+    use crate::zing_match::ZingMatch;
+
+    fn names() -> Vec<String> {
+        vec!["Alice".into(), "Bob".into()]
+    }
+
+    fn play_game(game: &mut ZingGame) {
+        game.setup_game().unwrap();
+        while let Some(player) = game.current_player() {
+            let hand_size = game.state().players[player].hand.len();
+            game.play_card(player, game.turn() % hand_size).unwrap();
+            if game.turn() == 5 {
+                game.undo_last_turn().unwrap();
+                game.play_card(player, 0).unwrap();
+            }
+        }
+    }
+
+    fn finished_game_replay() -> GameReplay {
+        let mut game = ZingGame::new_with_seed(names(), 1, 42).unwrap();
+        play_game(&mut game);
+        GameReplay::new(&game).unwrap()
+    }
+
+    #[test]
+    fn test_game_replay_roundtrip() {
+        let replay = finished_game_replay();
+        assert!(replay.points.is_some());
+        assert!(replay.moves.contains(&ZingMove::TakeBack));
+
+        let json = serde_json::to_string(&replay).unwrap();
+        let imported = GameReplay::from_json(&json).unwrap();
+        assert_eq!(imported, replay);
+
+        let game = imported.verify().unwrap();
+        assert_eq!(Some(game.points()), replay.points);
+    }
+
+    #[test]
+    fn test_running_game_replay() {
+        let mut game = ZingGame::new_with_seed(names(), 0, 7).unwrap();
+        game.setup_game().unwrap();
+        game.play_card(1, 2).unwrap();
+        let replay = GameReplay::new(&game).unwrap();
+        assert_eq!(replay.points, None);
+        assert_eq!(replay.verify().unwrap().turn(), 1);
+    }
+
+    #[test]
+    fn test_modified_replays_are_rejected() {
+        let replay = finished_game_replay();
+
+        let mut modified = replay.clone();
+        modified.version += 1;
+        assert_eq!(
+            modified.verify().err().unwrap(),
+            ReplayError::UnsupportedVersion(REPLAY_FORMAT_VERSION + 1)
+        );
+
+        let mut modified = replay.clone();
+        modified.deck.swap(0, 51);
+        assert!(modified.verify().is_err());
+
+        let mut modified = replay.clone();
+        modified.deck[0] = modified.deck[1];
+        assert!(matches!(
+            modified.verify(),
+            Err(ReplayError::InvalidSetup(_))
+        ));
+
+        let mut modified = replay.clone();
+        modified.moves.swap(0, 1);
+        assert!(matches!(
+            modified.verify(),
+            Err(ReplayError::InvalidMove(0, _))
+        ));
+
+        let mut modified = replay.clone();
+        modified.actions.pop();
+        assert_eq!(
+            modified.verify().err().unwrap(),
+            ReplayError::Mismatch("actions differ")
+        );
+
+        let mut modified = replay.clone();
+        modified.points.as_mut().unwrap().zing_points.0 += 10;
+        assert_eq!(
+            modified.verify().err().unwrap(),
+            ReplayError::Mismatch("points differ")
+        );
+
+        assert!(matches!(
+            GameReplay::from_json("{\"version\": 1}"),
+            Err(ReplayError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn test_match_replay() {
+        let mut zing_match = ZingMatch::new();
+        let mut games = Vec::new();
+        for seed in 0..3 {
+            let dealer = zing_match.next_dealer(2);
+            let mut game = ZingGame::new_with_seed(names(), dealer, seed).unwrap();
+            play_game(&mut game);
+            zing_match.add_game_result(game.points()).unwrap();
+            games.push(GameReplay::new(&game).unwrap());
+        }
+
+        let replay = MatchReplay::new(games);
+        let json = serde_json::to_string(&replay).unwrap();
+        let imported = MatchReplay::from_json(&json).unwrap();
+        assert_eq!(
+            imported.verify().unwrap().game_results(),
+            zing_match.game_results()
+        );
+
+        // dealers have to rotate
+        let mut modified = replay.clone();
+        modified.games.swap(0, 1);
+        assert!(matches!(
+            modified.verify(),
+            Err(ReplayError::InvalidGame(0, _))
+        ));
+    }
+}
@@ -1,10 +1,169 @@
-use crate::zing_game::ZingGame;
+use crate::{
+    zing_game::ZingGame,
+    zing_mcts::{MonteCarloPlayer, SearchBudget},
+    Card, Rank,
+};
 use rand::{rng, Rng};
 
+/// Computer player for Zing.  Implementations only have to choose which card
+/// to play; they should base that decision on [PlayerKnowledge] only, so that
+/// they do not cheat by looking at other players' hands.
 pub trait ZingAI {
-    fn auto_play(&self, game: &mut ZingGame);
+    /// Index of the player this AI plays for.
+    fn player_index(&self) -> usize;
+
+    /// Returns the index of the card (within the own hand) that should be
+    /// played next.  Must only be called when it's our turn.
+    fn choose_card(&self, game: &ZingGame) -> usize;
+
+    fn auto_play(&self, game: &mut ZingGame) {
+        let card_index = self.choose_card(game);
+        game.play_card(self.player_index(), card_index)
+            .expect("auto_play() assumes that it's our turn and selects a valid card index");
+    }
 }
 
+/// Names of the built-in AI strategies, see [ai_by_name].
+pub const AI_NAMES: [&str; 4] = ["random", "greedy", "heuristic", "mcts"];
+
+/// Creates one of the built-in AI strategies listed in [AI_NAMES] for the
+/// given player.
+pub fn ai_by_name(name: &str, player_index: usize) -> Option<Box<dyn ZingAI + Send + Sync>> {
+    match name {
+        "random" => Some(Box::new(RandomPlayer::new(player_index))),
+        "greedy" => Some(Box::new(GreedyPlayer::new(player_index))),
+        "heuristic" => Some(Box::new(HeuristicPlayer::new(player_index))),
+        "mcts" => Some(Box::new(MonteCarloPlayer::new(
+            player_index,
+            SearchBudget::Iterations(MonteCarloPlayer::DEFAULT_ITERATIONS),
+            rng().random(),
+        ))),
+        _ => None,
+    }
+}
+
+/// Everything a player can legally know about a running game: the own hand,
+/// the cards on the table, the dealer's revealed bottom card, and all cards
+/// that have been seen face up so far.
+pub struct PlayerKnowledge {
+    pub player_index: usize,
+    pub player_count: usize,
+    pub hand: Vec<Card>,
+    /// cards on the table (the last one is the relevant top card)
+    pub table: Vec<Card>,
+    /// revealed bottom card of the stock, which the dealer will get last
+    pub dealer_bottom_card: Option<Card>,
+    /// number of cards left in the stock
+    pub stock_size: usize,
+    /// number of cards of each rank (indexed by `Rank as usize`) that we have
+    /// not seen yet, i.e. that are on other players' hands or in the stock
+    pub unseen_ranks: [u8; 13],
+}
+
+impl PlayerKnowledge {
+    pub fn new(game: &ZingGame, player_index: usize) -> Self {
+        let view = game.state().new_view_for_player(player_index);
+        let hand: Vec<Card> = view.players[player_index]
+            .hand
+            .iter()
+            .map(|card_state| card_state.card)
+            .collect();
+        let table: Vec<Card> = view.stacks[1]
+            .cards
+            .iter()
+            .map(|card_state| card_state.card)
+            .collect();
+        let dealer_bottom_card = view.stacks[0]
+            .cards
+            .first()
+            .filter(|card_state| card_state.face_up)
+            .map(|card_state| card_state.card);
+
+        // every card that has ever been visible to us has been face up in
+        // some action from our point of view (cards dealt to us, played
+        // cards, initial table cards, revealed bottom card)
+        let mut seen: Vec<Card> = Vec::new();
+        // (all actions in the history have been applied, i.e. set up)
+        for view in game
+            .history()
+            .iter()
+            .filter_map(|action| action.new_view_for_player(player_index).ok())
+        {
+            seen.extend(
+                view.resulting_card_states
+                    .iter()
+                    .filter(|card_state| card_state.face_up)
+                    .map(|card_state| card_state.card),
+            );
+        }
+        // some cards (e.g. Jacks put below the stock, or cards played from
+        // our hand) are moved several times; only count every card once
+        seen.sort_by_key(|card| (card.suit as usize, card.rank as usize));
+        seen.dedup_by_key(|card| (card.suit as usize, card.rank as usize));
+
+        let mut unseen_ranks = [4u8; 13];
+        for card in seen {
+            unseen_ranks[card.rank as usize] -= 1;
+        }
+
+        Self {
+            player_index,
+            player_count: view.player_count(),
+            hand,
+            table,
+            dealer_bottom_card,
+            stock_size: view.stacks[0].cards.len(),
+            unseen_ranks,
+        }
+    }
+
+    pub fn top_card(&self) -> Option<&Card> {
+        self.table.last()
+    }
+
+    /// Returns true if playing the given card would win the cards on the table.
+    pub fn takes_trick(&self, card: &Card) -> bool {
+        match self.top_card() {
+            None => false,
+            Some(top_card) => top_card.rank == card.rank || card.rank == Rank::Jack,
+        }
+    }
+
+    /// Number of cards of the given rank that another player might still play
+    /// onto it.
+    pub fn unseen(&self, rank: Rank) -> u8 {
+        self.unseen_ranks[rank as usize]
+    }
+
+    /// Value of winning the table with the given card, including a possible
+    /// Zing bonus.
+    pub fn trick_value(&self, card: &Card) -> f32 {
+        let card_points: u32 = self
+            .table
+            .iter()
+            .chain(std::iter::once(card))
+            .map(ZingGame::card_points)
+            .sum();
+        // every captured card helps towards the three bonus points for the
+        // majority of cards (roughly 26 cards needed)
+        let card_count_value = 3. * (self.table.len() + 1) as f32 / 26.;
+
+        let zing_bonus = match self.table.as_slice() {
+            [top_card] if top_card.rank == card.rank => {
+                if card.rank == Rank::Jack {
+                    20.
+                } else {
+                    10.
+                }
+            }
+            _ => 0.,
+        };
+
+        card_points as f32 + card_count_value + zing_bonus
+    }
+}
+
+/// Plays a random card from the hand.
 pub struct RandomPlayer {
     player_index: usize,
 }
@@ -16,11 +175,227 @@ impl RandomPlayer {
 }
 
 impl ZingAI for RandomPlayer {
-    fn auto_play(&self, game: &mut ZingGame) {
-        game.play_card(
-            self.player_index,
-            rng().random_range(0..game.state().players[self.player_index].hand.len()),
-        )
-        .expect("auto_play() assumes that it's our turn and selects a valid card index");
+    fn player_index(&self) -> usize {
+        self.player_index
+    }
+
+    fn choose_card(&self, game: &ZingGame) -> usize {
+        rng().random_range(0..game.state().players[self.player_index].hand.len())
+    }
+}
+
+/// Takes the most valuable trick whenever possible, otherwise discards the
+/// card with the fewest points (never a Jack, if avoidable).
+pub struct GreedyPlayer {
+    player_index: usize,
+}
+
+impl GreedyPlayer {
+    pub fn new(player_index: usize) -> Self {
+        Self { player_index }
+    }
+}
+
+impl ZingAI for GreedyPlayer {
+    fn player_index(&self) -> usize {
+        self.player_index
+    }
+
+    fn choose_card(&self, game: &ZingGame) -> usize {
+        let knowledge = PlayerKnowledge::new(game, self.player_index);
+        best_card(&knowledge, |card| {
+            if knowledge.takes_trick(card) {
+                100. + knowledge.trick_value(card)
+            } else {
+                let jack_penalty = if card.rank == Rank::Jack { 10. } else { 0. };
+                -(ZingGame::card_points(card) as f32) - jack_penalty
+            }
+        })
+    }
+}
+
+/// Evaluates every card by the expected point difference it leads to, using
+/// card counting to estimate the risk of giving away tricks and Zings:
+/// * takes tricks when possible (preferring a matching card over a Jack),
+/// * avoids leaving a lone card on the table that an opponent may Zing,
+/// * holds back Jacks for valuable tricks.
+pub struct HeuristicPlayer {
+    player_index: usize,
+}
+
+impl HeuristicPlayer {
+    pub fn new(player_index: usize) -> Self {
+        Self { player_index }
+    }
+
+    /// Heuristic value of a Jack on our hand (it can take any future trick).
+    const JACK_VALUE: f32 = 3.;
+
+    fn card_value(knowledge: &PlayerKnowledge, card: &Card) -> f32 {
+        if knowledge.takes_trick(card) {
+            let mut value = knowledge.trick_value(card);
+            if card.rank == Rank::Jack && knowledge.top_card().unwrap().rank != Rank::Jack {
+                value -= Self::JACK_VALUE;
+            }
+            // after taking a trick, the next player has to play onto an empty
+            // table, which is an advantage for us
+            return value + 1.;
+        }
+
+        // the card stays on the table; estimate what the next player may gain
+        let unseen_total: u8 = knowledge.unseen_ranks.iter().sum();
+        let probability = |rank: Rank| -> f32 {
+            if unseen_total == 0 {
+                return 0.;
+            }
+            // chance that an opponent holds (at least) one of the unseen cards
+            let opponent_cards = 4. * (knowledge.player_count / 2) as f32;
+            let unseen = f32::from(knowledge.unseen(rank));
+            (unseen * opponent_cards / f32::from(unseen_total)).min(1.)
+        };
+
+        let mut table_after = knowledge.table.clone();
+        table_after.push(*card);
+        let table_points: u32 = table_after.iter().map(ZingGame::card_points).sum();
+        let table_value = table_points as f32 + 3. * table_after.len() as f32 / 26.;
+
+        let match_probability = probability(card.rank);
+        let jack_probability = if card.rank == Rank::Jack {
+            0.
+        } else {
+            probability(Rank::Jack)
+        };
+
+        let zing_risk = if knowledge.table.is_empty() {
+            match_probability * if card.rank == Rank::Jack { 20. } else { 10. }
+        } else {
+            0.
+        };
+        let trick_risk = match_probability.max(jack_probability) * table_value;
+
+        let jack_cost = if card.rank == Rank::Jack {
+            Self::JACK_VALUE
+        } else {
+            0.
+        };
+
+        -(zing_risk + trick_risk + jack_cost)
+    }
+}
+
+impl ZingAI for HeuristicPlayer {
+    fn player_index(&self) -> usize {
+        self.player_index
+    }
+
+    fn choose_card(&self, game: &ZingGame) -> usize {
+        let knowledge = PlayerKnowledge::new(game, self.player_index);
+        best_card(&knowledge, |card| Self::card_value(&knowledge, card))
+    }
+}
+
+/// Returns the index of the hand card with the highest value (the first one
+/// in case of ties).
+fn best_card(knowledge: &PlayerKnowledge, value: impl Fn(&Card) -> f32) -> usize {
+    let mut best = (0, f32::NEG_INFINITY);
+    for (index, card) in knowledge.hand.iter().enumerate() {
+        let card_value = value(card);
+        if card_value > best.1 {
+            best = (index, card_value);
+        }
+    }
+    best.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ai_by_name, HeuristicPlayer, PlayerKnowledge, ZingAI, AI_NAMES};
+    use crate::zing_game::ZingGame;
+    use crate::Rank;
+
+    fn new_game(seed: u64) -> ZingGame {
+        let mut game = ZingGame::new_with_seed(vec!["A".into(), "B".into()], 1, seed).unwrap();
+        game.setup_game().unwrap();
+        game
+    }
+
+    #[test]
+    fn test_ai_by_name() {
+        for name in AI_NAMES {
+            assert_eq!(ai_by_name(name, 1).unwrap().player_index(), 1);
+        }
+        assert!(ai_by_name("unknown", 0).is_none());
+    }
+
+    #[test]
+    fn test_knowledge_counts_cards() {
+        let game = new_game(1);
+        let knowledge = PlayerKnowledge::new(&game, 0);
+        assert_eq!(knowledge.hand.len(), 4);
+        assert_eq!(knowledge.table.len(), 4);
+        assert!(knowledge.dealer_bottom_card.is_some());
+
+        // we have seen our hand, the table and the revealed bottom card
+        let unseen: u8 = knowledge.unseen_ranks.iter().sum();
+        assert_eq!(unseen, 52 - 4 - 4 - 1);
+    }
+
+    #[test]
+    fn test_heuristic_player_decisions() {
+        for seed in 0..20 {
+            let mut game = new_game(seed);
+            let players = [HeuristicPlayer::new(0), HeuristicPlayer::new(1)];
+
+            while !game.finished() {
+                let player = &players[game.current_player().unwrap()];
+                let knowledge = PlayerKnowledge::new(&game, player.player_index());
+                let card = knowledge.hand[player.choose_card(&game)];
+
+                // always takes a trick with a matching card (and prefers that
+                // over taking it with a Jack)
+                if let Some(top_card) = knowledge.top_card() {
+                    if knowledge.hand.iter().any(|card| card.rank == top_card.rank) {
+                        assert_eq!(card.rank, top_card.rank);
+                    }
+                }
+
+                // does not play a Jack onto an empty table if avoidable
+                if knowledge.table.is_empty()
+                    && knowledge.hand.iter().any(|card| card.rank != Rank::Jack)
+                {
+                    assert_ne!(card.rank, Rank::Jack);
+                }
+
+                player.auto_play(&mut game);
+            }
+        }
+    }
+
+    #[test]
+    fn test_heuristic_beats_random() {
+        let mut heuristic_points = 0;
+        let mut random_points = 0;
+        for seed in 0..100 {
+            let mut game = new_game(seed);
+            let heuristic_player = seed as usize % 2;
+            let players = [
+                ai_by_name("heuristic", heuristic_player).unwrap(),
+                ai_by_name("random", 1 - heuristic_player).unwrap(),
+            ];
+
+            while !game.finished() {
+                let current = game.current_player().unwrap();
+                players
+                    .iter()
+                    .find(|ai| ai.player_index() == current)
+                    .unwrap()
+                    .auto_play(&mut game);
+            }
+
+            let points = game.points();
+            heuristic_points += points.total_points_of_player(heuristic_player);
+            random_points += points.total_points_of_player(1 - heuristic_player);
+        }
+        assert!(2 * heuristic_points > 3 * random_points);
     }
 }
@@ -1,12 +1,13 @@
 use std::cmp::Ordering;
 
 use itertools::Itertools;
+use rand::{rng, Rng};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    card_action::{CardAction, CardLocation, CardRotation},
-    decks::shuffled_deck,
-    game::{CardState, GamePhase, GameState, StackState},
+    card_action::{ActionRules, CardAction, CardLocation, CardRotation},
+    decks::shuffled_deck_with_seed,
+    game::{CardGameError, CardState, GamePhase, GameState, StackState},
     Card, Rank, Suit,
 };
 
@@ -16,22 +17,173 @@ use crate::{
 /// of game "phases", dealers etc. can be put into GameState eventually.  For
 /// now, this is catching all the rule-specific state until a more generic
 /// version can be properly modeled.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ZingGame {
     game_state: GameState,
     /// index of player who deals/dealt cards in this game
     dealer: usize,
+    /// seed used for shuffling the deck, which makes it possible to reproduce
+    /// the game (None if the game was started with an explicitly given deck)
+    #[serde(default)]
+    seed: Option<u64>,
     /// number of cards actively played
     turn: usize,
-    /// index of the player who last scored a stack of cards
-    last_trick_winner: usize,
+    /// score stack of the team that last took a trick (None before the first
+    /// trick), which gets the cards left on the table at the end
+    #[serde(deserialize_with = "deserialize_trick_winner")]
+    last_trick_winner: Option<usize>,
     /// outlier field - this is not a Zing-specific extension, but extends
     /// [GameState] via a generic history of actions performed, so it should
     /// possibly be moved
     history: Vec<CardAction>,
+    /// actions belonging to each turn, for taking back turns
+    #[serde(default)]
+    turns: Vec<TurnRecord>,
+    /// deck in its original order, before dealing (empty for games stored
+    /// before it was recorded)
+    #[serde(default)]
+    deck: Vec<Card>,
+    /// cards played and turns taken back, in order (see [ZingGame::moves])
+    #[serde(default)]
+    moves: Vec<ZingMove>,
+    /// scoring events of the turns played so far (see [ZingGame::events])
+    #[serde(default)]
+    events: Vec<ZingEvent>,
 }
 
-#[derive(Serialize, Clone)]
+/// A single decision made by a player.  Together with the initial deck,
+/// player names and dealer, the sequence of moves fully determines a game
+/// (see [crate::replay]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ZingMove {
+    /// The given player played the card at `card_index` of their hand.
+    PlayCard {
+        player: usize,
+        card_index: usize,
+        card: Card,
+    },
+    /// The last card played was taken back (see [ZingGame::undo_last_turn]).
+    TakeBack,
+}
+
+/// Something noteworthy that happened as a consequence of a card being played
+/// in the given turn (see [ZingGame::turn]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZingEvent {
+    pub turn: usize,
+    pub kind: ZingEventKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ZingEventKind {
+    /// The player took the cards on the table by playing a Jack or a card of
+    /// the same rank as the top card (but not a Zing).
+    Trick { player: usize, card_count: usize },
+    /// The player took a single card of the same rank, worth 10 points, or 20
+    /// if both cards are Jacks.
+    Zing { player: usize, jack: bool },
+    /// After the last card was played, the cards remaining on the table went
+    /// to the team that took the last trick.
+    FinalSweep { team: usize, card_count: usize },
+    /// All hands were empty, so new cards were dealt from the stock.
+    Redeal { stock_cards: usize },
+}
+
+impl ZingEventKind {
+    /// Team that scored with this event, if any.
+    pub fn team(&self) -> Option<usize> {
+        match *self {
+            ZingEventKind::Trick { player, .. } | ZingEventKind::Zing { player, .. } => {
+                Some(ZingGame::team_of_player(player))
+            }
+            ZingEventKind::FinalSweep { team, .. } => Some(team),
+            ZingEventKind::Redeal { .. } => None,
+        }
+    }
+}
+
+/// Number of scoring events per team, e.g. for showing how the points of a
+/// game came about.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZingEventSummary {
+    /// tricks taken, not counting Zings and the final sweep
+    pub tricks: (u32, u32),
+    /// Zings that are not Jack Zings
+    pub zings: (u32, u32),
+    pub jack_zings: (u32, u32),
+}
+
+impl ZingEventSummary {
+    pub fn from_events(events: &[ZingEvent]) -> Self {
+        let mut result = Self::default();
+        for event in events {
+            let counter = match event.kind {
+                ZingEventKind::Trick { .. } => &mut result.tricks,
+                ZingEventKind::Zing { jack: false, .. } => &mut result.zings,
+                ZingEventKind::Zing { jack: true, .. } => &mut result.jack_zings,
+                ZingEventKind::FinalSweep { .. } | ZingEventKind::Redeal { .. } => continue,
+            };
+            match event.kind.team() {
+                Some(0) => counter.0 += 1,
+                _ => counter.1 += 1,
+            }
+        }
+        result
+    }
+}
+
+/// A card the player at turn may play, and what playing it would lead to (see
+/// [ZingGame::move_hints]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveHint {
+    pub card_index: usize,
+    pub card: Card,
+    pub outcome: MoveOutcome,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MoveOutcome {
+    /// The card takes the given number of cards from the table (including
+    /// itself).
+    Trick { card_count: usize },
+    /// The card takes the single card on the table as a Zing (a Jack Zing if
+    /// both are Jacks).
+    Zing { jack: bool },
+    /// The card stays alone on the table, so that the next player may take
+    /// it as a Zing.
+    OpensZing,
+    /// The card is put onto the table without taking anything.
+    Discard,
+}
+
+impl MoveOutcome {
+    /// Whether the move takes cards from the table.
+    pub fn takes_cards(&self) -> bool {
+        matches!(self, MoveOutcome::Trick { .. } | MoveOutcome::Zing { .. })
+    }
+}
+
+/// Range of [ZingGame::history] resulting from playing a single card
+/// (including all automatic actions), and the state needed to revert the turn.
+#[derive(Clone, Serialize, Deserialize)]
+struct TurnRecord {
+    first_action: usize,
+    end_action: usize,
+    #[serde(deserialize_with = "deserialize_trick_winner")]
+    last_trick_winner: Option<usize>,
+    phase: GamePhase,
+}
+
+/// Games stored before the first trick used to have the placeholder 999
+/// instead of no trick winner.
+fn deserialize_trick_winner<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<usize>::deserialize(deserializer)?.filter(|&stack| stack != 999))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct ZingGamePoints {
     pub card_points: (u32, u32),
     pub card_count_points: (u32, u32),
@@ -45,16 +197,117 @@ impl ZingGamePoints {
             self.card_points.1 + self.card_count_points.1 + self.zing_points.1,
         )
     }
+
+    /// Total points of the team the given player belongs to (see
+    /// [ZingGame::team_of_player]).
+    pub fn total_points_of_player(&self, player: usize) -> u32 {
+        let (points0, points1) = self.total_points();
+        match ZingGame::team_of_player(player) {
+            0 => points0,
+            _ => points1,
+        }
+    }
+
+    /// Returns the team that won the game (None if both have the same number
+    /// of points).
+    pub fn winner(&self) -> Option<usize> {
+        let (points0, points1) = self.total_points();
+        match points0.cmp(&points1) {
+            Ordering::Greater => Some(0),
+            Ordering::Less => Some(1),
+            Ordering::Equal => None,
+        }
+    }
+}
+
+/// Reason why a move is not possible in the current state of the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayError {
+    NotPlayersTurn,
+    InvalidCardIndex,
+    NoTurnToTakeBack,
+    /// A card action sent by a client does not describe a valid move.
+    IllegalAction(&'static str),
+    /// A resulting card action could not be performed (which indicates an
+    /// inconsistent game state).
+    InvalidAction(CardGameError),
 }
 
+impl From<CardGameError> for PlayError {
+    fn from(error: CardGameError) -> Self {
+        PlayError::InvalidAction(error)
+    }
+}
+
+impl PlayError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            PlayError::NotPlayersTurn => "not player's turn",
+            PlayError::InvalidCardIndex => "invalid card index (exceeds player's hand)",
+            PlayError::NoTurnToTakeBack => "no turn to take back",
+            PlayError::IllegalAction(reason) => reason,
+            PlayError::InvalidAction(_) => "invalid card action",
+        }
+    }
+}
+
+impl std::fmt::Display for PlayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayError::InvalidAction(error) => write!(f, "{}: {error}", self.message()),
+            _ => f.write_str(self.message()),
+        }
+    }
+}
+
+impl std::error::Error for PlayError {}
+
 impl ZingGame {
-    // TODO: names must have length 2 or eventually 4:
-    pub fn new_with_player_names(names: Vec<String>, dealer: usize) -> Self {
+    /// Start a new game with two or four players.  In four player games,
+    /// opposite players form a team, i.e. players 0 and 2 play against players
+    /// 1 and 3 (see [ZingGame::team_of_player]).
+    ///
+    /// The deck is shuffled using a random seed, which is stored with the
+    /// game (see [ZingGame::seed]).
+    pub fn new_with_player_names(names: Vec<String>, dealer: usize) -> Result<Self, CardGameError> {
+        Self::new_with_seed(names, dealer, rng().random())
+    }
+
+    /// Start a new game whose deck is shuffled deterministically using the
+    /// given seed, i.e. the same seed always leads to the same deal.
+    pub fn new_with_seed(
+        names: Vec<String>,
+        dealer: usize,
+        seed: u64,
+    ) -> Result<Self, CardGameError> {
+        let mut result = Self::new_with_deck(
+            names,
+            dealer,
+            shuffled_deck_with_seed(crate::Back::Blue, seed),
+        )?;
+        result.seed = Some(seed);
+        Ok(result)
+    }
+
+    /// Start a new game with a pre-ordered deck of 52 cards.  Cards are dealt
+    /// from the end of the deck, i.e. the last card is dealt first, and the
+    /// first card is the dealer's bottom card that is revealed.
+    pub fn new_with_deck(
+        names: Vec<String>,
+        dealer: usize,
+        deck: Vec<Card>,
+    ) -> Result<Self, CardGameError> {
+        if names.len() != 2 && names.len() != 4 {
+            return Err(CardGameError::InvalidPlayerCount(names.len()));
+        }
+        if deck.len() != 52 {
+            return Err(CardGameError::InvalidDeckSize(deck.len()));
+        }
         let mut game_state = GameState::new_with_player_names(names);
 
         game_state.stacks.push(StackState::new_from_deck(
             "stock".into(),
-            shuffled_deck(crate::Back::Blue),
+            deck.clone(),
             false,
         ));
 
@@ -70,27 +323,55 @@ impl ZingGame {
             .stacks
             .push(StackState::new("open_counting_1".into()));
 
-        Self {
+        Ok(Self {
             game_state,
             dealer,
+            seed: None,
             turn: 0,
-            last_trick_winner: 999, // will always be overwritten; needs to be 0/1
+            last_trick_winner: None,
             history: Vec::new(),
-        }
+            turns: Vec::new(),
+            deck,
+            moves: Vec::new(),
+            events: Vec::new(),
+        })
     }
 
-    pub fn setup_game(&mut self) {
-        assert!(self.game_state.phase == GamePhase::Initial);
-        self.hand_out_cards();
-        self.show_bottom_card_of_dealer();
-        self.initial_cards_to_table();
+    pub fn setup_game(&mut self) -> Result<(), CardGameError> {
+        if self.game_state.phase != GamePhase::Initial {
+            return Err(CardGameError::WrongPhase(self.game_state.phase));
+        }
+        self.hand_out_cards()?;
+        self.show_bottom_card_of_dealer()?;
+        self.initial_cards_to_table()?;
         self.game_state.phase = GamePhase::Prepared;
+        Ok(())
     }
 
     pub fn state(&self) -> &GameState {
         &self.game_state
     }
 
+    /// Mutable access to the full state, used by AI players for simulating
+    /// games with re-distributed hidden cards.
+    pub(crate) fn state_mut(&mut self) -> &mut GameState {
+        &mut self.game_state
+    }
+
+    /// Clone of this game without the history of actions, which is not needed
+    /// for simulating the rest of the game (and relatively costly to copy).
+    pub(crate) fn clone_for_simulation(&self) -> Self {
+        Self {
+            game_state: self.game_state.clone(),
+            history: Vec::new(),
+            turns: Vec::new(),
+            deck: Vec::new(),
+            moves: Vec::new(),
+            events: Vec::new(),
+            ..*self
+        }
+    }
+
     pub fn turn(&self) -> usize {
         self.turn
     }
@@ -115,24 +396,52 @@ impl ZingGame {
         &self.history
     }
 
+    pub fn dealer(&self) -> usize {
+        self.dealer
+    }
+
+    /// The deck in the order before dealing, as passed to
+    /// [ZingGame::new_with_deck] (empty for games stored before the deck was
+    /// recorded).
+    pub fn initial_deck(&self) -> &Vec<Card> {
+        &self.deck
+    }
+
+    /// Tricks, Zings, the final sweep and redeals of the current game, in
+    /// order (events of turns taken back are removed).
+    pub fn events(&self) -> &Vec<ZingEvent> {
+        &self.events
+    }
+
+    /// All cards played and turns taken back so far, in order.
+    pub fn moves(&self) -> &Vec<ZingMove> {
+        &self.moves
+    }
+
+    /// Seed the deck was shuffled with, if it was not given explicitly.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Returns the team (0 or 1) of the given player.  With two players, each
+    /// player is a team of their own; with four players, the opposite players
+    /// sitting at even or odd indices play together.
+    pub fn team_of_player(player: usize) -> usize {
+        player % 2
+    }
+
+    /// Index of the stack collecting the tricks won by the given player's team.
+    fn score_stack_of_player(player: usize) -> usize {
+        2 + Self::team_of_player(player)
+    }
+
     #[allow(clippy::bool_to_int_with_if)]
     pub fn card_points(card: &Card) -> u32 {
         match card.rank {
             Rank::Jack | Rank::Queen | Rank::King | Rank::Ace => 1,
-            Rank::Ten => {
-                if card.suit == Suit::Diamonds {
-                    2
-                } else {
-                    1
-                }
-            }
-            Rank::Two => {
-                if card.suit == Suit::Clubs {
-                    1
-                } else {
-                    0
-                }
-            }
+            Rank::Ten if card.suit == Suit::Diamonds => 2,
+            Rank::Ten => 1,
+            Rank::Two if card.suit == Suit::Clubs => 1,
             _ => 0,
         }
     }
@@ -145,6 +454,21 @@ impl ZingGame {
         }
     }
 
+    /// Number of Zings per team (including Jack Zings) made so far.
+    pub fn zing_count(&self) -> (u32, u32) {
+        self.game_state.stacks[2..4]
+            .iter()
+            .map(|score_stack| {
+                score_stack
+                    .cards
+                    .iter()
+                    .filter(|card_state| card_state.face_up)
+                    .count() as u32
+            })
+            .collect_tuple()
+            .unwrap()
+    }
+
     fn total_card_points(&self) -> (u32, u32) {
         let (score0, score1, open0, open1) = self.game_state.stacks[2..6]
             .iter()
@@ -190,32 +514,50 @@ impl ZingGame {
         }
     }
 
-    fn perform_and_remember_action(&mut self, action: &CardAction) {
+    /// Applies the action and appends it to the history; actions not moving
+    /// any cards (e.g. sweeping an empty table) are skipped.
+    fn perform_and_remember_action(&mut self, action: &CardAction) -> Result<(), CardGameError> {
         if !action.source_card_indices.is_empty() {
             let mut action = action.clone();
-            action.apply_and_remember_cards(&mut self.game_state);
+            action.apply_and_remember_cards(&mut self.game_state)?;
             self.history.push(action);
         }
+        Ok(())
     }
 
-    pub fn play_card(&mut self, player: usize, card_index: usize) -> Result<(), &'static str> {
+    pub fn play_card(&mut self, player: usize, card_index: usize) -> Result<(), PlayError> {
         if Some(player) != self.current_player() {
-            return Err("not player's turn");
+            return Err(PlayError::NotPlayersTurn);
         }
 
         if card_index >= self.game_state.players[player].hand.len() {
-            return Err("invalid card index (exceeds player's hand)");
+            return Err(PlayError::InvalidCardIndex);
         }
 
+        let card = self.game_state.players[player].hand[card_index].card;
+        let mut turn_record = TurnRecord {
+            first_action: self.history.len(),
+            end_action: 0,
+            last_trick_winner: self.last_trick_winner,
+            phase: self.game_state.phase,
+        };
+
         self.perform_and_remember_action(
             CardAction::new()
-                .from_hand(&self.game_state, player, vec![card_index])
-                .to_stack_top(&self.game_state, 1)
+                .from_hand(&self.game_state, player, vec![card_index])?
+                .to_stack_top(&self.game_state, 1)?
                 .rotate(CardRotation::FaceUp),
-        );
+        )?;
 
-        self.auto_actions();
+        self.auto_actions()?;
 
+        turn_record.end_action = self.history.len();
+        self.turns.push(turn_record);
+        self.moves.push(ZingMove::PlayCard {
+            player,
+            card_index,
+            card,
+        });
         self.turn += 1;
 
         match self.game_state.phase {
@@ -232,101 +574,233 @@ impl ZingGame {
         Ok(())
     }
 
-    pub fn hand_out_cards(&mut self) {
+    /// Lists the cards the given player may play, together with their
+    /// outcome; empty if it is not the player's turn.  In Zing, every card on
+    /// the hand of the player at turn may be played.
+    pub fn move_hints(&self, player: usize) -> Vec<MoveHint> {
+        if Some(player) != self.current_player() {
+            return Vec::new();
+        }
+
+        self.game_state.players[player]
+            .hand
+            .iter()
+            .enumerate()
+            .map(|(card_index, card_state)| MoveHint {
+                card_index,
+                card: card_state.card,
+                outcome: self.move_outcome(player, card_index),
+            })
+            .collect()
+    }
+
+    /// Plays the given card in a simulation and derives the outcome from the
+    /// resulting events, so that it always follows the actual rules.
+    fn move_outcome(&self, player: usize, card_index: usize) -> MoveOutcome {
+        let mut simulation = self.clone_for_simulation();
+        if simulation.play_card(player, card_index).is_err() {
+            return MoveOutcome::Discard;
+        }
+        let turn = self.turn;
+        let outcome = simulation
+            .events
+            .iter()
+            .filter(|event| event.turn == turn)
+            .find_map(|event| match event.kind {
+                ZingEventKind::Trick { card_count, .. } => Some(MoveOutcome::Trick { card_count }),
+                ZingEventKind::Zing { jack, .. } => Some(MoveOutcome::Zing { jack }),
+                ZingEventKind::FinalSweep { .. } | ZingEventKind::Redeal { .. } => None,
+            });
+        match outcome {
+            Some(outcome) => outcome,
+            None if !simulation.finished() && simulation.game_state.stacks[1].cards.len() == 1 => {
+                MoveOutcome::OpensZing
+            }
+            None => MoveOutcome::Discard,
+        }
+    }
+
+    /// Takes back the card played last, together with all automatic actions
+    /// it caused (taking a trick, Zing, dealing new cards, final counting).
+    /// The reverting actions are appended to the history, so that it still
+    /// describes how the current state came about, and returned (e.g. for
+    /// animating the reversal).
+    pub fn undo_last_turn(&mut self) -> Result<Vec<CardAction>, PlayError> {
+        let turn_record = self.turns.pop().ok_or(PlayError::NoTurnToTakeBack)?;
+
+        let inverse_actions = self.history[turn_record.first_action..turn_record.end_action]
+            .iter()
+            .rev()
+            .map(CardAction::invert)
+            .collect::<Result<Vec<_>, _>>()?;
+        let first_inverse_action = self.history.len();
+        for action in &inverse_actions {
+            self.perform_and_remember_action(action)?;
+        }
+
+        self.moves.push(ZingMove::TakeBack);
+        self.turn -= 1;
+        let turn = self.turn;
+        self.events.retain(|event| event.turn < turn);
+        self.last_trick_winner = turn_record.last_trick_winner;
+        self.game_state.phase = turn_record.phase;
+
+        Ok(self.history[first_inverse_action..].to_vec())
+    }
+
+    pub fn hand_out_cards(&mut self) -> Result<(), CardGameError> {
         for _ in 0..2 {
             for i in 0..self.game_state.player_count() {
                 let player = (self.dealer + i + 1) % self.game_state.player_count();
                 self.perform_and_remember_action(
                     CardAction::new()
-                        .from_stack_top(&self.game_state, 0, 2)
-                        .to_hand(&self.game_state, player)
+                        .from_stack_top(&self.game_state, 0, 2)?
+                        .to_hand(&self.game_state, player)?
                         .rotate(CardRotation::FaceUp),
-                );
+                )?;
             }
         }
+        Ok(())
     }
 
-    pub fn show_bottom_card_of_dealer(&mut self) {
+    pub fn show_bottom_card_of_dealer(&mut self) -> Result<(), CardGameError> {
         // rotate bottom card face up (belongs to dealer, who is in advantage)
         self.perform_and_remember_action(
             CardAction::new()
-                .from_stack(&self.game_state, 0, vec![0])
-                .to_stack_bottom(&self.game_state, 0)
+                .from_stack(&self.game_state, 0, vec![0])?
+                .to_stack_bottom(&self.game_state, 0)?
                 .rotate(CardRotation::FaceUp),
-        );
+        )
     }
 
-    pub fn initial_cards_to_table(&mut self) {
+    pub fn initial_cards_to_table(&mut self) -> Result<(), CardGameError> {
         self.perform_and_remember_action(
             CardAction::new()
-                .from_stack_top(&self.game_state, 0, 4)
-                .to_stack_top(&self.game_state, 1)
+                .from_stack_top(&self.game_state, 0, 4)?
+                .to_stack_top(&self.game_state, 1)?
                 .rotate(CardRotation::FaceUp),
-        );
+        )?;
 
-        while self.game_state.stacks[1].cards.last().unwrap().card.rank == Rank::Jack {
+        while self.game_state.stacks[1]
+            .cards
+            .last()
+            .is_some_and(|top| top.card.rank == Rank::Jack)
+        {
             // put any Jack to bottom of stock, for dealer but public
             self.perform_and_remember_action(
                 CardAction::new()
-                    .from_stack_top(&self.game_state, 1, 1)
-                    .to_stack_bottom(&self.game_state, 0)
+                    .from_stack_top(&self.game_state, 1, 1)?
+                    .to_stack_bottom(&self.game_state, 0)?
                     .rotate(CardRotation::FaceUp),
-            );
+            )?;
             // deal a single new card to table
             self.perform_and_remember_action(
                 CardAction::new()
-                    .from_stack_top(&self.game_state, 0, 1)
-                    .to_stack_top(&self.game_state, 1)
+                    .from_stack_top(&self.game_state, 0, 1)?
+                    .to_stack_top(&self.game_state, 1)?
                     .rotate(CardRotation::FaceUp),
-            );
+            )?;
         }
+        Ok(())
     }
 
-    pub fn is_valid_action(&self, action: &CardAction) -> bool {
-        match action.source_location {
-            Some(CardLocation::PlayerHand) => {
-                (Some(action.source_index) == self.current_player())
-                    && (action.source_card_indices.len() == 1)
-                    && (*action.source_card_indices.first().unwrap()
-                        < self.game_state.players[self.current_player().unwrap()]
-                            .hand
-                            .len())
-            }
-            _ => false,
+    /// Returns the index of the card the given player plays with the action,
+    /// which must move exactly one card from the player's own hand face up
+    /// onto the table (the only move players can make in Zing).
+    fn card_played_by_action(
+        &self,
+        player: usize,
+        action: &CardAction,
+    ) -> Result<usize, PlayError> {
+        if action.source_location != Some(CardLocation::PlayerHand) || action.source_index != player
+        {
+            return Err(PlayError::IllegalAction(
+                "cards can only be played from one's own hand",
+            ));
+        }
+        if Some(player) != self.current_player() {
+            return Err(PlayError::NotPlayersTurn);
+        }
+        let [card_index] = action.source_card_indices[..] else {
+            return Err(PlayError::IllegalAction("exactly one card must be played"));
+        };
+        let hand = &self.game_state.players[player].hand;
+        let card_state = hand.get(card_index).ok_or(PlayError::InvalidCardIndex)?;
+
+        let table_size = self.game_state.stacks[1].cards.len();
+        if action.dest_location != Some(CardLocation::Stack)
+            || action.dest_index != 1
+            || action.dest_card_indices != [table_size]
+        {
+            return Err(PlayError::IllegalAction(
+                "cards must be played on top of the table stack",
+            ));
         }
+        if action.rotation == Some(CardRotation::FaceDown) {
+            return Err(PlayError::IllegalAction("cards must be played face up"));
+        }
+
+        // card states are optional, but must not make up other cards
+        let played = CardState {
+            card: card_state.card,
+            face_up: true,
+        };
+        let states_match = |states: &[CardState], expected: &CardState| {
+            states.is_empty() || states == std::slice::from_ref(expected)
+        };
+        if !states_match(&action.source_card_states, card_state)
+            || !states_match(&action.resulting_card_states, &played)
+        {
+            return Err(PlayError::IllegalAction(
+                "card states do not match the played card",
+            ));
+        }
+
+        Ok(card_index)
     }
 
-    pub fn auto_actions(&mut self) {
+    fn record_event(&mut self, kind: ZingEventKind) {
+        self.events.push(ZingEvent {
+            turn: self.turn,
+            kind,
+        });
+    }
+
+    pub fn auto_actions(&mut self) -> Result<(), CardGameError> {
         let table_stack = &self.game_state.stacks[1];
         if let [.., card1, card2] = &table_stack.cards[..] {
             if card1.card.rank == card2.card.rank {
-                let target_score_stack = 2 + self
+                let player = self
                     .current_player()
-                    .expect("When the top two cards are equal, we must be in game, so there must be a current player")
-                    % 2;
-                self.last_trick_winner = target_score_stack;
+                    .expect("When the top two cards are equal, we must be in game, so there must be a current player");
+                let target_score_stack = Self::score_stack_of_player(player);
+                self.last_trick_winner = Some(target_score_stack);
 
                 if table_stack.cards.len() == 2 {
                     // Zing!
+                    let jack = card2.card.rank == Rank::Jack;
                     self.perform_and_remember_action(
                         CardAction::new()
-                            .from_stack_top(&self.game_state, 1, 1)
-                            .to_stack_bottom(&self.game_state, target_score_stack)
+                            .from_stack_top(&self.game_state, 1, 1)?
+                            .to_stack_bottom(&self.game_state, target_score_stack)?
                             .rotate(CardRotation::FaceUp),
-                    );
+                    )?;
                     self.perform_and_remember_action(
                         CardAction::new()
-                            .from_stack_top(&self.game_state, 1, 1)
-                            .to_stack_top(&self.game_state, target_score_stack)
+                            .from_stack_top(&self.game_state, 1, 1)?
+                            .to_stack_top(&self.game_state, target_score_stack)?
                             .rotate(CardRotation::FaceDown),
-                    );
+                    )?;
+                    self.record_event(ZingEventKind::Zing { player, jack });
                 } else {
+                    let card_count = table_stack.cards.len();
                     self.perform_and_remember_action(
                         CardAction::new()
-                            .from_stack_top(&self.game_state, 1, table_stack.cards.len())
-                            .to_stack_top(&self.game_state, target_score_stack)
+                            .from_stack_top(&self.game_state, 1, card_count)?
+                            .to_stack_top(&self.game_state, target_score_stack)?
                             .rotate(CardRotation::FaceDown),
-                    );
+                    )?;
+                    self.record_event(ZingEventKind::Trick { player, card_count });
                 }
             }
         }
@@ -334,15 +808,18 @@ impl ZingGame {
         let table_stack = &self.game_state.stacks[1];
         if let Some(top_card) = table_stack.cards.last() {
             if top_card.card.rank == Rank::Jack && table_stack.cards.len() > 1 {
-                let target_stack = 2 + self.current_player().expect("When the top card is a Jack, we must be in game, so there must be a current player") % 2;
-                self.last_trick_winner = target_stack;
+                let player = self.current_player().expect("When the top card is a Jack, we must be in game, so there must be a current player");
+                let target_stack = Self::score_stack_of_player(player);
+                self.last_trick_winner = Some(target_stack);
 
+                let card_count = table_stack.cards.len();
                 self.perform_and_remember_action(
                     CardAction::new()
-                        .from_stack_top(&self.game_state, 1, table_stack.cards.len())
-                        .to_stack_top(&self.game_state, target_stack)
+                        .from_stack_top(&self.game_state, 1, card_count)?
+                        .to_stack_top(&self.game_state, target_stack)?
                         .rotate(CardRotation::FaceDown),
-                );
+                )?;
+                self.record_event(ZingEventKind::Trick { player, card_count });
             }
         }
 
@@ -353,15 +830,25 @@ impl ZingGame {
             .all(|player| player.hand.is_empty())
         {
             if !self.game_state.stacks[0].cards.is_empty() {
-                self.hand_out_cards();
+                self.hand_out_cards()?;
+                self.record_event(ZingEventKind::Redeal {
+                    stock_cards: self.game_state.stacks[0].cards.len(),
+                });
             } else {
-                let table_stack = &self.game_state.stacks[1];
-                self.perform_and_remember_action(
-                    CardAction::new()
-                        .from_stack_top(&self.game_state, 1, table_stack.cards.len())
-                        .to_stack_top(&self.game_state, self.last_trick_winner)
-                        .rotate(CardRotation::FaceDown),
-                );
+                // if nobody has taken a trick, the cards stay on the table
+                let card_count = self.game_state.stacks[1].cards.len();
+                if let Some(score_stack) = self.last_trick_winner.filter(|_| card_count > 0) {
+                    self.perform_and_remember_action(
+                        CardAction::new()
+                            .from_stack_top(&self.game_state, 1, card_count)?
+                            .to_stack_top(&self.game_state, score_stack)?
+                            .rotate(CardRotation::FaceDown),
+                    )?;
+                    self.record_event(ZingEventKind::FinalSweep {
+                        team: score_stack - 2,
+                        card_count,
+                    });
+                }
 
                 for score_index in 0..2 {
                     let score_stack = &self.state().stacks[2 + score_index].cards;
@@ -376,12 +863,622 @@ impl ZingGame {
                                     .filter(|(_, cs)| !cs.face_up)
                                     .map(|(i, _)| i)
                                     .collect(),
-                            )
-                            .to_stack_top(&self.game_state, 4 + score_index)
+                            )?
+                            .to_stack_top(&self.game_state, 4 + score_index)?
                             .rotate(CardRotation::FaceUp),
-                    );
+                    )?;
                 }
             }
         }
+        Ok(())
+    }
+}
+
+impl ActionRules for ZingGame {
+    type Error = PlayError;
+
+    fn validate_action(&self, player: usize, action: &CardAction) -> Result<(), PlayError> {
+        self.card_played_by_action(player, action).map(|_| ())
+    }
+
+    fn perform_action(&mut self, player: usize, action: &CardAction) -> Result<(), PlayError> {
+        // the action is only used for choosing the card; it is never applied
+        // itself, so that it cannot corrupt the game state
+        let card_index = self.card_played_by_action(player, action)?;
+        self.play_card(player, card_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::{MoveOutcome, PlayError, ZingEventKind, ZingEventSummary, ZingGame};
+    use crate::card_action::{ActionRules, CardAction, CardLocation, CardRotation};
+    use crate::decks::deck;
+    use crate::game::{CardGameError, CardState, GamePhase};
+    use crate::{Back, Rank};
+
+    fn four_player_game(dealer: usize) -> ZingGame {
+        let mut game = ZingGame::new_with_player_names(
+            vec!["N".into(), "E".into(), "S".into(), "W".into()],
+            dealer,
+        )
+        .unwrap();
+        game.setup_game().unwrap();
+        game
+    }
+
+    fn play_until_finished(game: &mut ZingGame) {
+        while !game.finished() {
+            let player = game.current_player().unwrap();
+            let hand_size = game.state().players[player].hand.len();
+            game.play_card(player, game.turn() % hand_size).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_teams() {
+        assert_eq!(ZingGame::team_of_player(0), 0);
+        assert_eq!(ZingGame::team_of_player(1), 1);
+        assert_eq!(ZingGame::team_of_player(2), 0);
+        assert_eq!(ZingGame::team_of_player(3), 1);
+    }
+
+    #[test]
+    fn test_play_errors() {
+        let mut game = four_player_game(0);
+        assert_eq!(game.current_player(), Some(1));
+        assert_eq!(game.play_card(0, 0), Err(PlayError::NotPlayersTurn));
+        assert_eq!(game.play_card(1, 4), Err(PlayError::InvalidCardIndex));
+        assert_eq!(game.turn(), 0);
+        assert!(game.moves().is_empty());
+    }
+
+    #[test]
+    fn test_four_player_dealer_rotation() {
+        for dealer in 0..4 {
+            let game = four_player_game(dealer);
+            assert_eq!(game.dealer(), dealer);
+            assert_eq!(game.current_player(), Some((dealer + 1) % 4));
+            for player in &game.state().players {
+                assert_eq!(player.hand.len(), 4);
+            }
+            // the dealer's revealed bottom card is the last one to be dealt
+            assert!(game.state().stacks[0].cards[0].face_up);
+        }
+    }
+
+    #[test]
+    fn test_four_player_game_deals_evenly() {
+        for dealer in 0..4 {
+            let mut game = four_player_game(dealer);
+            play_until_finished(&mut game);
+            assert_eq!(game.state().phase, GamePhase::Finished);
+            assert_eq!(game.turn(), 48);
+            assert_eq!(game.current_player(), None);
+
+            let mut cards_played = [0; 4];
+            for action in game.history() {
+                if action.source_location == Some(CardLocation::PlayerHand) {
+                    cards_played[action.source_index] += 1;
+                }
+            }
+            assert_eq!(cards_played, [12; 4]);
+
+            // stock and table are empty, all cards have been counted
+            assert!(game.state().stacks[0].cards.is_empty());
+            assert!(game.state().stacks[1].cards.is_empty());
+            let counted: usize = game.state().stacks[2..6]
+                .iter()
+                .map(|stack| stack.cards.len())
+                .sum();
+            assert_eq!(counted, 52);
+        }
+    }
+
+    #[test]
+    fn test_four_player_tricks_go_to_team() {
+        for dealer in 0..4 {
+            let mut game = four_player_game(dealer);
+            play_until_finished(&mut game);
+
+            let mut last_player = None;
+            let mut tricks = Vec::new();
+            for action in game.history() {
+                match (action.source_location, action.dest_location) {
+                    (Some(CardLocation::PlayerHand), _) => last_player = Some(action.source_index),
+                    (Some(CardLocation::Stack), Some(CardLocation::Stack))
+                        if action.source_index == 1 && action.dest_index >= 2 =>
+                    {
+                        tricks.push((action.dest_index, last_player.unwrap()));
+                    }
+                    _ => {}
+                }
+            }
+
+            // the final action may be the remaining table cards going to the
+            // last trick winner, which need not be the last player
+            let ((final_stack, final_player), tricks) = tricks.split_last().unwrap();
+            for (score_stack, player) in tricks {
+                assert_eq!(*score_stack, 2 + ZingGame::team_of_player(*player));
+            }
+            assert!(
+                *final_stack == 2 + ZingGame::team_of_player(*final_player)
+                    || *final_stack == tricks.last().unwrap().0
+            );
+        }
+    }
+
+    #[test]
+    fn test_four_player_points_add_up() {
+        for dealer in 0..4 {
+            let mut game = four_player_game(dealer);
+            play_until_finished(&mut game);
+
+            let points = game.points();
+            assert_eq!(points.card_points.0 + points.card_points.1, 22);
+
+            let count0 = game.state().stacks[2].cards.len() + game.state().stacks[4].cards.len();
+            let count1 = game.state().stacks[3].cards.len() + game.state().stacks[5].cards.len();
+            assert_eq!(count0 + count1, 52);
+            let expected_count_points = if count0 == count1 { 0 } else { 3 };
+            assert_eq!(
+                points.card_count_points.0 + points.card_count_points.1,
+                expected_count_points
+            );
+
+            // every Zing leaves exactly one face up card in a score stack
+            for team in 0..2 {
+                let zing_cards = game.state().stacks[2 + team]
+                    .cards
+                    .iter()
+                    .filter(|card_state| card_state.face_up)
+                    .count() as u32;
+                let zing_points = if team == 0 {
+                    points.zing_points.0
+                } else {
+                    points.zing_points.1
+                };
+                assert!(zing_points >= 10 * zing_cards);
+                assert!(zing_points <= 20 * zing_cards);
+                let zing_count = game.zing_count();
+                assert_eq!(
+                    if team == 0 {
+                        zing_count.0
+                    } else {
+                        zing_count.1
+                    },
+                    zing_cards
+                );
+            }
+
+            let total = points.total_points();
+            for player in 0..4 {
+                let team_total = if player % 2 == 0 { total.0 } else { total.1 };
+                assert_eq!(points.total_points_of_player(player), team_total);
+            }
+        }
+    }
+
+    #[test]
+    fn test_events_explain_points() {
+        for dealer in 0..4 {
+            let mut game = four_player_game(dealer);
+            play_until_finished(&mut game);
+
+            let points = game.points();
+            let zing_count = game.zing_count();
+            let summary = ZingEventSummary::from_events(game.events());
+            assert_eq!(
+                points.zing_points,
+                (
+                    10 * summary.zings.0 + 20 * summary.jack_zings.0,
+                    10 * summary.zings.1 + 20 * summary.jack_zings.1
+                )
+            );
+            assert_eq!(
+                zing_count,
+                (
+                    summary.zings.0 + summary.jack_zings.0,
+                    summary.zings.1 + summary.jack_zings.1
+                )
+            );
+
+            // all cards apart from the ones dealt end up in a score stack
+            let taken: usize = game
+                .events()
+                .iter()
+                .map(|event| match event.kind {
+                    ZingEventKind::Trick { card_count, .. }
+                    | ZingEventKind::FinalSweep { card_count, .. } => card_count,
+                    ZingEventKind::Zing { .. } => 2,
+                    ZingEventKind::Redeal { .. } => 0,
+                })
+                .sum();
+            assert_eq!(taken, 52);
+
+            let redeals = game
+                .events()
+                .iter()
+                .filter(|event| matches!(event.kind, ZingEventKind::Redeal { .. }))
+                .count();
+            // 4 cards on the table and 16 in the hands for each deal
+            assert_eq!(redeals, (52 - 4) / 16 - 1);
+
+            assert!(game
+                .events()
+                .windows(2)
+                .all(|pair| pair[0].turn <= pair[1].turn));
+        }
+    }
+
+    #[test]
+    fn test_undo_removes_events() {
+        let mut game = ZingGame::new_with_seed(two_player_names(), 1, 42).unwrap();
+        game.setup_game().unwrap();
+        play_until_finished(&mut game);
+        let events = game.events().clone();
+        let last_turn = events.last().unwrap().turn;
+
+        while game.turn() > last_turn {
+            game.undo_last_turn().unwrap();
+        }
+        assert!(game.events().iter().all(|event| event.turn < last_turn));
+        assert_eq!(game.events()[..], events[..game.events().len()]);
+    }
+
+    #[test]
+    fn test_move_hints() {
+        let mut outcomes_seen = [false; 4];
+        for seed in 0..5 {
+            let mut game = ZingGame::new_with_seed(two_player_names(), 0, seed).unwrap();
+            game.setup_game().unwrap();
+            while let Some(player) = game.current_player() {
+                assert!(game.move_hints(1 - player).is_empty());
+                let hints = game.move_hints(player);
+                let hand = &game.state().players[player].hand;
+                assert_eq!(hints.len(), hand.len());
+
+                let table = &game.state().stacks[1].cards;
+                for (card_index, hint) in hints.iter().enumerate() {
+                    assert_eq!(hint.card_index, card_index);
+                    assert_eq!(hint.card, hand[card_index].card);
+                    let takes_cards = table.last().is_some_and(|top| {
+                        top.card.rank == hint.card.rank || hint.card.rank == Rank::Jack
+                    });
+                    assert_eq!(hint.outcome.takes_cards(), takes_cards);
+                }
+
+                let card_index = game.turn() % hints.len();
+                let turn = game.turn();
+                let events_before = game.events().len();
+                game.play_card(player, card_index).unwrap();
+
+                let outcome = hints[card_index].outcome;
+                let scored = game.events()[events_before..]
+                    .iter()
+                    .filter(|event| event.turn == turn)
+                    .find_map(|event| match event.kind {
+                        ZingEventKind::Trick { card_count, .. } => {
+                            Some(MoveOutcome::Trick { card_count })
+                        }
+                        ZingEventKind::Zing { jack, .. } => Some(MoveOutcome::Zing { jack }),
+                        _ => None,
+                    });
+                match scored {
+                    Some(scored) => assert_eq!(outcome, scored),
+                    None if !game.finished() && game.state().stacks[1].cards.len() == 1 => {
+                        assert_eq!(outcome, MoveOutcome::OpensZing)
+                    }
+                    None => assert_eq!(outcome, MoveOutcome::Discard),
+                }
+                outcomes_seen[match outcome {
+                    MoveOutcome::Trick { .. } => 0,
+                    MoveOutcome::Zing { .. } => 1,
+                    MoveOutcome::OpensZing => 2,
+                    MoveOutcome::Discard => 3,
+                }] = true;
+            }
+            assert!(game.move_hints(0).is_empty());
+        }
+        assert_eq!(outcomes_seen, [true; 4]);
+    }
+
+    fn two_player_names() -> Vec<String> {
+        vec!["Hans".into(), "Darko".into()]
+    }
+
+    #[test]
+    fn test_same_seed_same_game() {
+        let mut game1 = ZingGame::new_with_seed(two_player_names(), 1, 4711).unwrap();
+        let mut game2 = ZingGame::new_with_seed(two_player_names(), 1, 4711).unwrap();
+        assert_eq!(game1.seed(), Some(4711));
+        game1.setup_game().unwrap();
+        game2.setup_game().unwrap();
+        play_until_finished(&mut game1);
+        play_until_finished(&mut game2);
+
+        assert_eq!(
+            serde_json::to_string(&game1).unwrap(),
+            serde_json::to_string(&game2).unwrap()
+        );
+        assert_eq!(game1.points(), game2.points());
+    }
+
+    #[test]
+    fn test_seed_is_serialized() {
+        let game = ZingGame::new_with_player_names(two_player_names(), 0).unwrap();
+        let seed = game.seed();
+        assert!(seed.is_some());
+
+        let json = serde_json::to_value(&game).unwrap();
+        let restored: ZingGame = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.seed(), seed);
+    }
+
+    #[test]
+    fn test_invalid_setup() {
+        assert_eq!(
+            ZingGame::new_with_seed(vec!["A".into(), "B".into(), "C".into()], 0, 42).err(),
+            Some(CardGameError::InvalidPlayerCount(3))
+        );
+        let mut short_deck = deck(Back::Blue);
+        short_deck.pop();
+        assert_eq!(
+            ZingGame::new_with_deck(two_player_names(), 0, short_deck).err(),
+            Some(CardGameError::InvalidDeckSize(51))
+        );
+
+        let mut game = ZingGame::new_with_seed(two_player_names(), 0, 42).unwrap();
+        game.setup_game().unwrap();
+        assert_eq!(
+            game.setup_game(),
+            Err(CardGameError::WrongPhase(GamePhase::Prepared))
+        );
+    }
+
+    #[test]
+    fn test_final_sweep_without_trick() {
+        let mut game = ZingGame::new_with_seed(two_player_names(), 0, 42).unwrap();
+        game.setup_game().unwrap();
+        // end the game before anybody has taken a trick
+        for player in &mut game.game_state.players {
+            player.hand.clear();
+        }
+        game.game_state.stacks[0].cards.clear();
+        game.game_state.stacks[1].cards.truncate(1);
+        assert_eq!(game.last_trick_winner, None);
+
+        game.auto_actions().unwrap();
+        assert_eq!(game.state().stacks[1].cards.len(), 1);
+        assert!(game.events().is_empty());
+
+        // games stored with the former placeholder have no trick winner
+        let mut json = serde_json::to_value(&game).unwrap();
+        json["last_trick_winner"] = serde_json::json!(999);
+        let restored: ZingGame = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.last_trick_winner, None);
+    }
+
+    #[test]
+    fn test_unshuffled_deck() {
+        let mut game = ZingGame::new_with_deck(two_player_names(), 1, deck(Back::Blue)).unwrap();
+        assert_eq!(game.seed(), None);
+        game.setup_game().unwrap();
+
+        let short_strs = |cards: &[CardState]| -> Vec<String> {
+            cards.iter().map(|cs| cs.card.short_str()).collect()
+        };
+
+        // cards are dealt from the end of the deck, two at a time, starting
+        // with the player after the dealer
+        assert_eq!(
+            short_strs(&game.state().players[0].hand),
+            ["K♣", "A♣", "9♣", "10♣"]
+        );
+        assert_eq!(
+            short_strs(&game.state().players[1].hand),
+            ["J♣", "Q♣", "7♣", "8♣"]
+        );
+        // the dealer's bottom card is revealed
+        assert_eq!(game.state().stacks[0].cards[0].card.short_str(), "2♦");
+        assert!(game.state().stacks[0].cards[0].face_up);
+        // four cards are dealt face up to the table
+        assert_eq!(
+            short_strs(&game.state().stacks[1].cards),
+            ["3♣", "4♣", "5♣", "6♣"]
+        );
+        assert_eq!(game.current_player(), Some(0));
+    }
+
+    fn state_json(game: &ZingGame) -> String {
+        serde_json::to_string(game.state()).unwrap()
+    }
+
+    #[test]
+    fn test_undo_all_turns() {
+        for names in [
+            two_player_names(),
+            vec!["N".into(), "E".into(), "S".into(), "W".into()],
+        ] {
+            let mut game = ZingGame::new_with_seed(names, 1, 42).unwrap();
+            game.setup_game().unwrap();
+            assert_eq!(game.undo_last_turn(), Err(PlayError::NoTurnToTakeBack));
+
+            let mut states = Vec::new();
+            while let Some(player) = game.current_player() {
+                states.push((state_json(&game), player));
+                game.play_card(
+                    player,
+                    game.turn() % 3 % game.state().players[player].hand.len(),
+                )
+                .unwrap();
+            }
+            let final_state = state_json(&game);
+            let final_points = game.points();
+
+            while let Some((state, player)) = states.pop() {
+                let history_len = game.history().len();
+                let inverse_actions = game.undo_last_turn().unwrap();
+                assert!(!inverse_actions.is_empty());
+                assert_eq!(game.history().len(), history_len + inverse_actions.len());
+                assert_eq!(state_json(&game), state);
+                assert_eq!(game.current_player(), Some(player));
+            }
+            assert_eq!(game.turn(), 0);
+            assert_eq!(game.state().phase, GamePhase::Prepared);
+            assert!(game.undo_last_turn().is_err());
+
+            // playing the same cards again must lead to the same result
+            while let Some(player) = game.current_player() {
+                game.play_card(
+                    player,
+                    game.turn() % 3 % game.state().players[player].hand.len(),
+                )
+                .unwrap();
+            }
+            assert_eq!(state_json(&game), final_state);
+            assert_eq!(game.points(), final_points);
+        }
+    }
+
+    #[test]
+    fn test_inverted_actions_replay() {
+        let mut game = ZingGame::new_with_seed(two_player_names(), 0, 7).unwrap();
+        game.setup_game().unwrap();
+        for _ in 0..10 {
+            game.play_card(game.current_player().unwrap(), 0).unwrap();
+        }
+        game.undo_last_turn().unwrap();
+        game.undo_last_turn().unwrap();
+
+        // the history (including the reverting actions) still reproduces the state
+        let mut replayed = ZingGame::new_with_seed(two_player_names(), 0, 7).unwrap();
+        for action in game.history() {
+            action.apply(&mut replayed.game_state).unwrap();
+        }
+        assert_eq!(
+            serde_json::to_string(&replayed.game_state.players).unwrap(),
+            serde_json::to_string(&game.state().players).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&replayed.game_state.stacks).unwrap(),
+            serde_json::to_string(&game.state().stacks).unwrap()
+        );
+    }
+
+    /// Random action that is often close to a valid move (e.g. a valid
+    /// action with a single field changed), since completely random actions
+    /// would hardly ever get past the first check.
+    fn random_action(game: &ZingGame, rng: &mut StdRng) -> CardAction {
+        let player = game.current_player().unwrap();
+        let hand_size = game.state().players[player].hand.len();
+        let mut action = CardAction::new()
+            .from_hand(game.state(), player, vec![rng.random_range(0..hand_size)])
+            .unwrap()
+            .to_stack_top(game.state(), 1)
+            .unwrap()
+            .clone();
+        if rng.random_bool(0.5) {
+            let mut remembered = action.clone();
+            remembered
+                .apply_and_remember_cards(&mut game.state().clone())
+                .unwrap();
+            action.source_card_states = remembered.source_card_states;
+            action.resulting_card_states = remembered.resulting_card_states;
+        }
+
+        let random_location = |rng: &mut StdRng| match rng.random_range(0..3) {
+            0 => None,
+            1 => Some(CardLocation::PlayerHand),
+            _ => Some(CardLocation::Stack),
+        };
+        let random_indices = |rng: &mut StdRng| {
+            (0..rng.random_range(0..3))
+                .map(|_| rng.random_range(0..12))
+                .collect()
+        };
+        let random_card = |rng: &mut StdRng| {
+            let cards = &game.state().stacks[0].cards;
+            cards.get(rng.random_range(0..cards.len().max(1))).cloned()
+        };
+        match rng.random_range(0..10) {
+            0 => action.source_location = random_location(rng),
+            1 => action.source_index = rng.random_range(0..8),
+            2 => action.source_card_indices = random_indices(rng),
+            3 => action.dest_location = random_location(rng),
+            4 => action.dest_index = rng.random_range(0..8),
+            5 => action.dest_card_indices = random_indices(rng),
+            6 => {
+                action.rotation = [
+                    None,
+                    Some(CardRotation::FaceUp),
+                    Some(CardRotation::FaceDown),
+                ][rng.random_range(0..3)]
+            }
+            7 => action.resulting_card_states = random_card(rng).into_iter().collect(),
+            8 => action.source_card_states = random_card(rng).into_iter().collect(),
+            _ => {}
+        }
+        action
+    }
+
+    #[test]
+    fn test_fuzzed_actions() {
+        let mut rng = StdRng::seed_from_u64(4711);
+        let (mut accepted, mut rejected) = (0, 0);
+        for seed in 0..10 {
+            let mut game =
+                ZingGame::new_with_seed(two_player_names(), seed as usize % 2, seed).unwrap();
+            game.setup_game().unwrap();
+            while let Some(current) = game.current_player() {
+                for _ in 0..20 {
+                    let action = random_action(&game, &mut rng);
+                    let player = if rng.random_bool(0.8) {
+                        current
+                    } else {
+                        1 - current
+                    };
+                    let mut attempt = game.clone();
+                    match attempt.perform_action(player, &action) {
+                        Ok(()) => {
+                            accepted += 1;
+                            assert_eq!(game.validate_action(player, &action), Ok(()));
+                            let mut expected = game.clone();
+                            expected
+                                .play_card(player, action.source_card_indices[0])
+                                .unwrap();
+                            assert_eq!(state_json(&attempt), state_json(&expected));
+                            assert_eq!(attempt.history(), expected.history());
+                        }
+                        Err(err) => {
+                            rejected += 1;
+                            assert_eq!(game.validate_action(player, &action), Err(err));
+                            assert_eq!(state_json(&attempt), state_json(&game));
+                            assert_eq!(attempt.history().len(), game.history().len());
+                        }
+                    }
+                }
+
+                let hand_size = game.state().players[current].hand.len();
+                game.play_card(current, rng.random_range(0..hand_size))
+                    .unwrap();
+                // no card may ever be lost or duplicated
+                let mut cards: Vec<_> = game
+                    .state()
+                    .players
+                    .iter()
+                    .flat_map(|player| &player.hand)
+                    .chain(game.state().stacks.iter().flat_map(|stack| &stack.cards))
+                    .map(|card_state| (card_state.card.suit as u8, card_state.card.rank as u8))
+                    .collect();
+                cards.sort_unstable();
+                cards.dedup();
+                assert_eq!(cards.len(), 52);
+            }
+        }
+        assert!(accepted > 100 && rejected > 100);
     }
 }
@@ -0,0 +1,159 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::zing_game::{ZingGame, ZingGamePoints};
+
+/// Number of points a team needs to collect over several games in order to
+/// win a match.
+pub const MATCH_POINTS: u32 = 101;
+
+/// A match consists of several games played by the same players, until one
+/// team has collected at least [MATCH_POINTS] points.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ZingMatch {
+    game_results: Vec<ZingGamePoints>,
+}
+
+impl ZingMatch {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Continue a match from the results of the games already played (e.g.,
+    /// after loading them from a DB).
+    pub fn new_with_game_results(game_results: Vec<ZingGamePoints>) -> Self {
+        Self { game_results }
+    }
+
+    pub fn games_played(&self) -> usize {
+        self.game_results.len()
+    }
+
+    pub fn game_results(&self) -> &Vec<ZingGamePoints> {
+        &self.game_results
+    }
+
+    /// Sum of the total points of all games played so far, per team.
+    pub fn total_points(&self) -> (u32, u32) {
+        self.game_results
+            .iter()
+            .map(ZingGamePoints::total_points)
+            .fold((0, 0), |(sum0, sum1), (points0, points1)| {
+                (sum0 + points0, sum1 + points1)
+            })
+    }
+
+    /// Returns the team that won the match, if any.  If both teams reach
+    /// [MATCH_POINTS] in the same game, the team with more points wins; if
+    /// both have the same number of points, the match is continued until the
+    /// tie is broken.
+    pub fn winner(&self) -> Option<usize> {
+        let (points0, points1) = self.total_points();
+        if points0.max(points1) < MATCH_POINTS {
+            return None;
+        }
+        match points0.cmp(&points1) {
+            Ordering::Greater => Some(0),
+            Ordering::Less => Some(1),
+            Ordering::Equal => None,
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.winner().is_some()
+    }
+
+    /// The dealer rotates clockwise around the table with every game.
+    pub fn next_dealer(&self, player_count: usize) -> usize {
+        self.game_results.len() % player_count
+    }
+
+    /// Creates the next game of this match with the right dealer.  Returns an
+    /// error if the match has already been decided.
+    pub fn start_next_game(&self, names: Vec<String>) -> Result<ZingGame, &'static str> {
+        if self.finished() {
+            return Err("match has already been decided");
+        }
+        if names.len() != 2 && names.len() != 4 {
+            return Err("Zing can only be played by two or four players");
+        }
+        let dealer = self.next_dealer(names.len());
+        ZingGame::new_with_player_names(names, dealer).map_err(|_| "game could not be set up")
+    }
+
+    /// Records the points of a finished game.
+    pub fn add_game_result(&mut self, points: ZingGamePoints) -> Result<(), &'static str> {
+        if self.finished() {
+            return Err("match has already been decided");
+        }
+        self.game_results.push(points);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ZingMatch, MATCH_POINTS};
+    use crate::zing_game::ZingGamePoints;
+
+    fn points(card_points: (u32, u32), zing_points: (u32, u32)) -> ZingGamePoints {
+        ZingGamePoints {
+            card_points,
+            card_count_points: (0, 0),
+            zing_points,
+        }
+    }
+
+    #[test]
+    fn test_match_winner() {
+        let mut zing_match = ZingMatch::new();
+        while zing_match.total_points().0 + 40 < MATCH_POINTS {
+            zing_match
+                .add_game_result(points((14, 8), (20, 0)))
+                .unwrap();
+            assert_eq!(zing_match.winner(), None);
+        }
+        zing_match
+            .add_game_result(points((14, 8), (20, 0)))
+            .unwrap();
+        assert_eq!(zing_match.winner(), Some(0));
+        assert!(zing_match.add_game_result(points((0, 22), (0, 0))).is_err());
+    }
+
+    #[test]
+    fn test_match_tie_break() {
+        let mut zing_match = ZingMatch::new();
+        zing_match
+            .add_game_result(points((11, 11), (80, 80)))
+            .unwrap();
+        assert_eq!(zing_match.total_points(), (91, 91));
+        assert_eq!(zing_match.winner(), None);
+
+        // both teams pass 101 with the same points, so the match continues
+        zing_match
+            .add_game_result(points((11, 11), (0, 0)))
+            .unwrap();
+        assert_eq!(zing_match.winner(), None);
+
+        // both teams pass 101, the team with more points wins
+        zing_match
+            .add_game_result(points((12, 10), (0, 0)))
+            .unwrap();
+        assert_eq!(zing_match.winner(), Some(0));
+    }
+
+    #[test]
+    fn test_match_dealer_rotation() {
+        let mut zing_match = ZingMatch::new();
+        let names: Vec<String> = vec!["N".into(), "E".into(), "S".into(), "W".into()];
+        for dealer in 0..6 {
+            let game = zing_match.start_next_game(names.clone()).unwrap();
+            assert_eq!(game.dealer(), dealer % 4);
+            zing_match
+                .add_game_result(points((11, 11), (0, 0)))
+                .unwrap();
+        }
+        assert!(zing_match.start_next_game(names[..3].to_vec()).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::{zing_ai::ZingAI, zing_game::ZingGame, Rank};
+
+/// Limits the amount of work [MonteCarloPlayer] spends on a single decision.
+#[derive(Clone, Copy, Debug)]
+pub enum SearchBudget {
+    /// Number of sampled distributions of the hidden cards (each of which is
+    /// used for one rollout per playable card).  Results are reproducible for
+    /// a given seed.
+    Iterations(usize),
+    /// Sample as many distributions as possible within the given time (at
+    /// least one).  Results depend on the machine's speed.
+    Time(Duration),
+}
+
+/// Information set Monte Carlo player: for every decision, it repeatedly
+/// samples a distribution of the cards it cannot see (other players' hands and
+/// the face down stock) that is consistent with its own view of the game,
+/// plays each hand card in that sampled game, simulates the rest of the game
+/// with a fast rollout policy, and finally picks the card with the best
+/// average point difference.
+pub struct MonteCarloPlayer {
+    player_index: usize,
+    budget: SearchBudget,
+    seed: u64,
+}
+
+impl MonteCarloPlayer {
+    pub const DEFAULT_ITERATIONS: usize = 200;
+
+    pub fn new(player_index: usize, budget: SearchBudget, seed: u64) -> Self {
+        Self {
+            player_index,
+            budget,
+            seed,
+        }
+    }
+
+    /// Random number generator for a single decision.  It is derived from the
+    /// seed and the turn, so that the same game and seed always lead to the
+    /// same decisions, regardless of which decisions have been made before.
+    fn decision_rng(&self, game: &ZingGame) -> StdRng {
+        StdRng::seed_from_u64(
+            self.seed
+                .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+                .wrapping_add(game.turn() as u64),
+        )
+    }
+
+    /// Returns a copy of the game in which all cards hidden from our player
+    /// are randomly redistributed among the hidden positions.  The pool of
+    /// redistributed cards is exactly the set of cards our player has not
+    /// seen (all other cards are on our hand, on the table, face up in the
+    /// stock, or have been won in tricks in plain sight), so no secret
+    /// information leaks into the decision.
+    fn determinize(&self, game: &ZingGame, rng: &mut StdRng) -> ZingGame {
+        let mut result = game.clone_for_simulation();
+        let state = result.state_mut();
+
+        let mut pool = Vec::new();
+        for (i, player) in state.players.iter().enumerate() {
+            if i != self.player_index {
+                pool.extend(player.hand.iter().map(|card_state| card_state.card));
+            }
+        }
+        pool.extend(
+            state.stacks[0]
+                .cards
+                .iter()
+                .filter(|card_state| !card_state.face_up)
+                .map(|card_state| card_state.card),
+        );
+        pool.shuffle(rng);
+
+        let mut pool = pool.into_iter();
+        for (i, player) in state.players.iter_mut().enumerate() {
+            if i != self.player_index {
+                for card_state in player.hand.iter_mut() {
+                    card_state.card = pool.next().unwrap();
+                }
+            }
+        }
+        for card_state in state.stacks[0].cards.iter_mut() {
+            if !card_state.face_up {
+                card_state.card = pool.next().unwrap();
+            }
+        }
+
+        result
+    }
+
+    /// Fast rollout policy: take a trick with a matching card if possible (or
+    /// with a Jack if there are at least two cards on the table), avoid
+    /// playing Jacks onto an empty table, otherwise play randomly.
+    fn rollout_card(game: &ZingGame, player: usize, rng: &mut StdRng) -> usize {
+        let hand = &game.state().players[player].hand;
+        let table = &game.state().stacks[1].cards;
+        let jack_index = hand
+            .iter()
+            .position(|card_state| card_state.card.rank == Rank::Jack);
+        if let Some(top_card) = table.last() {
+            if let Some(index) = hand
+                .iter()
+                .position(|card_state| card_state.card.rank == top_card.card.rank)
+            {
+                return index;
+            }
+            if let Some(index) = jack_index.filter(|_| table.len() >= 2) {
+                return index;
+            }
+        }
+        let non_jacks: Vec<usize> = (0..hand.len())
+            .filter(|i| hand[*i].card.rank != Rank::Jack)
+            .collect();
+        if non_jacks.is_empty() {
+            rng.random_range(0..hand.len())
+        } else {
+            non_jacks[rng.random_range(0..non_jacks.len())]
+        }
+    }
+
+    /// Plays the given card and simulates the rest of the game, returning the
+    /// resulting point difference from our team's perspective.
+    fn simulate(&self, mut game: ZingGame, card_index: usize, rng: &mut StdRng) -> f64 {
+        game.play_card(self.player_index, card_index)
+            .expect("simulating a valid move");
+        while let Some(player) = game.current_player() {
+            let card_index = Self::rollout_card(&game, player, rng);
+            game.play_card(player, card_index)
+                .expect("rollout policy must choose valid moves");
+        }
+
+        let points = game.points();
+        let own = points.total_points_of_player(self.player_index);
+        let other = points.total_points_of_player(self.player_index + 1);
+        f64::from(own) - f64::from(other)
+    }
+
+    /// Returns the average point difference for each card on our hand.
+    pub fn evaluate_cards(&self, game: &ZingGame) -> Vec<f64> {
+        let hand_size = game.state().players[self.player_index].hand.len();
+        let mut rng = self.decision_rng(game);
+        let mut totals = vec![0.; hand_size];
+        let mut iterations = 0;
+
+        let start = Instant::now();
+        loop {
+            let done = match self.budget {
+                SearchBudget::Iterations(max_iterations) => iterations >= max_iterations.max(1),
+                SearchBudget::Time(max_duration) => {
+                    iterations > 0 && start.elapsed() >= max_duration
+                }
+            };
+            if done {
+                break;
+            }
+
+            // all cards are evaluated with the same sampled distribution,
+            // which reduces the variance of their comparison
+            let sampled_game = self.determinize(game, &mut rng);
+            for (card_index, total) in totals.iter_mut().enumerate() {
+                *total += self.simulate(sampled_game.clone(), card_index, &mut rng);
+            }
+            iterations += 1;
+        }
+
+        totals
+            .into_iter()
+            .map(|total| total / iterations as f64)
+            .collect()
+    }
+}
+
+impl ZingAI for MonteCarloPlayer {
+    fn player_index(&self) -> usize {
+        self.player_index
+    }
+
+    fn choose_card(&self, game: &ZingGame) -> usize {
+        let mut best = (0, f64::NEG_INFINITY);
+        for (index, value) in self.evaluate_cards(game).into_iter().enumerate() {
+            if value > best.1 {
+                best = (index, value);
+            }
+        }
+        best.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MonteCarloPlayer, SearchBudget};
+    use crate::zing_ai::{RandomPlayer, ZingAI};
+    use crate::zing_game::ZingGame;
+
+    fn new_game(seed: u64) -> ZingGame {
+        let mut game = ZingGame::new_with_seed(vec!["A".into(), "B".into()], 1, seed).unwrap();
+        game.setup_game().unwrap();
+        game
+    }
+
+    fn card_names(game: &ZingGame, player: usize) -> Vec<String> {
+        let mut result: Vec<String> = game.state().players[player]
+            .hand
+            .iter()
+            .map(|card_state| card_state.card.short_str())
+            .collect();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn test_determinize_keeps_visible_cards() {
+        let game = new_game(3);
+        let player = MonteCarloPlayer::new(0, SearchBudget::Iterations(1), 42);
+        let mut rng = player.decision_rng(&game);
+        let sampled = player.determinize(&game, &mut rng);
+
+        assert_eq!(card_names(&sampled, 0), card_names(&game, 0));
+        assert_eq!(
+            sampled.state().stacks[1].cards.len(),
+            game.state().stacks[1].cards.len()
+        );
+        assert_eq!(
+            sampled.state().stacks[0].cards[0].card.short_str(),
+            game.state().stacks[0].cards[0].card.short_str()
+        );
+
+        // the hidden cards are only redistributed
+        let mut hidden = card_names(&game, 1);
+        let mut sampled_hidden = card_names(&sampled, 1);
+        for (original, sampled_stack) in [(&game, &mut hidden), (&sampled, &mut sampled_hidden)] {
+            sampled_stack.extend(
+                original.state().stacks[0]
+                    .cards
+                    .iter()
+                    .map(|card_state| card_state.card.short_str()),
+            );
+            sampled_stack.sort();
+        }
+        assert_eq!(hidden, sampled_hidden);
+    }
+
+    #[test]
+    fn test_deterministic_with_seed() {
+        let player1 = MonteCarloPlayer::new(0, SearchBudget::Iterations(10), 7);
+        let player2 = MonteCarloPlayer::new(0, SearchBudget::Iterations(10), 7);
+        let game = new_game(5);
+        assert_eq!(player1.evaluate_cards(&game), player2.evaluate_cards(&game));
+        assert_eq!(player1.choose_card(&game), player2.choose_card(&game));
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let player = MonteCarloPlayer::new(0, SearchBudget::Time(Default::default()), 7);
+        let game = new_game(5);
+        assert_eq!(player.evaluate_cards(&game).len(), 4);
+    }
+
+    #[test]
+    fn test_monte_carlo_beats_random() {
+        let mut monte_carlo_points = 0;
+        let mut random_points = 0;
+        for seed in 0..20 {
+            let mut game = new_game(seed);
+            let monte_carlo_index = seed as usize % 2;
+            let monte_carlo =
+                MonteCarloPlayer::new(monte_carlo_index, SearchBudget::Iterations(20), seed);
+            let random = RandomPlayer::new(1 - monte_carlo_index);
+
+            while let Some(current) = game.current_player() {
+                if current == monte_carlo_index {
+                    monte_carlo.auto_play(&mut game);
+                } else {
+                    random.auto_play(&mut game);
+                }
+            }
+
+            let points = game.points();
+            monte_carlo_points += points.total_points_of_player(monte_carlo_index);
+            random_points += points.total_points_of_player(1 - monte_carlo_index);
+        }
+        assert!(2 * monte_carlo_points > 3 * random_points);
+    }
+}
@@ -3,6 +3,16 @@ pub use sea_orm_migration::prelude::*;
 mod m20230924_133141_create_user_table;
 mod m20231004_134936_create_table_table;
 mod m20231005_153749_add_game_results_table;
+mod m20261017_120000_add_user_ai_strategy;
+mod m20261017_130000_add_take_back_agreement;
+mod m20261017_140000_add_game_replay;
+mod m20261017_150000_add_table_turn_time_limit;
+mod m20261017_160000_add_table_allow_spectators;
+mod m20261017_170000_create_chat_message_table;
+mod m20261017_180000_add_user_account;
+mod m20261017_190000_add_player_stats;
+mod m20261017_200000_add_game_events;
+mod m20261017_210000_add_table_allow_move_hints;
 
 pub struct Migrator;
 
@@ -13,6 +23,16 @@ impl MigratorTrait for Migrator {
             Box::new(m20230924_133141_create_user_table::Migration),
             Box::new(m20231004_134936_create_table_table::Migration),
             Box::new(m20231005_153749_add_game_results_table::Migration),
+            Box::new(m20261017_120000_add_user_ai_strategy::Migration),
+            Box::new(m20261017_130000_add_take_back_agreement::Migration),
+            Box::new(m20261017_140000_add_game_replay::Migration),
+            Box::new(m20261017_150000_add_table_turn_time_limit::Migration),
+            Box::new(m20261017_160000_add_table_allow_spectators::Migration),
+            Box::new(m20261017_170000_create_chat_message_table::Migration),
+            Box::new(m20261017_180000_add_user_account::Migration),
+            Box::new(m20261017_190000_add_player_stats::Migration),
+            Box::new(m20261017_200000_add_game_events::Migration),
+            Box::new(m20261017_210000_add_table_allow_move_hints::Migration),
         ]
     }
 }
@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230924_133141_create_user_table::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // computer players are users with an AI strategy (NULL for humans)
+        manager
+            .alter_table(
+                sea_orm_migration::prelude::Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(UserAiStrategy::AiStrategy).string())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                sea_orm_migration::prelude::Table::alter()
+                    .table(User::Table)
+                    .drop_column(UserAiStrategy::AiStrategy)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UserAiStrategy {
+    AiStrategy,
+}
@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // whether the player agrees to allow taking back cards at this table
+        manager
+            .alter_table(
+                sea_orm_migration::prelude::Table::alter()
+                    .table(TableJoin::Table)
+                    .add_column(
+                        ColumnDef::new(TableJoin::AllowTakeBacks)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                sea_orm_migration::prelude::Table::alter()
+                    .table(TableJoin::Table)
+                    .drop_column(TableJoin::AllowTakeBacks)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TableJoin {
+    Table,
+    AllowTakeBacks,
+}
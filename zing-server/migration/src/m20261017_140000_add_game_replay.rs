@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // replay of the finished game (see zing_game::replay::GameReplay)
+        manager
+            .alter_table(
+                sea_orm_migration::prelude::Table::alter()
+                    .table(GameResults::Table)
+                    .add_column(ColumnDef::new(GameResults::Replay).json_binary())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                sea_orm_migration::prelude::Table::alter()
+                    .table(GameResults::Table)
+                    .drop_column(GameResults::Replay)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum GameResults {
+    Table,
+    Replay,
+}
@@ -0,0 +1,74 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230924_133141_create_user_table::User;
+use crate::m20231004_134936_create_table_table::Table;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                sea_orm_migration::prelude::Table::create()
+                    .table(ChatMessage::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ChatMessage::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ChatMessage::TableId).integer().not_null())
+                    .col(ColumnDef::new(ChatMessage::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(ChatMessage::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    // exactly one of text / emote is set
+                    .col(ColumnDef::new(ChatMessage::Text).string())
+                    .col(ColumnDef::new(ChatMessage::Emote).string())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-chat_message-table_id")
+                            .from(ChatMessage::Table, ChatMessage::TableId)
+                            .to(Table::Table, Table::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-chat_message-user_id")
+                            .from(ChatMessage::Table, ChatMessage::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                sea_orm_migration::prelude::Table::drop()
+                    .table(ChatMessage::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ChatMessage {
+    Table,
+    Id,
+    TableId,
+    UserId,
+    CreatedAt,
+    Text,
+    Emote,
+}
@@ -0,0 +1,74 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230924_133141_create_user_table::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// NOTE: SQLite only supports one column per ALTER TABLE statement and cannot
+// add UNIQUE columns, hence the separate statements and index.
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // registered users have a unique user name and a password hash
+        // (both remain NULL for anonymous users)
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(UserAccount::Username).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(UserAccount::PasswordHash).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-user-username")
+                    .table(User::Table)
+                    .col(UserAccount::Username)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-user-username")
+                    .table(User::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        for column in [UserAccount::Username, UserAccount::PasswordHash] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(User::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum UserAccount {
+    Username,
+    PasswordHash,
+}
@@ -0,0 +1,93 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230924_133141_create_user_table::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// NOTE: SQLite only supports one column per ALTER TABLE statement.
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // number of Zings per team (including Jack Zings, which can be told
+        // apart via the Zing points); unknown for games finished before
+        for column in [GameResults::Zings0, GameResults::Zings1] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(GameResults::Table)
+                        .add_column(ColumnDef::new(column).integer().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        // Elo rating (see zing_game::rating) and number of rated games
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(
+                        ColumnDef::new(UserRating::Rating)
+                            .double()
+                            .not_null()
+                            .default(1500.),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(
+                        ColumnDef::new(UserRating::RatedGames)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [UserRating::Rating, UserRating::RatedGames] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(User::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        for column in [GameResults::Zings0, GameResults::Zings1] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(GameResults::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum GameResults {
+    Table,
+    Zings0,
+    Zings1,
+}
+
+#[derive(DeriveIden)]
+enum UserRating {
+    Rating,
+    RatedGames,
+}
@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // tricks, Zings, final sweep and redeals of each game (see
+        // zing_game::zing_game::ZingEvent); unknown for games finished before
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(GameResults::Table)
+                    .add_column(ColumnDef::new(GameResults::Events).json_binary().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(GameResults::Table)
+                    .drop_column(GameResults::Events)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum GameResults {
+    Table,
+    Events,
+}
@@ -0,0 +1,42 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20231004_134936_create_table_table::Table;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // whether players are told which of their cards take tricks
+        manager
+            .alter_table(
+                sea_orm_migration::prelude::Table::alter()
+                    .table(Table::Table)
+                    .add_column(
+                        ColumnDef::new(TableAllowMoveHints::AllowMoveHints)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                sea_orm_migration::prelude::Table::alter()
+                    .table(Table::Table)
+                    .drop_column(TableAllowMoveHints::AllowMoveHints)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TableAllowMoveHints {
+    AllowMoveHints,
+}
@@ -1,6 +1,6 @@
 use std::sync::RwLock;
 
-use zing_game::client_notification::ClientNotification;
+use zing_game::client_notification::{ClientNotification, NotificationMessage, PROTOCOL_VERSION};
 
 use crate::{entities, util::random_id, ws_notifications::NotificationSenderHandle};
 
@@ -39,6 +39,10 @@ impl ClientConnection {
         &self.user.token
     }
 
+    pub fn client_user_name(&self) -> &str {
+        &self.user.name
+    }
+
     pub fn serialized_notification(&self, msg: String) -> SerializedNotification {
         SerializedNotification {
             connection_id: self.connection_id.clone(),
@@ -50,11 +54,23 @@ impl ClientConnection {
     pub fn client_notification(
         &self,
         client_notification: &ClientNotification,
+        seq: u64,
     ) -> SerializedNotification {
-        self.serialized_notification(serde_json::to_string(client_notification).unwrap())
+        self.serialized_notification(notification_json(client_notification, seq))
     }
 }
 
+/// Serializes a table notification (wrapped with protocol version and sequence
+/// number).
+pub fn notification_json(client_notification: &ClientNotification, seq: u64) -> String {
+    serde_json::to_string(&NotificationMessage {
+        version: PROTOCOL_VERSION,
+        seq,
+        notification: client_notification,
+    })
+    .unwrap()
+}
+
 #[derive(Default)]
 pub struct ClientConnections(Vec<ClientConnection>);
 
@@ -71,6 +87,14 @@ impl ClientConnections {
         self.0.push(ClientConnection::new(user, sender));
     }
 
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn last(&self) -> std::option::Option<&'_ ClientConnection> {
         self.0.last()
     }
@@ -0,0 +1,59 @@
+//! Time source of the server's timers (delays of computer players and between
+//! the games of a match, turn time limits), so that tests can advance the
+//! time instead of waiting for it to pass.
+
+use std::{sync::Arc, time::Duration};
+
+use tokio::{sync::watch, time::Instant};
+
+/// Clock measuring time as the duration since its creation.
+#[derive(Clone)]
+pub struct Clock(Arc<ClockKind>);
+
+enum ClockKind {
+    System(Instant),
+    /// only moves forward via [Clock::advance]
+    Manual(watch::Sender<Duration>),
+}
+
+impl Clock {
+    pub fn system() -> Self {
+        Self(Arc::new(ClockKind::System(Instant::now())))
+    }
+
+    pub fn manual() -> Self {
+        Self(Arc::new(ClockKind::Manual(watch::Sender::new(
+            Duration::ZERO,
+        ))))
+    }
+
+    pub fn now(&self) -> Duration {
+        match self.0.as_ref() {
+            ClockKind::System(start) => start.elapsed(),
+            ClockKind::Manual(time) => *time.borrow(),
+        }
+    }
+
+    /// Waits until the given time (as returned by [Self::now]) is reached.
+    pub async fn sleep_until(&self, deadline: Duration) {
+        match self.0.as_ref() {
+            ClockKind::System(start) => tokio::time::sleep_until(*start + deadline).await,
+            ClockKind::Manual(time) => {
+                // cannot fail, since the sender is kept alive by self
+                let _ = time.subscribe().wait_for(|now| *now >= deadline).await;
+            }
+        }
+    }
+
+    /// Moves a manual clock forward, waking all timers that have expired.
+    ///
+    /// # Panics
+    ///
+    /// If called on the system clock.
+    pub fn advance(&self, duration: Duration) {
+        match self.0.as_ref() {
+            ClockKind::System(_) => panic!("the system clock cannot be advanced"),
+            ClockKind::Manual(time) => time.send_modify(|now| *now += duration),
+        }
+    }
+}
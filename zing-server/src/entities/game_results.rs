@@ -14,6 +14,12 @@ pub struct Model {
     pub card_count_points1: i32,
     pub zing_points0: i32,
     pub zing_points1: i32,
+    pub zings0: Option<i32>,
+    pub zings1: Option<i32>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub replay: Option<Json>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub events: Option<Json>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
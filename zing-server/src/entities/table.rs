@@ -11,16 +11,27 @@ pub struct Model {
     #[sea_orm(column_type = "JsonBinary", nullable)]
     pub game: Option<Json>,
     pub token: String,
+    pub turn_time_limit: Option<i32>,
+    pub allow_spectators: bool,
+    pub allow_move_hints: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::chat_message::Entity")]
+    ChatMessage,
     #[sea_orm(has_many = "super::game_results::Entity")]
     GameResults,
     #[sea_orm(has_many = "super::table_join::Entity")]
     TableJoin,
 }
 
+impl Related<super::chat_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChatMessage.def()
+    }
+}
+
 impl Related<super::game_results::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::GameResults.def()
@@ -10,6 +10,7 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub table_id: i32,
     pub table_pos: i32,
+    pub allow_take_backs: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
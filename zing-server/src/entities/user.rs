@@ -2,7 +2,7 @@
 
 use sea_orm::entity::prelude::*;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "user")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -11,14 +11,29 @@ pub struct Model {
     pub name: String,
     pub logged_in: bool,
     pub last_seen: DateTimeWithTimeZone,
+    pub ai_strategy: Option<String>,
+    #[sea_orm(unique)]
+    pub username: Option<String>,
+    pub password_hash: Option<String>,
+    #[sea_orm(column_type = "Double")]
+    pub rating: f64,
+    pub rated_games: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::chat_message::Entity")]
+    ChatMessage,
     #[sea_orm(has_many = "super::table_join::Entity")]
     TableJoin,
 }
 
+impl Related<super::chat_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChatMessage.def()
+    }
+}
+
 impl Related<super::table_join::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::TableJoin.def()
@@ -1,23 +1,130 @@
-use axum::{http, response::IntoResponse};
+use axum::{http, response::IntoResponse, Json};
+use sea_orm::DbErr;
+use zing_game::{
+    client_command::{CommandError, CommandErrorKind},
+    client_notification::PROTOCOL_VERSION,
+    error_code::{ErrorBody, ErrorCode},
+    replay::ReplayError,
+    zing_game::PlayError,
+};
 
+#[derive(Debug)]
 pub enum GameError {
-    Unauthorized(&'static str),
-    NotFound(&'static str),
-    BadRequest(&'static str),
-    Conflict(&'static str),
-    DBError(&'static str),
+    /// request cannot be fulfilled; the code determines the HTTP status
+    Request(ErrorCode, &'static str),
+    /// database access failed; the cause is logged, but not sent to clients
+    DBError(&'static str, DbErr),
+    /// unexpected failure unrelated to the database (e.g. password hashing)
+    Internal(&'static str),
+    InvalidReplay(ReplayError),
+    /// client speaks another version of the notification protocol
+    UnsupportedProtocolVersion(u32),
 }
 
-impl IntoResponse for GameError {
-    fn into_response(self) -> axum::response::Response {
+impl From<PlayError> for GameError {
+    fn from(error: PlayError) -> Self {
+        let code = match error {
+            PlayError::NotPlayersTurn => ErrorCode::NotYourTurn,
+            PlayError::InvalidCardIndex => ErrorCode::InvalidCardIndex,
+            PlayError::NoTurnToTakeBack => ErrorCode::NothingToTakeBack,
+            PlayError::IllegalAction(_) => ErrorCode::IllegalAction,
+            // not caused by the request, but by an inconsistent game state
+            PlayError::InvalidAction(_) => return GameError::Internal(error.message()),
+        };
+        GameError::Request(code, error.message())
+    }
+}
+
+fn status_code(kind: CommandErrorKind) -> http::StatusCode {
+    match kind {
+        CommandErrorKind::Unauthorized => http::StatusCode::UNAUTHORIZED,
+        CommandErrorKind::NotFound => http::StatusCode::NOT_FOUND,
+        CommandErrorKind::BadRequest => http::StatusCode::BAD_REQUEST,
+        CommandErrorKind::Conflict => http::StatusCode::CONFLICT,
+        CommandErrorKind::RateLimited => http::StatusCode::TOO_MANY_REQUESTS,
+        CommandErrorKind::Internal => http::StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Error code for error responses that do not come from a [GameError] (e.g.
+/// unknown routes or malformed JSON rejected by axum's extractors).
+pub fn code_for_status(status: http::StatusCode) -> ErrorCode {
+    match status {
+        http::StatusCode::UNAUTHORIZED => ErrorCode::NotLoggedIn,
+        http::StatusCode::NOT_FOUND => ErrorCode::NotFound,
+        http::StatusCode::TOO_MANY_REQUESTS => ErrorCode::RateLimited,
+        status if status.is_client_error() => ErrorCode::InvalidRequest,
+        _ => ErrorCode::InternalError,
+    }
+}
+
+fn unsupported_version_message(version: u32) -> String {
+    format!(
+        "unsupported notification protocol version {version} (server speaks version {PROTOCOL_VERSION})"
+    )
+}
+
+/// Underlying cause of a failed request, attached to the response (together
+/// with its [ErrorBody]) so that it can be logged with the request id (see
+/// [crate::request_id::request_id_layer]).
+#[derive(Clone, Debug)]
+pub struct ErrorCause(pub String);
+
+impl GameError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            GameError::Request(code, _) => *code,
+            GameError::DBError(..) => ErrorCode::DatabaseError,
+            GameError::Internal(_) => ErrorCode::InternalError,
+            GameError::InvalidReplay(_) => ErrorCode::InvalidReplay,
+            GameError::UnsupportedProtocolVersion(_) => ErrorCode::UnsupportedProtocolVersion,
+        }
+    }
+
+    pub fn message(&self) -> String {
         match self {
-            GameError::Unauthorized(msg) => (http::StatusCode::UNAUTHORIZED, msg).into_response(),
-            GameError::NotFound(msg) => (http::StatusCode::NOT_FOUND, msg).into_response(),
-            GameError::BadRequest(msg) => (http::StatusCode::BAD_REQUEST, msg).into_response(),
-            GameError::Conflict(msg) => (http::StatusCode::CONFLICT, msg).into_response(),
-            GameError::DBError(msg) => {
-                (http::StatusCode::INTERNAL_SERVER_ERROR, msg).into_response()
+            GameError::Request(_, msg) | GameError::DBError(msg, _) | GameError::Internal(msg) => {
+                (*msg).to_owned()
             }
+            GameError::InvalidReplay(error) => error.to_string(),
+            GameError::UnsupportedProtocolVersion(version) => unsupported_version_message(*version),
+        }
+    }
+
+    /// Details that are logged, but not sent to clients.
+    pub fn cause(&self) -> Option<String> {
+        match self {
+            GameError::DBError(_, err) => Some(err.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for GameError {
+    fn into_response(self) -> axum::response::Response {
+        let code = self.code();
+        let body = ErrorBody {
+            code,
+            message: self.message(),
+            // filled in by the request id layer
+            request_id: None,
+        };
+        let mut response = (status_code(code.kind()), Json(body.clone())).into_response();
+        response.extensions_mut().insert(body);
+        if let Some(cause) = self.cause() {
+            response.extensions_mut().insert(ErrorCause(cause));
+        }
+        response
+    }
+}
+
+impl From<GameError> for CommandError {
+    fn from(error: GameError) -> Self {
+        let code = error.code();
+        CommandError {
+            kind: code.kind(),
+            code,
+            message: error.message(),
         }
     }
 }
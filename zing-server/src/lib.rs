@@ -0,0 +1,617 @@
+//! Zing game server: REST API and WebSocket notifications on top of the
+//! game logic in [zing_game], with users, tables and game results stored in a
+//! database (see [storage]).
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{
+        ws::Message, DefaultBodyLimit, FromRequestParts, Path, Query, State, WebSocketUpgrade,
+    },
+    http::request::Parts,
+    middleware,
+    response::IntoResponse,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use futures::StreamExt;
+use game_error::GameError;
+use maintenance::Metrics;
+use serde::{de::DeserializeOwned, Deserialize};
+use stats::{LeaderboardEntry, UserStats};
+use tower_cookies::cookie::SameSite;
+use tower_cookies::{Cookie, CookieManagerLayer, Cookies};
+use tower_http::services::{ServeDir, ServeFile};
+use tracing::{debug, info, warn};
+use ws_notifications::NotificationSenderHandle;
+use zing_game::{
+    card_action::CardAction,
+    client_command::{ClientCommand, CommandError, CommandErrorKind},
+    client_notification::{ClientNotification, PROTOCOL_VERSION},
+    error_code::ErrorCode,
+    game::GameState,
+    replay::{GameReplay, MatchReplay, ReplayError},
+    zing_ai::AI_NAMES,
+    zing_game::MoveHint,
+    zing_match::ZingMatch,
+};
+
+mod client_connection;
+pub mod clock;
+mod entities;
+mod game_error;
+pub mod maintenance;
+mod password;
+mod request_id;
+mod stats;
+pub mod storage;
+mod table;
+mod util;
+mod ws_notifications;
+mod zing_state;
+
+pub use zing_state::ZingState;
+
+/// Router with all REST and WebSocket endpoints of the server.
+pub fn app(state: Arc<ZingState>) -> Router {
+    Router::new()
+        .route_service("/", ServeFile::new("zing-server/assets/index.html"))
+        .route("/login", post(login).get(whoami).delete(logout))
+        .route("/account", post(register))
+        .route("/table", post(create_table).get(list_tables))
+        .route("/ws", get(global_ws_handler))
+        .route("/bot", get(list_bot_strategies))
+        .route("/metrics", get(metrics))
+        .route("/stats", get(own_stats))
+        .route("/stats/{username}", get(user_stats))
+        .route("/leaderboard", get(leaderboard))
+        .route(
+            "/table/{table_id}",
+            post(join_table).get(get_table_info).delete(leave_table),
+        )
+        .route(
+            "/table/{table_id}/game",
+            post(start_game).get(game_status).delete(finish_game),
+        )
+        .route("/table/{table_id}/game/play", post(play_card))
+        .route("/table/{table_id}/game/action", post(perform_action))
+        .route("/table/{table_id}/game/undo", post(take_back))
+        .route("/table/{table_id}/game/hints", get(move_hints))
+        .route("/table/{table_id}/take_backs", put(set_take_back_agreement))
+        .route("/table/{table_id}/spectators", put(set_allow_spectators))
+        .route("/table/{table_id}/hints", put(set_allow_move_hints))
+        .route("/table/{table_id}/bot", post(add_bot))
+        .route("/table/{table_id}/bot/{player_index}", delete(remove_bot))
+        .route("/table/{table_id}/replay", get(match_replay))
+        .route("/table/{table_id}/replay/{game_index}", get(game_replay))
+        .route(
+            "/replay/game",
+            post(import_game_replay).layer(DefaultBodyLimit::max(REPLAY_BODY_LIMIT)),
+        )
+        .route(
+            "/replay/match",
+            post(import_match_replay).layer(DefaultBodyLimit::max(REPLAY_BODY_LIMIT)),
+        )
+        .route("/table/{table_id}/ws", get(table_ws_handler))
+        .nest_service(
+            "/zing_ui_lib.js",
+            ServeFile::new("zing-ui-lib/pkg/zing_ui_lib.js"),
+        )
+        .nest_service(
+            "/zing_ui_lib_bg.wasm",
+            ServeFile::new("zing-ui-lib/pkg/zing_ui_lib_bg.wasm"),
+        )
+        .nest_service("/assets", ServeDir::new("zing-ui-lib/assets"))
+        .with_state(state)
+        .layer(middleware::from_fn(request_id::request_id_layer))
+        .layer(CookieManagerLayer::new())
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    name: String,
+    /// only for registered users; without password, a new anonymous user is
+    /// created
+    password: Option<String>,
+}
+
+const LOGIN_COOKIE: &str = "login_id";
+
+async fn login(
+    State(state): State<Arc<ZingState>>,
+    cookies: Cookies,
+    Json(login_request): Json<LoginRequest>,
+) -> Result<String, GameError> {
+    let mut user_name = login_request.name;
+    if user_name.is_empty() {
+        return Err(GameError::Request(
+            ErrorCode::InvalidName,
+            "name must not be empty",
+        ));
+    }
+    let login_token = match login_request.password {
+        Some(password) => {
+            let (login_token, name) = state.login_with_password(&user_name, &password).await?;
+            user_name = name;
+            login_token
+        }
+        None => state.login(&user_name).await?,
+    };
+    info!("Logged in {} as {}", user_name, login_token);
+
+    // TODO: report error if LOGIN_COOKIE is already set (and valid)?
+
+    let mut login_cookie = Cookie::new(LOGIN_COOKIE, login_token);
+    login_cookie.set_same_site(SameSite::Strict);
+    cookies.add(login_cookie);
+    Ok(user_name)
+}
+
+#[derive(Deserialize)]
+struct AccountRequest {
+    name: String,
+    password: String,
+}
+
+/// Upgrades the current (anonymous) user into a registered account.
+async fn register(
+    State(state): State<Arc<ZingState>>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    Json(account_request): Json<AccountRequest>,
+) -> Result<String, GameError> {
+    state
+        .register(user, &account_request.name, &account_request.password)
+        .await?;
+    info!("Registered user {}", account_request.name);
+    Ok(account_request.name)
+}
+
+async fn logout(
+    State(state): State<Arc<ZingState>>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    cookies: Cookies,
+) -> Result<(), GameError> {
+    state.logout(user.clone()).await?;
+    info!("Logged out {}", user.name);
+
+    let mut login_cookie = Cookie::new(LOGIN_COOKIE, "");
+    login_cookie.set_same_site(SameSite::Strict);
+    cookies.remove(login_cookie);
+    Ok(())
+}
+
+struct LoginToken(String);
+
+impl<S> FromRequestParts<S> for LoginToken
+where
+    S: Send + Sync,
+{
+    type Rejection = GameError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let cookies = Cookies::from_request_parts(parts, state)
+            .await
+            .map_err(|_| {
+                GameError::Request(
+                    ErrorCode::NotLoggedIn,
+                    "internal error trying to extract cookies",
+                )
+            })?;
+
+        let login_token = cookies
+            .get(LOGIN_COOKIE)
+            .ok_or(GameError::Request(
+                ErrorCode::NotLoggedIn,
+                "login first (id cookie missing)",
+            ))?
+            .value()
+            .to_string();
+
+        Ok(LoginToken(login_token))
+    }
+}
+
+struct AuthenticatedUser(entities::user::Model);
+
+impl FromRequestParts<Arc<ZingState>> for AuthenticatedUser {
+    type Rejection = GameError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<ZingState>,
+    ) -> Result<Self, Self::Rejection> {
+        let LoginToken(login_token) = LoginToken::from_request_parts(parts, state).await?;
+
+        let user = state.get_user_with_token(&login_token).await?;
+        state.touch_user(&user).await?;
+
+        Ok(AuthenticatedUser(user))
+    }
+}
+
+async fn whoami(AuthenticatedUser(user): AuthenticatedUser) -> Result<String, GameError> {
+    Ok(user.name.clone())
+}
+
+/// Longest allowed turn time limit (one hour)
+const MAX_TURN_TIME_LIMIT: u32 = 3600;
+
+#[derive(Deserialize)]
+struct TableSettings {
+    /// time limit per turn in seconds
+    turn_time_limit: Option<u32>,
+}
+
+async fn create_table(
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(state): State<Arc<ZingState>>,
+    settings: Option<Json<TableSettings>>,
+) -> Result<impl IntoResponse, GameError> {
+    let turn_time_limit = settings.and_then(|Json(settings)| settings.turn_time_limit);
+    if turn_time_limit.is_some_and(|secs| secs == 0 || secs > MAX_TURN_TIME_LIMIT) {
+        return Err(GameError::Request(
+            ErrorCode::InvalidTurnTimeLimit,
+            "turn time limit must be between 1 and 3600 seconds",
+        ));
+    }
+    state.create_table(user, turn_time_limit).await
+}
+
+async fn list_tables(
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(state): State<Arc<ZingState>>,
+) -> Result<impl IntoResponse, GameError> {
+    state.list_tables(user).await
+}
+
+async fn get_table_info(
+    AuthenticatedUser(_user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+) -> Result<impl IntoResponse, GameError> {
+    state.get_table_info(&table_id).await
+}
+
+async fn join_table(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+) -> Result<impl IntoResponse, GameError> {
+    state.join_table(&user, &table_id).await
+}
+
+async fn leave_table(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+) -> Result<(), GameError> {
+    state.leave_table(&user, &table_id).await
+}
+
+async fn start_game(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+) -> Result<(), GameError> {
+    state.start_game(&user, &table_id).await
+}
+
+async fn game_status(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+) -> Result<Json<GameState>, GameError> {
+    state.game_status(&user, &table_id).await
+}
+
+async fn finish_game(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+) -> Result<(), GameError> {
+    state.finish_game(&user, &table_id).await
+}
+
+async fn metrics(State(state): State<Arc<ZingState>>) -> Json<Metrics> {
+    Json(state.metrics())
+}
+
+async fn own_stats(
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(state): State<Arc<ZingState>>,
+) -> Result<Json<UserStats>, GameError> {
+    Ok(Json(state.user_stats(&user).await?))
+}
+
+async fn user_stats(
+    State(state): State<Arc<ZingState>>,
+    Path(username): Path<String>,
+) -> Result<Json<UserStats>, GameError> {
+    Ok(Json(state.registered_user_stats(&username).await?))
+}
+
+async fn leaderboard(
+    State(state): State<Arc<ZingState>>,
+) -> Result<Json<Vec<LeaderboardEntry>>, GameError> {
+    Ok(Json(state.leaderboard().await?))
+}
+
+async fn list_bot_strategies() -> Json<Vec<&'static str>> {
+    Json(AI_NAMES.to_vec())
+}
+
+#[derive(Deserialize)]
+struct BotRequest {
+    strategy: String,
+}
+
+async fn add_bot(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+    Json(bot_request): Json<BotRequest>,
+) -> Result<impl IntoResponse, GameError> {
+    state.add_bot(&user, &table_id, &bot_request.strategy).await
+}
+
+async fn remove_bot(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path((table_id, player_index)): Path<(String, usize)>,
+    State(state): State<Arc<ZingState>>,
+) -> Result<(), GameError> {
+    state.remove_bot(&user, &table_id, player_index).await
+}
+
+#[derive(Deserialize)]
+struct GameAction {
+    card_index: usize,
+}
+
+async fn play_card(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+    Json(game_action): Json<GameAction>,
+) -> Result<(), GameError> {
+    state
+        .play_card(&user, &table_id, game_action.card_index)
+        .await
+}
+
+/// Plays the card described by a full card action, which is checked against
+/// the rules (see [ActionRules](zing_game::card_action::ActionRules)).
+async fn perform_action(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+    Json(action): Json<CardAction>,
+) -> Result<(), GameError> {
+    state.perform_action(&user, &table_id, &action).await
+}
+
+#[derive(Deserialize)]
+struct TakeBackAgreement {
+    allow: bool,
+}
+
+async fn set_take_back_agreement(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+    Json(agreement): Json<TakeBackAgreement>,
+) -> Result<(), GameError> {
+    state
+        .set_take_back_agreement(&user, &table_id, agreement.allow)
+        .await
+}
+
+#[derive(Deserialize)]
+struct SpectatorSetting {
+    allow: bool,
+}
+
+async fn set_allow_spectators(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+    Json(setting): Json<SpectatorSetting>,
+) -> Result<(), GameError> {
+    state
+        .set_allow_spectators(&user, &table_id, setting.allow)
+        .await
+}
+
+async fn move_hints(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+) -> Result<Json<Vec<MoveHint>>, GameError> {
+    Ok(Json(state.move_hints(&user, &table_id).await?))
+}
+
+#[derive(Deserialize)]
+struct MoveHintSetting {
+    allow: bool,
+}
+
+async fn set_allow_move_hints(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+    Json(setting): Json<MoveHintSetting>,
+) -> Result<(), GameError> {
+    state
+        .set_allow_move_hints(&user, &table_id, setting.allow)
+        .await
+}
+
+async fn take_back(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+) -> Result<(), GameError> {
+    state.take_back(&user, &table_id).await
+}
+
+async fn match_replay(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    State(state): State<Arc<ZingState>>,
+) -> Result<Json<MatchReplay>, GameError> {
+    state.match_replay(&user, &table_id).await
+}
+
+async fn game_replay(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path((table_id, game_index)): Path<(String, usize)>,
+    State(state): State<Arc<ZingState>>,
+) -> Result<Json<GameReplay>, GameError> {
+    state.game_replay(&user, &table_id, game_index).await
+}
+
+/// Maximum size of uploaded replays in bytes; a match replay has some 50 kB
+/// per game, and a match is decided after a handful of games.
+const REPLAY_BODY_LIMIT: usize = 1024 * 1024;
+
+fn parse_replay<T: DeserializeOwned>(json: &str) -> Result<T, GameError> {
+    serde_json::from_str(json)
+        .map_err(|err| GameError::InvalidReplay(ReplayError::Format(err.to_string())))
+}
+
+/// Verifies an uploaded game replay and returns the resulting game state.
+async fn import_game_replay(
+    AuthenticatedUser(_user): AuthenticatedUser,
+    body: String,
+) -> Result<Json<GameState>, GameError> {
+    let replay: GameReplay = parse_replay(&body)?;
+    // replaying all moves is CPU-bound
+    let game = tokio::task::spawn_blocking(move || replay.verify())
+        .await
+        .map_err(|_| GameError::Internal("replay verification failed unexpectedly"))?
+        .map_err(GameError::InvalidReplay)?;
+    Ok(Json(game.state().clone()))
+}
+
+/// Verifies an uploaded match replay and returns the resulting match.
+async fn import_match_replay(
+    AuthenticatedUser(_user): AuthenticatedUser,
+    body: String,
+) -> Result<Json<ZingMatch>, GameError> {
+    let replay: MatchReplay = parse_replay(&body)?;
+    // replaying all moves is CPU-bound
+    let zing_match = tokio::task::spawn_blocking(move || replay.verify())
+        .await
+        .map_err(|_| GameError::Internal("replay verification failed unexpectedly"))?
+        .map_err(GameError::InvalidReplay)?;
+    Ok(Json(zing_match))
+}
+
+async fn global_ws_handler(
+    AuthenticatedUser(user): AuthenticatedUser,
+    State(state): State<Arc<ZingState>>,
+    ws: WebSocketUpgrade,
+) -> Result<impl IntoResponse, GameError> {
+    Ok(ws.on_upgrade(move |socket| {
+        // the global connection is only used for notifications
+        let (socket_sender, _socket_receiver) = socket.split();
+        let sender = NotificationSenderHandle::new(socket_sender);
+
+        async move { state.add_user_global_connection(user, sender).await }
+    }))
+}
+
+#[derive(Deserialize)]
+struct TableConnectionParams {
+    /// notification protocol version spoken by the client
+    version: Option<u32>,
+    /// sequence number of the last notification received before reconnecting
+    resume_from: Option<u64>,
+}
+
+async fn table_ws_handler(
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(table_id): Path<String>,
+    Query(params): Query<TableConnectionParams>,
+    State(state): State<Arc<ZingState>>,
+    ws: WebSocketUpgrade,
+) -> Result<impl IntoResponse, GameError> {
+    if let Some(version) = params
+        .version
+        .filter(|version| *version != PROTOCOL_VERSION)
+    {
+        return Err(GameError::UnsupportedProtocolVersion(version));
+    }
+
+    // players and (if allowed) spectators may connect
+    state.check_table_viewer(&user, &table_id).await?;
+
+    Ok(ws.on_upgrade(move |socket| async move {
+        let (socket_sender, mut socket_receiver) = socket.split();
+        let sender = NotificationSenderHandle::new(socket_sender);
+
+        let Some(connection_id) = state
+            .add_user_table_connection(
+                user.clone(),
+                table_id.clone(),
+                sender.clone(),
+                params.resume_from,
+            )
+            .await
+        else {
+            return;
+        };
+
+        // execute commands until the client disconnects
+        while let Some(Ok(msg)) = socket_receiver.next().await {
+            let Message::Text(json) = msg else {
+                continue;
+            };
+            let (request_id, result) = match serde_json::from_str::<ClientCommand>(&json) {
+                Ok(ClientCommand {
+                    request_id,
+                    command,
+                }) => (
+                    request_id,
+                    state
+                        .handle_command(&user, &table_id, command)
+                        .await
+                        .map_err(|err| {
+                            if let Some(cause) = err.cause() {
+                                warn!("command {request_id} of {} failed: {cause}", user.name);
+                            }
+                            CommandError::from(err)
+                        }),
+                ),
+                Err(err) => {
+                    // we can only reply if the request id is readable
+                    let Some(request_id) = serde_json::from_str::<serde_json::Value>(&json)
+                        .ok()
+                        .and_then(|value| value["request_id"].as_u64())
+                    else {
+                        debug!("ignoring invalid client command: {err}");
+                        continue;
+                    };
+                    (
+                        request_id,
+                        Err(CommandError {
+                            kind: CommandErrorKind::BadRequest,
+                            code: ErrorCode::InvalidRequest,
+                            message: err.to_string(),
+                        }),
+                    )
+                }
+            };
+            let notification = ClientNotification::CommandResult(request_id, result);
+            let Some(json) = state.table_notification_json(&table_id, &notification) else {
+                break;
+            };
+            if sender.send(json).await.is_err() {
+                break;
+            }
+        }
+
+        state
+            .remove_table_connection(&table_id, connection_id)
+            .await;
+    }))
+}
@@ -0,0 +1,99 @@
+//! Settings and counters of the background maintenance task (see
+//! [crate::zing_state::ZingState::run_maintenance]), which expires idle login
+//! sessions, unloads inactive tables from memory and deletes abandoned tables
+//! and users.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+#[derive(Clone, Debug)]
+pub struct MaintenanceConfig {
+    /// how often the maintenance task runs
+    pub interval: Duration,
+    /// login sessions expire after this time without requests (users with
+    /// open WebSocket connections count as active)
+    pub session_timeout: Duration,
+    /// loaded tables without connections are unloaded after this time
+    pub table_unload_time: Duration,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            session_timeout: Duration::from_secs(24 * 60 * 60),
+            table_unload_time: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+/// Totals of what the maintenance task has cleaned up since server start.
+#[derive(Default)]
+pub struct MaintenanceMetrics {
+    runs: AtomicU64,
+    sessions_expired: AtomicU64,
+    tables_unloaded: AtomicU64,
+    tables_deleted: AtomicU64,
+    users_deleted: AtomicU64,
+}
+
+/// Snapshot of the [MaintenanceMetrics] plus current resource usage, as
+/// reported by `GET /metrics`.
+#[derive(Serialize)]
+pub struct Metrics {
+    pub maintenance_runs: u64,
+    pub sessions_expired: u64,
+    pub tables_unloaded: u64,
+    pub tables_deleted: u64,
+    pub users_deleted: u64,
+    pub loaded_tables: usize,
+    pub connections: usize,
+}
+
+/// What a single maintenance run has cleaned up.
+#[derive(Default, Debug)]
+pub struct MaintenanceRun {
+    pub sessions_expired: u64,
+    pub tables_unloaded: u64,
+    pub tables_deleted: u64,
+    pub users_deleted: u64,
+}
+
+impl MaintenanceRun {
+    pub fn is_empty(&self) -> bool {
+        self.sessions_expired == 0
+            && self.tables_unloaded == 0
+            && self.tables_deleted == 0
+            && self.users_deleted == 0
+    }
+}
+
+impl MaintenanceMetrics {
+    pub fn record(&self, run: &MaintenanceRun) {
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        self.sessions_expired
+            .fetch_add(run.sessions_expired, Ordering::Relaxed);
+        self.tables_unloaded
+            .fetch_add(run.tables_unloaded, Ordering::Relaxed);
+        self.tables_deleted
+            .fetch_add(run.tables_deleted, Ordering::Relaxed);
+        self.users_deleted
+            .fetch_add(run.users_deleted, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self, loaded_tables: usize, connections: usize) -> Metrics {
+        Metrics {
+            maintenance_runs: self.runs.load(Ordering::Relaxed),
+            sessions_expired: self.sessions_expired.load(Ordering::Relaxed),
+            tables_unloaded: self.tables_unloaded.load(Ordering::Relaxed),
+            tables_deleted: self.tables_deleted.load(Ordering::Relaxed),
+            users_deleted: self.users_deleted.load(Ordering::Relaxed),
+            loaded_tables,
+            connections,
+        }
+    }
+}
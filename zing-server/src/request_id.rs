@@ -0,0 +1,89 @@
+//! Request ids: every response carries an `x-request-id` header (taken from
+//! the request if the client or a proxy sent a usable one), and every error
+//! response a JSON [ErrorBody] containing the same id, so that problems
+//! reported by users can be found in the server logs.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::{debug, warn};
+use zing_game::error_code::ErrorBody;
+
+use crate::{
+    game_error::{code_for_status, ErrorCause},
+    util::random_id,
+};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest error message taken over from responses not created by
+/// [GameError](crate::game_error::GameError) (e.g. axum's JSON rejections).
+const MAX_MESSAGE_LENGTH: usize = 4096;
+
+fn incoming_request_id(request: &Request) -> Option<String> {
+    let id = request.headers().get(REQUEST_ID_HEADER)?.to_str().ok()?;
+    let valid = !id.is_empty()
+        && id.len() <= 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| id.to_owned())
+}
+
+pub async fn request_id_layer(request: Request, next: Next) -> Response {
+    let request_id = incoming_request_id(&request).unwrap_or_else(random_id);
+    let method = request.method().clone();
+    let uri = request.uri().clone();
+
+    let mut response = next.run(request).await;
+
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        let cause = response.extensions().get::<ErrorCause>().cloned();
+        let mut body = match response.extensions().get::<ErrorBody>() {
+            Some(body) => body.clone(),
+            None => {
+                let bytes = to_bytes(
+                    std::mem::replace(response.body_mut(), Body::empty()),
+                    MAX_MESSAGE_LENGTH,
+                )
+                .await
+                .unwrap_or_default();
+                ErrorBody {
+                    code: code_for_status(status),
+                    message: String::from_utf8_lossy(&bytes).into_owned(),
+                    request_id: None,
+                }
+            }
+        };
+        body.request_id = Some(request_id.clone());
+
+        match cause {
+            Some(ErrorCause(cause)) => {
+                warn!(
+                    "{method} {uri} failed [{request_id}]: {} ({cause})",
+                    body.message
+                )
+            }
+            None if status.is_server_error() => {
+                warn!("{method} {uri} failed [{request_id}]: {}", body.message)
+            }
+            None => debug!("{method} {uri} failed [{request_id}]: {}", body.message),
+        }
+
+        let (mut parts, _) = response.into_parts();
+        parts.headers.remove(header::CONTENT_LENGTH);
+        parts.headers.remove(header::CONTENT_TYPE);
+        response = (parts, Json(body)).into_response();
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
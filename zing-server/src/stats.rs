@@ -0,0 +1,182 @@
+//! Per-player statistics computed from the stored game results, and the
+//! leaderboard of Elo ratings (see [zing_game::rating]).
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use zing_game::{
+    zing_game::{ZingEvent, ZingEventSummary, ZingGame, ZingGamePoints},
+    zing_match::ZingMatch,
+};
+
+use crate::entities;
+
+#[derive(Serialize)]
+pub struct UserStats {
+    pub name: String,
+    pub rating: f64,
+    pub rated_games: u32,
+    pub games_played: u32,
+    pub games_won: u32,
+    /// only matches that have been decided
+    pub matches_played: u32,
+    pub matches_won: u32,
+    /// card points of the player's team per game
+    pub average_card_points: f64,
+    /// Zings (including Jack Zings) of the player's team; not known for
+    /// games finished before Zings were counted
+    pub zings: u32,
+    pub jack_zings: u32,
+    /// tricks taken by the player's team (not counting Zings and the final
+    /// sweep); only known for games finished since events were recorded
+    pub tricks: u32,
+    /// games against each opponent (by name), sorted by name
+    pub head_to_head: Vec<HeadToHead>,
+    #[serde(skip)]
+    card_points: u32,
+    #[serde(skip)]
+    opponents: BTreeMap<String, HeadToHead>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct HeadToHead {
+    pub opponent: String,
+    pub games_played: u32,
+    pub games_won: u32,
+    pub games_lost: u32,
+}
+
+#[derive(Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub name: String,
+    pub rating: f64,
+    pub rated_games: u32,
+}
+
+impl UserStats {
+    pub fn new(user: &entities::user::Model) -> Self {
+        Self {
+            name: user.name.clone(),
+            rating: user.rating,
+            rated_games: user.rated_games as u32,
+            games_played: 0,
+            games_won: 0,
+            matches_played: 0,
+            matches_won: 0,
+            average_card_points: 0.,
+            zings: 0,
+            jack_zings: 0,
+            tricks: 0,
+            head_to_head: Vec::new(),
+            card_points: 0,
+            opponents: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the finished games of a table at which the user sat at the given
+    /// position; `player_names` lists all players in seat order.
+    pub fn add_table(
+        &mut self,
+        position: usize,
+        player_names: &[String],
+        game_results: &[entities::game_results::Model],
+    ) {
+        let team = ZingGame::team_of_player(position);
+        let opponents: Vec<&String> = player_names
+            .iter()
+            .enumerate()
+            .filter(|(player, _)| ZingGame::team_of_player(*player) != team)
+            .map(|(_, name)| name)
+            .collect();
+
+        let mut match_results = Vec::new();
+        for game_result in game_results {
+            let points = ZingGamePoints {
+                card_points: (
+                    game_result.card_points0 as u32,
+                    game_result.card_points1 as u32,
+                ),
+                card_count_points: (
+                    game_result.card_count_points0 as u32,
+                    game_result.card_count_points1 as u32,
+                ),
+                zing_points: (
+                    game_result.zing_points0 as u32,
+                    game_result.zing_points1 as u32,
+                ),
+            };
+            let (card_points, zing_points, zings) = if team == 0 {
+                (
+                    points.card_points.0,
+                    points.zing_points.0,
+                    game_result.zings0,
+                )
+            } else {
+                (
+                    points.card_points.1,
+                    points.zing_points.1,
+                    game_result.zings1,
+                )
+            };
+            let winner = points.winner();
+
+            self.games_played += 1;
+            if winner == Some(team) {
+                self.games_won += 1;
+            }
+            self.card_points += card_points;
+            let summary = game_result
+                .events
+                .clone()
+                .and_then(|events| serde_json::from_value::<Vec<ZingEvent>>(events).ok())
+                .map(|events| ZingEventSummary::from_events(&events));
+            if let Some(summary) = summary {
+                let of_team = |counts: (u32, u32)| if team == 0 { counts.0 } else { counts.1 };
+                self.zings += of_team(summary.zings) + of_team(summary.jack_zings);
+                self.jack_zings += of_team(summary.jack_zings);
+                self.tricks += of_team(summary.tricks);
+            } else if let Some(zings) = zings {
+                // Zings count 10 points, Jack Zings 20
+                self.zings += zings as u32;
+                self.jack_zings += (zing_points / 10).saturating_sub(zings as u32);
+            }
+
+            for opponent in &opponents {
+                let record = self
+                    .opponents
+                    .entry((*opponent).clone())
+                    .or_insert_with(|| HeadToHead {
+                        opponent: (*opponent).clone(),
+                        games_played: 0,
+                        games_won: 0,
+                        games_lost: 0,
+                    });
+                record.games_played += 1;
+                match winner {
+                    Some(winner) if winner == team => record.games_won += 1,
+                    Some(_) => record.games_lost += 1,
+                    None => {}
+                }
+            }
+
+            match_results.push(points);
+        }
+
+        if let Some(winner) = ZingMatch::new_with_game_results(match_results).winner() {
+            self.matches_played += 1;
+            if winner == team {
+                self.matches_won += 1;
+            }
+        }
+    }
+
+    /// Computes the averages and sorted lists after all tables were added.
+    pub fn finish(mut self) -> Self {
+        if self.games_played > 0 {
+            self.average_card_points = self.card_points as f64 / self.games_played as f64;
+        }
+        self.head_to_head = self.opponents.values().cloned().collect();
+        self
+    }
+}
@@ -0,0 +1,74 @@
+//! Opening the database that users, tables and game results are stored in.
+//!
+//! The backend is chosen by the scheme of the `DATABASE_URL`: `postgres://` /
+//! `postgresql://` for a Postgres server, or `sqlite:` for a local SQLite file
+//! (e.g. `sqlite://zing.db`, created if missing) or an in-memory database
+//! (`sqlite::memory:`).  All queries go through sea-orm's
+//! [DatabaseConnection], so the rest of the server does not depend on the
+//! backend.
+
+use std::{error::Error, io, str::FromStr};
+
+use migration::MigratorTrait;
+use sea_orm::{DatabaseConnection, SqlxPostgresConnector, SqlxSqliteConnector};
+use sqlx::{
+    postgres::PgPoolOptions,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Postgres,
+    Sqlite,
+}
+
+impl Backend {
+    pub fn from_url(database_url: &str) -> Result<Self, io::Error> {
+        let scheme = database_url
+            .split_once(':')
+            .map(|(scheme, _)| scheme)
+            .unwrap_or_default();
+        match scheme {
+            "postgres" | "postgresql" => Ok(Backend::Postgres),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported DATABASE_URL scheme {scheme:?} (expected postgres or sqlite)"),
+            )),
+        }
+    }
+}
+
+/// Connects to the database and applies all pending migrations.
+pub async fn open_database(database_url: &str) -> Result<DatabaseConnection, Box<dyn Error>> {
+    let conn = match Backend::from_url(database_url)? {
+        Backend::Postgres => {
+            let pool = PgPoolOptions::new().connect(database_url).await?;
+            SqlxPostgresConnector::from_sqlx_postgres_pool(pool)
+        }
+        Backend::Sqlite => {
+            let options = SqliteConnectOptions::from_str(database_url)?.create_if_missing(true);
+            let pool_options = if is_in_memory(database_url) {
+                // every connection would get its own, empty in-memory
+                // database, so there must be exactly one that is never closed
+                SqlitePoolOptions::new()
+                    .max_connections(1)
+                    .min_connections(1)
+                    .idle_timeout(None)
+                    .max_lifetime(None)
+            } else {
+                SqlitePoolOptions::new()
+            };
+            let pool = pool_options.connect_with(options).await?;
+            SqlxSqliteConnector::from_sqlx_sqlite_pool(pool)
+        }
+    };
+
+    migration::Migrator::up(&conn, None).await?;
+
+    Ok(conn)
+}
+
+fn is_in_memory(database_url: &str) -> bool {
+    database_url.contains(":memory:") || database_url.contains("mode=memory")
+}
@@ -1,16 +1,30 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
 use sea_orm::{prelude::*, ActiveValue, Order, QueryOrder};
 
 use serde::{Serialize, Serializer};
 use zing_game::{
-    client_notification::ClientNotification,
+    chat::ChatMessage,
+    client_notification::{ClientNotification, TurnTimer},
+    error_code::ErrorCode,
     game::{GamePhase, GameState},
-    zing_game::{ZingGame, ZingGamePoints},
+    zing_ai::{ai_by_name, ZingAI},
+    zing_game::{MoveHint, ZingEvent, ZingEventSummary, ZingGame, ZingGamePoints},
+    zing_match::ZingMatch,
 };
 
-use crate::util::random_id;
+use crate::util::{now, random_id};
 use crate::{
     client_connection::{
-        ClientConnection, ClientConnections, SerializedNotification, SerializedNotifications,
+        notification_json, ClientConnection, ClientConnections, SerializedNotification,
+        SerializedNotifications,
     },
     entities,
     entities::prelude::*,
@@ -18,26 +32,97 @@ use crate::{
     ws_notifications::NotificationSenderHandle,
 };
 
+/// Strategy used for playing in place of players whose turn time has run out
+/// (must be cheap, since it runs while the table is locked).
+const IDLE_PLAYER_AI: &str = "heuristic";
+
+/// Users may send at most this many chat messages per [CHAT_RATE_WINDOW]
+const CHAT_RATE_LIMIT: usize = 5;
+const CHAT_RATE_WINDOW: Duration = Duration::from_secs(10);
+
 pub struct LoadedTable {
     table: entities::table::Model,
     // ATTENTION: user entities will not be kept up to date; only use this for
     // list of players (with id/token/name):
     players: Vec<entities::user::Model>,
+    /// whether each player agrees to allowing take-backs
+    take_back_agreement: Vec<bool>,
+    /// whether the server had to play for each player because their turn
+    /// time ran out (reset when they play a card themselves)
+    idle_players: Vec<bool>,
+    /// running time limit for the current turn
+    turn_deadline: Option<TurnDeadline>,
+    /// sequence number of the next notification
+    next_seq: AtomicU64,
+    /// sequence number and game history length of each GameStatus and
+    /// CardActions notification of the current game (for resuming)
+    delivered_history: RwLock<Vec<(u64, usize)>>,
+    /// times of recent chat messages per user id (for rate limiting)
+    chat_times: HashMap<i32, VecDeque<Instant>>,
+    /// since when nobody has been connected to the table (as observed by
+    /// [Self::unused_for])
+    unused_since: Option<Instant>,
     pub connections: ClientConnections,
-    pub game_results: Vec<ZingGamePoints>,
+    pub zing_match: ZingMatch,
+    /// counts of tricks and Zings of each finished game (unknown for games
+    /// finished before events were recorded)
+    pub game_summaries: Vec<Option<ZingEventSummary>>,
     pub game: Option<ZingGame>,
 }
 
+/// Deadline for the current turn, identified by a server-wide counter so that
+/// expired timers can be told apart from outdated ones.
+struct TurnDeadline {
+    id: u64,
+    player: usize,
+    deadline: SystemTime,
+}
+
 #[derive(Serialize)]
 pub struct TableInfo {
     pub id: String,
     #[serde(serialize_with = "serialize_datetime_as_iso8601")]
     pub created_at: DateTimeWithTimeZone,
     pub user_names: Vec<String>,
+    /// Names of the two competing sides; with four players, opposite players
+    /// form a team (e.g. "Alice & Carol").
+    pub team_names: Vec<String>,
     pub game_results: Vec<ZingGamePoints>,
+    /// Tricks, Zings and Jack Zings per team of each finished game, if known
+    pub game_summaries: Vec<Option<ZingEventSummary>>,
+    /// Total points per team over all games of the match
+    pub match_points: (u32, u32),
+    /// Team that has won the match, if already decided
+    pub match_winner: Option<usize>,
+    /// Whether each player agrees to allowing take-backs; cards can only be
+    /// taken back if all players do
+    pub allow_take_backs: Vec<bool>,
+    /// Time limit per turn in seconds, if any
+    pub turn_time_limit: Option<u32>,
+    /// Whether each player has been played for by the server because their
+    /// time ran out
+    pub idle_players: Vec<bool>,
+    /// Whether users who have not joined the table may watch its games
+    pub allow_spectators: bool,
+    /// Names of users currently watching the table without playing
+    pub spectator_names: Vec<String>,
+    /// Whether players are told what playing each of their cards would lead
+    /// to (see [ZingGame::move_hints])
+    pub allow_move_hints: bool,
     pub game: Option<GamePhase>,
 }
 
+/// Sequence numbers start at the time a table is loaded (in milliseconds since
+/// the epoch), so that they keep increasing across server restarts.
+fn initial_seq() -> AtomicU64 {
+    AtomicU64::new(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+    )
+}
+
 fn serialize_datetime_as_iso8601<S>(
     datetime: &DateTimeWithTimeZone,
     serializer: S,
@@ -52,6 +137,7 @@ where
 impl LoadedTable {
     pub async fn create_for_user(
         user: entities::user::Model,
+        turn_time_limit: Option<u32>,
         db_conn: &DatabaseConnection,
     ) -> Result<Self, GameError> {
         let table_token = random_id();
@@ -59,27 +145,38 @@ impl LoadedTable {
         // insert new table entity
         let table = entities::table::ActiveModel {
             token: ActiveValue::Set(table_token.clone()),
+            turn_time_limit: ActiveValue::Set(turn_time_limit.map(|secs| secs as i32)),
+            created_at: ActiveValue::Set(now()),
             ..Default::default()
         }
         .insert(db_conn)
         .await
-        .map_err(|_| GameError::DBError("DB insert failed unexpectedly"))?;
+        .map_err(|err| GameError::DBError("DB insert failed unexpectedly", err))?;
 
         // insert new table_join entity for this user
         entities::table_join::ActiveModel {
             user_id: ActiveValue::Set(user.id),
             table_id: ActiveValue::Set(table.id),
             table_pos: ActiveValue::Set(0),
+            allow_take_backs: ActiveValue::Set(false),
         }
         .insert(db_conn)
         .await
-        .map_err(|_| GameError::DBError("DB insert failed unexpectedly"))?;
+        .map_err(|err| GameError::DBError("DB insert failed unexpectedly", err))?;
 
         Ok(Self {
             table,
             players: vec![user],
+            take_back_agreement: vec![false],
+            idle_players: vec![false],
+            turn_deadline: None,
+            next_seq: initial_seq(),
+            delivered_history: Default::default(),
+            chat_times: HashMap::new(),
+            unused_since: None,
             connections: ClientConnections::new(),
-            game_results: Vec::new(),
+            zing_match: ZingMatch::new(),
+            game_summaries: Vec::new(),
             game: None,
         })
     }
@@ -92,11 +189,35 @@ impl LoadedTable {
             .await
             .unwrap();
 
-        let game_results = GameResults::find()
-            .filter(entities::game_results::Column::TableId.eq(table.id))
+        let take_back_agreement = TableJoin::find()
+            .filter(entities::table_join::Column::TableId.eq(table.id))
+            .order_by(entities::table_join::Column::TablePos, Order::Asc)
             .all(db_conn)
             .await
             .unwrap()
+            .into_iter()
+            .map(|table_join| table_join.allow_take_backs)
+            .collect();
+
+        let game_result_models = GameResults::find()
+            .filter(entities::game_results::Column::TableId.eq(table.id))
+            .order_by_asc(entities::game_results::Column::Id)
+            .all(db_conn)
+            .await
+            .unwrap();
+
+        let game_summaries = game_result_models
+            .iter()
+            .map(|game_results| {
+                game_results
+                    .events
+                    .clone()
+                    .and_then(|events| serde_json::from_value::<Vec<ZingEvent>>(events).ok())
+                    .map(|events| ZingEventSummary::from_events(&events))
+            })
+            .collect();
+
+        let game_results = game_result_models
             .into_iter()
             .map(|game_results| ZingGamePoints {
                 card_points: (
@@ -120,10 +241,18 @@ impl LoadedTable {
             .map(|game_json| serde_json::from_value(game_json).unwrap());
 
         Self {
+            idle_players: vec![false; players.len()],
             table,
             players,
+            take_back_agreement,
+            turn_deadline: None,
+            next_seq: initial_seq(),
+            delivered_history: Default::default(),
+            chat_times: HashMap::new(),
+            unused_since: None,
             connections: ClientConnections::new(),
-            game_results,
+            zing_match: ZingMatch::new_with_game_results(game_results),
+            game_summaries,
             game,
         }
     }
@@ -146,43 +275,199 @@ impl LoadedTable {
         self.players.iter().map(|user| user.name.clone()).collect()
     }
 
+    fn team_names(&self) -> Vec<String> {
+        let names = self.player_names();
+        (0..names.len().min(2))
+            .map(|team| {
+                names
+                    .iter()
+                    .skip(team)
+                    .step_by(2)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" & ")
+            })
+            .collect()
+    }
+
     pub fn table_info(&self) -> TableInfo {
         TableInfo {
             id: self.table.token.to_owned(),
             created_at: self.table.created_at,
             user_names: self.player_names(),
-            game_results: self.game_results.clone(),
+            team_names: self.team_names(),
+            game_results: self.zing_match.game_results().clone(),
+            game_summaries: self.game_summaries.clone(),
+            match_points: self.zing_match.total_points(),
+            match_winner: self.zing_match.winner(),
+            allow_take_backs: self.take_back_agreement.clone(),
+            turn_time_limit: self.table.turn_time_limit.map(|secs| secs as u32),
+            idle_players: self.idle_players.clone(),
+            allow_spectators: self.table.allow_spectators,
+            spectator_names: self.spectator_names(),
+            allow_move_hints: self.table.allow_move_hints,
             game: self.game.as_ref().map(|game| game.state().phase()),
         }
     }
 
+    fn spectator_names(&self) -> Vec<String> {
+        let mut spectator_tokens = Vec::new();
+        self.connections
+            .iter()
+            .filter(|c| {
+                let token = c.client_login_token();
+                if self.player_index(token).is_some() || spectator_tokens.contains(&token) {
+                    return false;
+                }
+                spectator_tokens.push(token);
+                true
+            })
+            .map(|c| c.client_user_name().to_owned())
+            .collect()
+    }
+
+    pub fn allow_spectators(&self) -> bool {
+        self.table.allow_spectators
+    }
+
+    /// Allows or forbids watching the table without playing; forbidding it
+    /// closes the connections of current spectators.
+    pub fn set_allow_spectators(&mut self, allow_spectators: bool) {
+        self.table.allow_spectators = allow_spectators;
+        if !allow_spectators {
+            let spectator_tokens: Vec<String> = self
+                .connections
+                .iter()
+                .map(|c| c.client_login_token())
+                .filter(|token| self.player_index(token).is_none())
+                .map(str::to_owned)
+                .collect();
+            for token in spectator_tokens {
+                self.connections.remove_user_with_token(&token);
+            }
+        }
+    }
+
+    /// Switches move hints on or off; the player at turn is sent their hints
+    /// (or an empty list, clearing them) right away.
+    pub fn set_allow_move_hints(&mut self, allow_move_hints: bool) -> SerializedNotifications {
+        self.table.allow_move_hints = allow_move_hints;
+        self.current_move_hint_notifications()
+    }
+
     pub fn games_have_started(&self) -> bool {
-        self.game.is_some() || !self.game_results.is_empty()
+        self.game.is_some() || self.zing_match.games_played() > 0
+    }
+
+    /// Zing is played by two or four players, so nobody else can join.
+    pub fn is_full(&self) -> bool {
+        self.players.len() >= 4
     }
 
     pub fn user_left(&mut self, player_index: usize) {
         self.players.remove(player_index);
+        self.take_back_agreement.remove(player_index);
+        self.idle_players.remove(player_index);
     }
 
-    pub fn user_joined(&mut self, user: &entities::user::Model) {
+    pub fn user_joined(&mut self, user: &entities::user::Model, allow_take_backs: bool) {
         self.players.push(user.clone());
+        self.take_back_agreement.push(allow_take_backs);
+        self.idle_players.push(false);
+    }
+
+    /// Sets whether the player agrees to take-backs; computer players follow
+    /// the table owner.  Returns the ids of all users whose agreement has been
+    /// set.
+    pub fn set_take_back_agreement(
+        &mut self,
+        player_index: usize,
+        allow_take_backs: bool,
+    ) -> Vec<i32> {
+        let mut user_ids = vec![self.players[player_index].id];
+        self.take_back_agreement[player_index] = allow_take_backs;
+        if player_index == 0 {
+            for (player, agreement) in self.players.iter().zip(&mut self.take_back_agreement) {
+                if player.ai_strategy.is_some() {
+                    *agreement = allow_take_backs;
+                    user_ids.push(player.id);
+                }
+            }
+        }
+        user_ids
+    }
+
+    /// Whether the table owner agrees to take-backs (which computer players
+    /// joining the table adopt).
+    pub fn owner_allows_take_backs(&self) -> bool {
+        self.take_back_agreement.first().copied().unwrap_or(false)
+    }
+
+    pub fn take_backs_allowed(&self) -> bool {
+        self.take_back_agreement.iter().all(|allow| *allow)
+    }
+
+    /// Takes back the last card played by the given player, together with all
+    /// cards played after it.
+    pub fn take_back(&mut self, player_index: usize) -> Result<(), GameError> {
+        if !self.take_backs_allowed() {
+            return Err(GameError::Request(
+                ErrorCode::TakeBacksNotAllowed,
+                "take-backs have not been allowed by all players",
+            ));
+        }
+
+        let game = self.game.as_mut().ok_or(GameError::Request(
+            ErrorCode::GameNotStarted,
+            "no active game",
+        ))?;
+        if game.state().phase == GamePhase::Finished {
+            return Err(GameError::Request(
+                ErrorCode::GameFinished,
+                "cannot take back cards after the game has finished",
+            ));
+        }
+
+        // the turn in which the player played their first card
+        let player_count = game.state().player_count();
+        let first_turn = (player_index + player_count - game.dealer() - 1) % player_count;
+        if game.turn() <= first_turn {
+            return Err(GameError::Request(
+                ErrorCode::NothingToTakeBack,
+                "player has not played a card yet",
+            ));
+        }
+
+        loop {
+            game.undo_last_turn()?;
+            if game.current_player() == Some(player_index) {
+                return Ok(());
+            }
+        }
     }
 
     pub fn start_game(&mut self) -> Result<(), GameError> {
         if self.game.is_some() {
-            return Err(GameError::Conflict("game already started"));
+            return Err(GameError::Request(
+                ErrorCode::GameAlreadyStarted,
+                "game already started",
+            ));
         }
 
         let names = self.player_names();
         let players_at_table = names.len();
         if (players_at_table != 2) && (players_at_table != 4) {
-            return Err(GameError::Conflict(
+            return Err(GameError::Request(
+                ErrorCode::InvalidPlayerCount,
                 "game can only start when there are exactly two or four players present",
             ));
         }
 
-        let dealer_index = self.game_results.len() % names.len();
-        self.game = Some(ZingGame::new_with_player_names(names, dealer_index));
+        self.game = Some(
+            self.zing_match
+                .start_next_game(names)
+                .map_err(|msg| GameError::Request(ErrorCode::MatchDecided, msg))?,
+        );
 
         // TODO: move game JSON storing code here after finding out how to
         // return it as closure / future
@@ -190,35 +475,323 @@ impl LoadedTable {
         Ok(())
     }
 
+    /// Starts the time limit for the current turn if the table has one and a
+    /// human player is at turn, returning the timer's id (which must be
+    /// unique) and duration.  Any previous timer is cancelled.
+    pub fn start_turn_timer(&mut self, timer_id: u64) -> Option<(u64, Duration)> {
+        self.turn_deadline = None;
+
+        let time_limit = Duration::from_secs(self.table.turn_time_limit? as u64);
+        let player = self.game.as_ref()?.current_player()?;
+        if self.current_bot().is_some() {
+            return None;
+        }
+
+        self.turn_deadline = Some(TurnDeadline {
+            id: timer_id,
+            player,
+            deadline: SystemTime::now() + time_limit,
+        });
+        Some((timer_id, time_limit))
+    }
+
+    /// Returns the player whose time has run out if the given timer is still
+    /// the current one.
+    fn expired_turn(&self, timer_id: u64) -> Option<usize> {
+        let turn_deadline = self.turn_deadline.as_ref()?;
+        let current_player = self.game.as_ref()?.current_player()?;
+        (turn_deadline.id == timer_id && turn_deadline.player == current_player)
+            .then_some(current_player)
+    }
+
+    /// Marks a player as idle (or active again); returns whether this changed
+    /// anything.
+    pub fn set_idle(&mut self, player_index: usize, idle: bool) -> bool {
+        let changed = self.idle_players[player_index] != idle;
+        self.idle_players[player_index] = idle;
+        changed
+    }
+
+    fn turn_timer(&self) -> Option<TurnTimer> {
+        self.turn_deadline.as_ref().map(|turn_deadline| {
+            let millis = |duration: Duration| duration.as_millis() as u64;
+            TurnTimer {
+                player: turn_deadline.player,
+                deadline: millis(
+                    turn_deadline
+                        .deadline
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default(),
+                ),
+                remaining_millis: millis(
+                    turn_deadline
+                        .deadline
+                        .duration_since(SystemTime::now())
+                        .unwrap_or_default(),
+                ),
+            }
+        })
+    }
+
+    pub fn turn_timer_notifications(&self) -> SerializedNotifications {
+        if self.table.turn_time_limit.is_none() {
+            return Vec::new();
+        }
+        let notification = ClientNotification::TurnTimer(self.turn_timer());
+        let seq = self.next_seq();
+        self.connections
+            .iter()
+            .map(|c| c.client_notification(&notification, seq))
+            .collect()
+    }
+
+    /// Counts a chat message of the given user, failing if the user has sent
+    /// too many messages recently.
+    pub fn count_chat_message(&mut self, user_id: i32) -> Result<(), GameError> {
+        let now = Instant::now();
+        let times = self.chat_times.entry(user_id).or_default();
+        while times
+            .front()
+            .is_some_and(|time| now.duration_since(*time) > CHAT_RATE_WINDOW)
+        {
+            times.pop_front();
+        }
+        if times.len() >= CHAT_RATE_LIMIT {
+            return Err(GameError::Request(
+                ErrorCode::RateLimited,
+                "too many chat messages, slow down",
+            ));
+        }
+        times.push_back(now);
+        Ok(())
+    }
+
+    pub fn chat_notifications(&self, message: ChatMessage) -> SerializedNotifications {
+        let notification = ClientNotification::Chat(message);
+        let seq = self.next_seq();
+        self.connections
+            .iter()
+            .map(|c| c.client_notification(&notification, seq))
+            .collect()
+    }
+
+    /// Returns for how long the table has had no connections (called
+    /// periodically, so the result is only as precise as the calling
+    /// interval).
+    pub fn unused_for(&mut self, now: Instant) -> Option<Duration> {
+        if !self.connections.is_empty() {
+            self.unused_since = None;
+            return None;
+        }
+        Some(now.duration_since(*self.unused_since.get_or_insert(now)))
+    }
+
+    /// User ids of all players, in seat order.
+    pub fn player_ids(&self) -> Vec<i32> {
+        self.players.iter().map(|user| user.id).collect()
+    }
+
+    /// Replaces the stored entity of the given user if they play at this
+    /// table (e.g. after their name has changed); returns whether they do.
+    pub fn update_player(&mut self, user: &entities::user::Model) -> bool {
+        let mut plays_here = false;
+        for player in self
+            .players
+            .iter_mut()
+            .filter(|player| player.id == user.id)
+        {
+            *player = user.clone();
+            plays_here = true;
+        }
+        plays_here
+    }
+
+    pub fn player(&self, player_index: usize) -> Option<entities::user::Model> {
+        self.players.get(player_index).cloned()
+    }
+
+    /// Legal moves of the given player and their outcomes; empty if it is not
+    /// the player's turn.
+    pub fn move_hints(&self, player_index: usize) -> Result<Vec<MoveHint>, GameError> {
+        if !self.table.allow_move_hints {
+            return Err(GameError::Request(
+                ErrorCode::MoveHintsDisabled,
+                "move hints are disabled at this table",
+            ));
+        }
+        let game = self.game.as_ref().ok_or(GameError::Request(
+            ErrorCode::NoActiveGame,
+            "no game active",
+        ))?;
+        Ok(game.move_hints(player_index))
+    }
+
+    /// Hints for the player at turn (none if the table does not allow them).
+    fn current_move_hints(&self) -> Option<(usize, ClientNotification)> {
+        let game = self.game.as_ref()?;
+        let player_index = game.current_player()?;
+        let hints = if self.table.allow_move_hints {
+            game.move_hints(player_index)
+        } else {
+            Vec::new()
+        };
+        Some((player_index, ClientNotification::MoveHints(hints)))
+    }
+
+    /// Sends the player at turn the hints for their cards (if the table
+    /// allows move hints).
+    pub fn move_hint_notifications(&self) -> SerializedNotifications {
+        if !self.table.allow_move_hints {
+            return Vec::new();
+        }
+        self.current_move_hint_notifications()
+    }
+
+    fn current_move_hint_notifications(&self) -> SerializedNotifications {
+        let Some((player_index, notification)) = self.current_move_hints() else {
+            return Vec::new();
+        };
+        let seq = self.next_seq();
+        self.connections
+            .iter()
+            .filter(|c| self.player_index(c.client_login_token()) == Some(player_index))
+            .map(|c| c.client_notification(&notification, seq))
+            .collect()
+    }
+
+    /// Chooses a card for a player whose time has run out, if the given timer
+    /// is still the current one.
+    pub fn idle_player_move(&self, timer_id: u64) -> Option<(usize, usize)> {
+        let player_index = self.expired_turn(timer_id)?;
+        let ai = ai_by_name(IDLE_PLAYER_AI, player_index).expect("built-in AI");
+        Some((player_index, ai.choose_card(self.game.as_ref()?)))
+    }
+
+    /// Returns the computer player whose turn it is (if any).
+    pub fn current_bot(&self) -> Option<Box<dyn ZingAI + Send + Sync>> {
+        let player_index = self.game.as_ref()?.current_player()?;
+        let strategy = self.players[player_index].ai_strategy.as_ref()?;
+        ai_by_name(strategy, player_index)
+    }
+
     pub fn player_index(&self, login_token: &str) -> Option<usize> {
         self.players
             .iter()
             .position(|user| user.token == login_token)
     }
 
-    fn game_status_notification(&self, c: &ClientConnection) -> SerializedNotification {
-        c.client_notification(&ClientNotification::GameStatus(
-            self.game_status(c.client_login_token())
-                .expect("game should be started, so must have valid state"),
-            self.player_index(c.client_login_token()).unwrap(), // FIXME: include in game status result?
-            self.game.as_ref().and_then(|game| game.current_player()),
-        ))
+    /// Index of the player whose cards are visible to the given user; for
+    /// spectators, this does not correspond to any player, so that all hands
+    /// are covered.
+    fn viewer_index(&self, login_token: &str) -> usize {
+        self.player_index(login_token).unwrap_or(self.players.len())
+    }
+
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Serializes a notification for a single connection.
+    pub fn notification_json(&self, notification: &ClientNotification) -> String {
+        notification_json(notification, self.next_seq())
+    }
+
+    /// Remembers that all connections know the first `history_len` actions of
+    /// the current game as of notification `seq`.
+    fn record_delivery(&self, seq: u64, history_len: usize) {
+        self.delivered_history
+            .write()
+            .expect("RwLock poisoned through panic")
+            .push((seq, history_len));
+    }
+
+    /// Number of actions of the current game known to a client that has
+    /// received the notification with the given sequence number, if that was
+    /// sent during the current game.
+    fn delivered_history_len(&self, seq: u64) -> Option<usize> {
+        if seq >= self.next_seq.load(Ordering::Relaxed) {
+            // sent by another server or for another table
+            return None;
+        }
+        self.delivered_history
+            .read()
+            .expect("RwLock poisoned through panic")
+            .iter()
+            .rev()
+            .find(|(delivery_seq, _)| *delivery_seq <= seq)
+            .map(|(_, history_len)| *history_len)
+    }
+
+    fn game_status_notification(&self, c: &ClientConnection, seq: u64) -> SerializedNotification {
+        let game = self
+            .game
+            .as_ref()
+            .expect("game should be started, so must have valid state");
+        *c.actions_sent
+            .write()
+            .expect("RwLock poisoned through panic") = game.history().len();
+        c.client_notification(
+            &ClientNotification::GameStatus(
+                self.game_status(c.client_login_token())
+                    .expect("game should be started, so must have valid state"),
+                // spectators watch from the first player's seat
+                self.player_index(c.client_login_token()).unwrap_or(0), // FIXME: include in game status result?
+                game.current_player(),
+            ),
+            seq,
+        )
     }
 
     pub fn initial_game_status_messages(&self) -> SerializedNotifications {
+        let seq = self.next_seq();
+        self.delivered_history
+            .write()
+            .expect("RwLock poisoned through panic")
+            .clear();
+        self.record_delivery(seq, 0);
         self.connections
             .iter()
-            .map(|c| self.game_status_notification(c))
+            .map(|c| self.game_status_notification(c, seq))
             .collect()
     }
 
+    fn card_actions_notification(
+        &self,
+        c: &ClientConnection,
+        known_actions: usize,
+        seq: u64,
+    ) -> SerializedNotification {
+        let game = self
+            .game
+            .as_ref()
+            .expect("card actions require an active game");
+        let history = game.history();
+        let player_index = self.viewer_index(c.client_login_token());
+        *c.actions_sent
+            .write()
+            .expect("RwLock poisoned through panic") = history.len();
+        c.client_notification(
+            &ClientNotification::CardActions(
+                // all actions in the history have been applied, i.e. set up
+                history[known_actions..]
+                    .iter()
+                    .filter_map(|action| action.new_view_for_player(player_index).ok())
+                    .collect(),
+                game.current_player(),
+            ),
+            seq,
+        )
+    }
+
     pub fn setup_game(&mut self) -> Result<(), GameError> {
         match &mut self.game {
-            None => Err(GameError::Conflict("game not started yet")),
-            Some(game) => {
-                game.setup_game();
-                Ok(())
-            }
+            None => Err(GameError::Request(
+                ErrorCode::GameNotStarted,
+                "game not started yet",
+            )),
+            Some(game) => game
+                .setup_game()
+                .map_err(|_| GameError::Internal("cards could not be dealt")),
         }
     }
 
@@ -229,6 +802,8 @@ impl LoadedTable {
             .expect("action_notifications() called without active game")
             .history();
         let current_actions = history.len();
+        let seq = self.next_seq();
+        self.record_delivery(seq, current_actions);
 
         self.connections
             .iter()
@@ -237,64 +812,91 @@ impl LoadedTable {
                     .actions_sent
                     .read()
                     .expect("RwLock poisoned through panic");
-                {
-                    if current_actions > known_actions {
-                        let player_index = self.player_index(c.client_login_token()).unwrap();
-                        *c.actions_sent
-                            .write()
-                            .expect("RwLock poisoned through panic") = current_actions;
-                        Some(
-                            c.client_notification(&ClientNotification::CardActions(
-                                history[known_actions..current_actions]
-                                    .iter()
-                                    .map(|action| action.new_view_for_player(player_index))
-                                    .collect(),
-                                self.game.as_ref().and_then(|game| game.current_player()),
-                            )),
-                        )
-                    } else {
-                        None
-                    }
-                }
+                (current_actions > known_actions)
+                    .then(|| self.card_actions_notification(c, known_actions, seq))
             })
             .collect()
     }
 
+    pub fn match_status_notifications(&self) -> SerializedNotifications {
+        let notification = ClientNotification::MatchStatus(
+            self.zing_match.total_points(),
+            self.zing_match.winner(),
+        );
+        let seq = self.next_seq();
+        self.connections
+            .iter()
+            .map(|c| c.client_notification(&notification, seq))
+            .collect()
+    }
+
     pub fn game_status(&self, login_token: &str) -> Option<GameState> {
-        let player_index = self.player_index(login_token);
+        let player_index = self.viewer_index(login_token);
 
         self.game
             .as_ref()
-            .map(|game| game.state().new_view_for_player(player_index.unwrap()))
+            .map(|game| game.state().new_view_for_player(player_index))
     }
 
     pub fn finish_game(&mut self) -> Result<(), GameError> {
-        let game = self
-            .game
-            .as_ref()
-            .ok_or(GameError::Conflict("no active game"))?;
+        let game = self.game.as_ref().ok_or(GameError::Request(
+            ErrorCode::GameNotStarted,
+            "no active game",
+        ))?;
         if !game.finished() {
-            return Err(GameError::Conflict("game still running"));
+            return Err(GameError::Request(
+                ErrorCode::GameStillRunning,
+                "game still running",
+            ));
         }
         self.game = None;
+        self.delivered_history
+            .write()
+            .expect("RwLock poisoned through panic")
+            .clear();
         Ok(())
     }
 
+    /// Registers a new table connection and returns the notifications
+    /// bringing the client up to date: the full game status, or only the
+    /// missing card actions if the client is resuming from the sequence
+    /// number of the last notification it received.
     pub fn connection_opened(
         &mut self,
         user: entities::user::Model,
         sender: NotificationSenderHandle,
-    ) -> Option<SerializedNotification> {
+        resume_from: Option<u64>,
+    ) -> SerializedNotifications {
         self.connections.add(user, sender);
-        // self.game.as_ref().map(..) would be more ideomatic, but does not work
-        // with the .await yet:
+        let mut result = Vec::new();
         if let Some(game) = self.game.as_ref() {
             // add() cannot return this, because its self is mutable
             let new_conn = self.connections.last().unwrap();
-            *new_conn.actions_sent.write().unwrap() = game.history().len();
-            Some(self.game_status_notification(new_conn))
-        } else {
-            None
+            let seq = self.next_seq();
+            let known_actions = resume_from
+                .and_then(|resume_seq| self.delivered_history_len(resume_seq))
+                .filter(|known_actions| *known_actions <= game.history().len());
+            result.push(match known_actions {
+                Some(known_actions) => self.card_actions_notification(new_conn, known_actions, seq),
+                None => {
+                    self.record_delivery(seq, game.history().len());
+                    self.game_status_notification(new_conn, seq)
+                }
+            });
+            if let Some(turn_timer) = self.turn_timer() {
+                result.push(new_conn.client_notification(
+                    &ClientNotification::TurnTimer(Some(turn_timer)),
+                    self.next_seq(),
+                ));
+            }
+            if let Some((player_index, notification)) = self.current_move_hints() {
+                if self.table.allow_move_hints
+                    && self.player_index(new_conn.client_login_token()) == Some(player_index)
+                {
+                    result.push(new_conn.client_notification(&notification, self.next_seq()));
+                }
+            }
         }
+        result
     }
 }
@@ -1,5 +1,13 @@
 use rand::distr::{Alphanumeric, SampleString};
+use sea_orm::prelude::DateTimeWithTimeZone;
 
 pub fn random_id() -> String {
     Alphanumeric.sample_string(&mut rand::rng(), 16)
 }
+
+/// Current time for storing in timestamp columns.  Timestamps are always set
+/// from here instead of via DB defaults, since SQLite stores them as text and
+/// only compares them correctly if they all have the same format.
+pub fn now() -> DateTimeWithTimeZone {
+    chrono::Utc::now().into()
+}
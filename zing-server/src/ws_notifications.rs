@@ -1,4 +1,5 @@
 use axum::extract::ws::{Message, Utf8Bytes, WebSocket};
+use futures::{stream::SplitSink, SinkExt};
 use tokio::sync::mpsc;
 use tokio::time::{self, Duration};
 use tracing::debug;
@@ -7,7 +8,7 @@ const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
 
 struct NotificationSender {
     receiver: mpsc::Receiver<Notification>,
-    socket: WebSocket,
+    socket: SplitSink<WebSocket, Message>,
 }
 
 struct Notification {
@@ -15,7 +16,7 @@ struct Notification {
 }
 
 impl NotificationSender {
-    fn new(receiver: mpsc::Receiver<Notification>, socket: WebSocket) -> Self {
+    fn new(receiver: mpsc::Receiver<Notification>, socket: SplitSink<WebSocket, Message>) -> Self {
         Self { receiver, socket }
     }
 
@@ -52,7 +53,9 @@ pub struct NotificationSenderHandle {
 }
 
 impl NotificationSenderHandle {
-    pub fn new(socket: WebSocket) -> Self {
+    /// Spawns a task sending notifications to the given (sending half of a)
+    /// WebSocket.
+    pub fn new(socket: SplitSink<WebSocket, Message>) -> Self {
         let (sender, receiver) = mpsc::channel(8);
         let mut actor = NotificationSender::new(receiver, socket);
         tokio::spawn(async move { actor.run().await });
@@ -1,45 +1,492 @@
 use axum::Json;
 use entities::prelude::*;
-use sea_orm::{prelude::*, ActiveValue, Condition, QueryOrder, Set};
-use std::{collections::HashMap, sync::RwLock};
-use tracing::debug;
-use zing_game::game::{GamePhase, GameState};
+use sea_orm::{prelude::*, ActiveValue, Condition, QueryOrder, QuerySelect, QueryTrait, Set};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock, Weak,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{debug, info, warn};
+use zing_game::{
+    card_action::{ActionRules, CardAction},
+    chat::{ChatContent, ChatMessage as ChatMessageInfo, Emote},
+    client_command::Command,
+    client_notification::ClientNotification,
+    error_code::ErrorCode,
+    game::{GamePhase, GameState},
+    rating::{rating_changes, team_rating},
+    replay::{GameReplay, MatchReplay},
+    zing_ai::AI_NAMES,
+    zing_game::{MoveHint, PlayError, ZingEventSummary, ZingGame},
+};
 
 use crate::{
     client_connection::{ClientConnections, SerializedNotifications},
+    clock::Clock,
     entities,
     game_error::GameError,
+    maintenance::{MaintenanceConfig, MaintenanceMetrics, MaintenanceRun, Metrics},
+    password::{hash_password, verify_password, MIN_PASSWORD_CHARS},
+    stats::{LeaderboardEntry, UserStats},
     table::{LoadedTable, TableInfo},
-    util::random_id,
+    util::{now, random_id},
     ws_notifications::NotificationSenderHandle,
 };
 
-#[derive(Default)]
+/// Number of recent chat messages sent to clients connecting to a table
+const CHAT_HISTORY_LENGTH: u64 = 50;
+
+/// `user.last_seen` is only updated if it is older than this, in order to
+/// avoid a DB write for every request
+const LAST_SEEN_RESOLUTION: Duration = Duration::from_secs(60);
+
+/// Number of players listed by [ZingState::leaderboard]
+const LEADERBOARD_LENGTH: u64 = 100;
+
 pub struct ZingState {
     tables: RwLock<HashMap<String, LoadedTable>>,
     connections: RwLock<ClientConnections>,
     db_conn: DatabaseConnection,
+    /// time computer players wait before playing a card, so that the card
+    /// animations in the UI remain readable
+    bot_delay: Duration,
+    /// tables at which a computer player has to move next (table token, time
+    /// of the move)
+    bot_moves: UnboundedSender<(String, Duration)>,
+    /// started turn timers (table token, timer id, deadline)
+    turn_timers: UnboundedSender<(String, u64, Duration)>,
+    /// pause between the games of a match, so that players can look at the
+    /// result before the next game is dealt
+    next_game_delay: Duration,
+    /// matches to be continued (table token, number of games played, time
+    /// of the next game)
+    next_games: UnboundedSender<(String, usize, Duration)>,
+    /// time source of the above timers
+    clock: Clock,
+    /// id of the next turn timer; never reset (not even when tables are
+    /// reloaded), so that outdated timers cannot match a later turn
+    next_timer_id: AtomicU64,
+    maintenance: MaintenanceConfig,
+    maintenance_metrics: MaintenanceMetrics,
 }
 
 impl ZingState {
-    pub async fn new(db_conn: DatabaseConnection) -> Self {
-        Self {
+    pub async fn new(
+        db_conn: DatabaseConnection,
+        bot_delay: Duration,
+        next_game_delay: Duration,
+        maintenance: MaintenanceConfig,
+        clock: Clock,
+    ) -> Arc<Self> {
+        let (bot_moves, bot_moves_receiver) = mpsc::unbounded_channel();
+        let (turn_timers, turn_timers_receiver) = mpsc::unbounded_channel();
+        let (next_games, next_games_receiver) = mpsc::unbounded_channel();
+
+        let result = Arc::new(Self {
+            tables: Default::default(),
+            connections: Default::default(),
             db_conn,
-            ..Default::default()
+            bot_delay,
+            bot_moves,
+            turn_timers,
+            next_game_delay,
+            next_games,
+            clock,
+            next_timer_id: AtomicU64::new(0),
+            maintenance,
+            maintenance_metrics: Default::default(),
+        });
+
+        tokio::spawn(Self::run_bot_players(
+            Arc::downgrade(&result),
+            bot_moves_receiver,
+        ));
+        tokio::spawn(Self::run_turn_timers(
+            Arc::downgrade(&result),
+            turn_timers_receiver,
+        ));
+        tokio::spawn(Self::run_next_games(
+            Arc::downgrade(&result),
+            next_games_receiver,
+        ));
+        tokio::spawn(Self::run_maintenance(Arc::downgrade(&result)));
+
+        result
+    }
+
+    /// Background task performing the moves of computer players scheduled via
+    /// [Self::schedule_bot_move]; ends when the state is dropped.
+    async fn run_bot_players(
+        state: Weak<Self>,
+        mut bot_moves: UnboundedReceiver<(String, Duration)>,
+    ) {
+        while let Some((table_token, move_time)) = bot_moves.recv().await {
+            let Some(state) = state.upgrade() else {
+                break;
+            };
+            tokio::spawn(async move {
+                state.clock.sleep_until(move_time).await;
+                if let Err(err) = state.play_bot_move(&table_token).await {
+                    warn!("computer player at table {table_token} could not move: {err:?}");
+                }
+            });
+        }
+    }
+
+    /// Lets a computer player move (after the configured delay) if it is its
+    /// turn at the given table.
+    fn schedule_bot_move(&self, table_token: &str) {
+        let bot_to_move = self
+            .tables
+            .read()
+            .unwrap()
+            .get(table_token)
+            .is_some_and(|table| table.current_bot().is_some());
+
+        if bot_to_move {
+            // sending only fails if run_bot_players() has ended, i.e. during shutdown
+            let move_time = self.clock.now() + self.bot_delay;
+            let _ = self.bot_moves.send((table_token.to_owned(), move_time));
+        }
+    }
+
+    async fn play_bot_move(&self, table_token: &str) -> Result<(), GameError> {
+        let (bot, game) = {
+            let tables = self.tables.read().unwrap();
+            let table = tables.get(table_token).ok_or(GameError::Request(
+                ErrorCode::TableNotFound,
+                "table id not found",
+            ))?;
+            match table.current_bot() {
+                // the move may have been scheduled more than once
+                None => return Ok(()),
+                Some(bot) => (bot, table.game.clone().expect("bot is at turn")),
+            }
+        };
+
+        // searching AIs may take a while; do not block the async runtime
+        let player_index = bot.player_index();
+        let card_index = tokio::task::spawn_blocking(move || bot.choose_card(&game))
+            .await
+            .expect("computer player panicked");
+
+        self.play_card_at(table_token, |game| game.play_card(player_index, card_index))
+            .await
+    }
+
+    /// Background task playing for human players whose turn timers (started
+    /// via [Self::start_turn_timer]) have expired; ends when the state is
+    /// dropped.
+    async fn run_turn_timers(
+        state: Weak<Self>,
+        mut turn_timers: UnboundedReceiver<(String, u64, Duration)>,
+    ) {
+        while let Some((table_token, timer_id, deadline)) = turn_timers.recv().await {
+            let Some(clock) = state.upgrade().map(|state| state.clock.clone()) else {
+                break;
+            };
+            let state = state.clone();
+            tokio::spawn(async move {
+                clock.sleep_until(deadline).await;
+                let Some(state) = state.upgrade() else {
+                    return;
+                };
+                if let Err(err) = state.play_for_idle_player(&table_token, timer_id).await {
+                    warn!("could not play for idle player at table {table_token}: {err:?}");
+                }
+            });
+        }
+    }
+
+    /// Starts the time limit for the current turn (if the table has one and a
+    /// human player is at turn) and notifies all connected users about it;
+    /// also sends the player at turn their move hints.
+    async fn start_turn_timer(&self, table_token: &str) {
+        let (timer, notifications) = {
+            let mut tables = self.tables.write().unwrap();
+            let Some(table) = tables.get_mut(table_token) else {
+                return;
+            };
+            let timer = table
+                .start_turn_timer(self.next_timer_id.fetch_add(1, Ordering::Relaxed))
+                .map(|(timer_id, time_limit)| (timer_id, self.clock.now() + time_limit));
+            let mut notifications = table.turn_timer_notifications();
+            notifications.extend(table.move_hint_notifications());
+            (timer, notifications)
+        };
+
+        self.send_notifications(notifications, Some(table_token))
+            .await;
+
+        if let Some((timer_id, deadline)) = timer {
+            // sending only fails if run_turn_timers() has ended, i.e. during shutdown
+            let _ = self
+                .turn_timers
+                .send((table_token.to_owned(), timer_id, deadline));
+        }
+    }
+
+    /// Plays a card for the player at turn if the given turn timer has
+    /// expired (i.e. nobody has played since it was started), marking the
+    /// player as idle.
+    async fn play_for_idle_player(
+        &self,
+        table_token: &str,
+        timer_id: u64,
+    ) -> Result<(), GameError> {
+        let (player_index, card_index, idle_changed) = {
+            let mut tables = self.tables.write().unwrap();
+            let Some(table) = tables.get_mut(table_token) else {
+                // table has been unloaded or deleted in the meantime
+                return Ok(());
+            };
+            let Some((player_index, card_index)) = table.idle_player_move(timer_id) else {
+                return Ok(());
+            };
+            (player_index, card_index, table.set_idle(player_index, true))
+        };
+
+        debug!("turn time of player {player_index} at table {table_token} has run out");
+        if idle_changed {
+            self.send_table_notifications(table_token).await;
+        }
+
+        self.play_card_at(table_token, |game| game.play_card(player_index, card_index))
+            .await
+    }
+
+    /// Background task starting the next game of a match (after the
+    /// configured delay) once a game has finished; ends when the state is
+    /// dropped.
+    async fn run_next_games(
+        state: Weak<Self>,
+        mut next_games: UnboundedReceiver<(String, usize, Duration)>,
+    ) {
+        while let Some((table_token, games_played, start_time)) = next_games.recv().await {
+            let Some(clock) = state.upgrade().map(|state| state.clock.clone()) else {
+                break;
+            };
+            let state = state.clone();
+            tokio::spawn(async move {
+                clock.sleep_until(start_time).await;
+                let Some(state) = state.upgrade() else {
+                    return;
+                };
+                if let Err(err) = state.continue_match(&table_token, games_played).await {
+                    warn!("could not start next game at table {table_token}: {err:?}");
+                }
+            });
         }
     }
 
+    /// Replaces the finished game with the next one of the match, unless the
+    /// finished game has been cleared (see [Self::finish_game]) or replaced
+    /// in the meantime.
+    async fn continue_match(
+        &self,
+        table_token: &str,
+        games_played: usize,
+    ) -> Result<(), GameError> {
+        {
+            let mut tables = self.tables.write().unwrap();
+            let Some(table) = tables.get_mut(table_token) else {
+                // table has been unloaded or deleted in the meantime
+                return Ok(());
+            };
+            let game_finished = table.game.as_ref().is_some_and(|game| game.finished());
+            if !game_finished || table.zing_match.games_played() != games_played {
+                return Ok(());
+            }
+            table.finish_game()?;
+        }
+
+        self.start_next_game(table_token).await
+    }
+
+    /// Background task periodically cleaning up stale sessions and tables
+    /// (see [Self::maintain]); ends when the state is dropped.
+    async fn run_maintenance(state: Weak<Self>) {
+        let Some(interval) = state.upgrade().map(|state| state.maintenance.interval) else {
+            return;
+        };
+        let mut ticks = tokio::time::interval(interval);
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticks.tick().await; // the first tick completes immediately
+
+        loop {
+            ticks.tick().await;
+            let Some(state) = state.upgrade() else {
+                break;
+            };
+            match state.maintain().await {
+                Ok(run) => {
+                    state.maintenance_metrics.record(&run);
+                    if !run.is_empty() {
+                        info!("maintenance: {run:?}");
+                    }
+                }
+                Err(err) => warn!("maintenance failed: {err:?}"),
+            }
+        }
+    }
+
+    /// Expires idle login sessions, unloads tables nobody is connected to
+    /// and deletes tables without logged-in players (except for those where
+    /// registered users have finished games, which their statistics are based
+    /// on) as well as anonymous users who are neither logged in nor at any
+    /// table.
+    async fn maintain(&self) -> Result<MaintenanceRun, GameError> {
+        let mut run = MaintenanceRun::default();
+
+        // users with open connections are active even without requests
+        let connected_tokens: Vec<String> = {
+            let connections = self.connections.read().unwrap();
+            let tables = self.tables.read().unwrap();
+            connections
+                .iter()
+                .chain(tables.values().flat_map(|table| table.connections.iter()))
+                .map(|c| c.client_login_token().to_owned())
+                .collect()
+        };
+        if !connected_tokens.is_empty() {
+            User::update_many()
+                .col_expr(entities::user::Column::LastSeen, Expr::value(now()))
+                .filter(entities::user::Column::Token.is_in(connected_tokens))
+                .exec(&self.db_conn)
+                .await
+                .map_err(|err| GameError::DBError("DB error (UPDATE user.last_seen)", err))?;
+        }
+
+        let session_start = chrono::Utc::now() - self.maintenance.session_timeout;
+        let expired_users = User::find()
+            .filter(entities::user::Column::LoggedIn.eq(true))
+            .filter(entities::user::Column::LastSeen.lt(session_start))
+            .all(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (SELECT expired sessions)", err))?;
+        for user in expired_users {
+            debug!("session of {} has expired", user.name);
+            self.close_user_connections(&user.token);
+            let mut user: entities::user::ActiveModel = user.into();
+            user.logged_in = Set(false);
+            user.update(&self.db_conn)
+                .await
+                .map_err(|err| GameError::DBError("DB error (UPDATE user.logged_in)", err))?;
+            run.sessions_expired += 1;
+        }
+
+        {
+            let now = Instant::now();
+            let unload_time = self.maintenance.table_unload_time;
+            let mut tables = self.tables.write().unwrap();
+            let count = tables.len();
+            tables.retain(|_, table| table.unused_for(now).is_none_or(|time| time < unload_time));
+            run.tables_unloaded = (count - tables.len()) as u64;
+        }
+
+        let tables_with_logged_in_users = TableJoin::find()
+            .select_only()
+            .column(entities::table_join::Column::TableId)
+            .inner_join(User)
+            .filter(entities::user::Column::LoggedIn.eq(true))
+            .into_query();
+        // finished games of registered users are kept (with the table and its
+        // players) for their statistics
+        let tables_with_registered_users = TableJoin::find()
+            .select_only()
+            .column(entities::table_join::Column::TableId)
+            .inner_join(User)
+            .filter(entities::user::Column::Username.is_not_null())
+            .into_query();
+        let tables_with_finished_games = GameResults::find()
+            .select_only()
+            .column(entities::game_results::Column::TableId)
+            .into_query();
+        let abandoned_tables = Table::find()
+            .filter(entities::table::Column::Id.not_in_subquery(tables_with_logged_in_users))
+            .filter(
+                Condition::any()
+                    .add(entities::table::Column::Id.not_in_subquery(tables_with_registered_users))
+                    .add(entities::table::Column::Id.not_in_subquery(tables_with_finished_games)),
+            )
+            .all(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (SELECT abandoned tables)", err))?;
+        for table in abandoned_tables {
+            let watched = self
+                .tables
+                .read()
+                .unwrap()
+                .get(&table.token)
+                .is_some_and(|loaded| !loaded.connections.is_empty());
+            if !watched {
+                self.delete_table(table).await?;
+                run.tables_deleted += 1;
+            }
+        }
+
+        let users_at_tables = TableJoin::find()
+            .select_only()
+            .column(entities::table_join::Column::UserId)
+            .into_query();
+        run.users_deleted = User::delete_many()
+            .filter(entities::user::Column::LoggedIn.eq(false))
+            .filter(entities::user::Column::Username.is_null())
+            .filter(entities::user::Column::Id.not_in_subquery(users_at_tables))
+            .exec(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (DELETE stale users)", err))?
+            .rows_affected;
+
+        Ok(run)
+    }
+
+    /// Totals of the maintenance task and current resource usage.
+    pub fn metrics(&self) -> Metrics {
+        let tables = self.tables.read().unwrap();
+        let connections = self.connections.read().unwrap().len()
+            + tables
+                .values()
+                .map(|table| table.connections.len())
+                .sum::<usize>();
+        self.maintenance_metrics.snapshot(tables.len(), connections)
+    }
+
     pub async fn get_user_with_token(
         &self,
         login_token: &str,
     ) -> Result<entities::user::Model, GameError> {
         let user = User::find()
             .filter(entities::user::Column::Token.eq(login_token))
+            .filter(entities::user::Column::LoggedIn.eq(true))
             .one(&self.db_conn)
             .await
-            .map_err(|_| GameError::DBError("DB error"))?;
+            .map_err(|err| GameError::DBError("DB error", err))?;
+
+        user.ok_or(GameError::Request(
+            ErrorCode::SessionExpired,
+            "user not found or session expired (bad id cookie)",
+        ))
+    }
 
-        user.ok_or(GameError::Unauthorized("user not found (bad id cookie)"))
+    /// Records that the user is active (see [MaintenanceConfig::session_timeout]).
+    pub async fn touch_user(&self, user: &entities::user::Model) -> Result<(), GameError> {
+        let last_seen_before = chrono::Utc::now() - LAST_SEEN_RESOLUTION;
+        if user.last_seen >= last_seen_before {
+            return Ok(());
+        }
+
+        User::update_many()
+            .col_expr(entities::user::Column::LastSeen, Expr::value(now()))
+            .filter(entities::user::Column::Id.eq(user.id))
+            .exec(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (UPDATE user.last_seen)", err))?;
+        Ok(())
     }
 
     pub async fn login(&self, user_name: &str) -> Result<String, GameError> {
@@ -49,15 +496,132 @@ impl ZingState {
             name: Set(user_name.to_owned()),
             token: Set(login_token.clone()),
             logged_in: Set(true),
+            last_seen: Set(now()),
             ..Default::default()
         })
         .exec_without_returning(&self.db_conn)
         .await
-        .map_err(|_| GameError::DBError("DB insert failed unexpectedly"))?;
+        .map_err(|err| GameError::DBError("DB insert failed unexpectedly", err))?;
 
         Ok(login_token)
     }
 
+    /// Logs in a registered user, returning the login token and user name.
+    pub async fn login_with_password(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<(String, String), GameError> {
+        let user = User::find()
+            .filter(entities::user::Column::Username.eq(username))
+            .one(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB query failed unexpectedly", err))?;
+
+        let invalid_credentials = GameError::Request(
+            ErrorCode::InvalidCredentials,
+            "invalid user name or password",
+        );
+        let Some((user, password_hash)) =
+            user.and_then(|user| user.password_hash.clone().map(|hash| (user, hash)))
+        else {
+            return Err(invalid_credentials);
+        };
+
+        let password = password.to_owned();
+        let password_ok =
+            tokio::task::spawn_blocking(move || verify_password(&password, &password_hash))
+                .await
+                .map_err(|_| GameError::Internal("password verification failed unexpectedly"))?;
+        if !password_ok {
+            return Err(invalid_credentials);
+        }
+
+        let token = user.token.clone();
+        let user_name = user.name.clone();
+        let mut user: entities::user::ActiveModel = user.into();
+        user.logged_in = Set(true);
+        // the session may have expired long ago
+        user.last_seen = Set(now());
+        user.update(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB update failed unexpectedly", err))?;
+
+        Ok((token, user_name))
+    }
+
+    /// Turns an anonymous user into a registered one with a unique user name
+    /// (which also becomes the displayed name) and a password.
+    pub async fn register(
+        &self,
+        user: entities::user::Model,
+        username: &str,
+        password: &str,
+    ) -> Result<(), GameError> {
+        if user.username.is_some() {
+            return Err(GameError::Request(
+                ErrorCode::AlreadyRegistered,
+                "user is already registered",
+            ));
+        }
+        if username.trim().is_empty() {
+            return Err(GameError::Request(
+                ErrorCode::InvalidName,
+                "name must not be empty",
+            ));
+        }
+        if password.chars().count() < MIN_PASSWORD_CHARS {
+            return Err(GameError::Request(
+                ErrorCode::PasswordTooShort,
+                "password must have at least 8 characters",
+            ));
+        }
+
+        let name_taken = User::find()
+            .filter(entities::user::Column::Username.eq(username))
+            .one(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB query failed unexpectedly", err))?
+            .is_some();
+        if name_taken {
+            return Err(GameError::Request(
+                ErrorCode::NameTaken,
+                "user name is already taken",
+            ));
+        }
+
+        let password = password.to_owned();
+        let password_hash = tokio::task::spawn_blocking(move || hash_password(&password))
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .ok_or(GameError::Internal("password hashing failed unexpectedly"))?;
+
+        let mut user: entities::user::ActiveModel = user.into();
+        user.name = Set(username.to_owned());
+        user.username = Set(Some(username.to_owned()));
+        user.password_hash = Set(Some(password_hash));
+        let user = user
+            .update(&self.db_conn)
+            .await
+            // most likely, somebody else has just registered the same name:
+            .map_err(|_| GameError::Request(ErrorCode::NameTaken, "user name is already taken"))?;
+
+        // show the new name at the tables of the user
+        let table_tokens: Vec<String> = self
+            .tables
+            .write()
+            .unwrap()
+            .iter_mut()
+            .filter_map(|(token, table)| table.update_player(&user).then(|| token.clone()))
+            .collect();
+        for table_token in table_tokens {
+            self.send_table_notifications(&table_token).await;
+        }
+
+        Ok(())
+    }
+
     pub async fn logout(&self, user: entities::user::Model) -> Result<String, GameError> {
         let token = user.token.clone();
         let user_name = user.name.clone();
@@ -67,29 +631,67 @@ impl ZingState {
         user.logged_in = Set(false);
         user.update(&self.db_conn)
             .await
-            .map_err(|_| GameError::DBError("DB update failed unexpectedly"))?;
+            .map_err(|err| GameError::DBError("DB update failed unexpectedly", err))?;
+
+        self.close_user_connections(&token);
+
+        // tables without logged in users are removed by the maintenance task
 
-        // close websocket connections
+        Ok(user_name)
+    }
+
+    fn close_user_connections(&self, login_token: &str) {
         self.connections
             .write()
             .unwrap()
-            .remove_user_with_token(&token);
+            .remove_user_with_token(login_token);
 
         let mut tables = self.tables.write().unwrap();
         for tc in tables.values_mut() {
-            tc.connections.remove_user_with_token(&token);
+            tc.connections.remove_user_with_token(login_token);
         }
+    }
 
-        // FIXME: remove table if all users have logged out (taking into account loaded tables)
+    /// Removes a table with everything belonging to it (including its
+    /// computer players) from the DB and memory.
+    async fn delete_table(&self, table: entities::table::Model) -> Result<(), GameError> {
+        self.tables.write().unwrap().remove(&table.token);
 
-        Ok(user_name)
+        let bots = table
+            .find_related(User)
+            .filter(entities::user::Column::AiStrategy.is_not_null())
+            .all(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (SELECT computer players)", err))?;
+        TableJoin::delete_many()
+            .filter(entities::table_join::Column::TableId.eq(table.id))
+            .exec(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (DELETE from table_join)", err))?;
+        for bot in bots {
+            bot.delete(&self.db_conn)
+                .await
+                .map_err(|err| GameError::DBError("DB error (DELETE computer player)", err))?;
+        }
+        GameResults::delete_many()
+            .filter(entities::game_results::Column::TableId.eq(table.id))
+            .exec(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (DELETE from game_results)", err))?;
+
+        table
+            .delete(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (DELETE table)", err))?;
+        Ok(())
     }
 
     pub async fn create_table(
         &self,
         user: entities::user::Model,
+        turn_time_limit: Option<u32>,
     ) -> Result<Json<TableInfo>, GameError> {
-        let table = LoadedTable::create_for_user(user, &self.db_conn).await?;
+        let table = LoadedTable::create_for_user(user, turn_time_limit, &self.db_conn).await?;
 
         let table_info = table.table_info();
 
@@ -104,8 +706,11 @@ impl ZingState {
         if !loaded {
             let token = table.token.clone();
             let loaded = LoadedTable::new_from_db(table, &self.db_conn).await;
-            let mut tables = self.tables.write().unwrap();
-            tables.insert(token.clone(), loaded);
+            self.tables.write().unwrap().insert(token.clone(), loaded);
+
+            // continue games that were interrupted while a computer player was at turn
+            self.schedule_bot_move(&token);
+            self.start_turn_timer(&token).await;
         }
     }
 
@@ -131,7 +736,7 @@ impl ZingState {
             .order_by_asc(entities::table::Column::CreatedAt)
             .all(&self.db_conn)
             .await
-            .map_err(|_| GameError::DBError("DB query failed unexpectedly"))?
+            .map_err(|err| GameError::DBError("DB query failed unexpectedly", err))?
         {
             result.push(self.table_info(table).await);
         }
@@ -154,8 +759,11 @@ impl ZingState {
             .filter(entities::table::Column::Token.eq(token))
             .one(&self.db_conn)
             .await
-            .map_err(|_| GameError::DBError("DB query failed unexpectedly"))?
-            .ok_or(GameError::NotFound("table not found by token"))
+            .map_err(|err| GameError::DBError("DB query failed unexpectedly", err))?
+            .ok_or(GameError::Request(
+                ErrorCode::TableNotFound,
+                "table not found by token",
+            ))
     }
 
     pub async fn get_table_info(&self, token: &str) -> Result<Json<TableInfo>, GameError> {
@@ -199,41 +807,45 @@ impl ZingState {
     ) -> Result<Json<TableInfo>, GameError> {
         let table = self.find_table_with_token(table_token).await?;
 
-        {
+        // computer players follow the table owner's take-back agreement
+        let allow_take_backs = {
             self.ensure_loaded_table(table.clone()).await;
 
             let tables = self.tables.read().unwrap();
-            if tables
-                .get(table_token)
-                .expect("must be loaded now")
-                .games_have_started()
-            {
-                return Err(GameError::Conflict(
+            let loaded_table = tables.get(table_token).expect("must be loaded now");
+            if loaded_table.games_have_started() {
+                return Err(GameError::Request(
+                    ErrorCode::GameAlreadyStarted,
                     "cannot join a table after games have started",
                 ));
             }
-        }
+            if loaded_table.is_full() {
+                return Err(GameError::Request(ErrorCode::TableFull, "table is full"));
+            }
+            user.ai_strategy.is_some() && loaded_table.owner_allows_take_backs()
+        };
 
         let table_pos = table
             .find_related(TableJoin)
             .count(&self.db_conn)
             .await
-            .map_err(|_| GameError::DBError("DB query failed unexpectedly"))?;
+            .map_err(|err| GameError::DBError("DB query failed unexpectedly", err))?;
 
         TableJoin::insert(entities::table_join::ActiveModel {
             user_id: Set(user.id),
             table_id: Set(table.id),
             table_pos: Set(table_pos.try_into().unwrap()),
+            allow_take_backs: Set(allow_take_backs),
         })
         .exec_without_returning(&self.db_conn)
         .await
         // TODO: discriminate between a generic DB error vs. a constraint violation?
-        .map_err(|_| GameError::Conflict("trying to join table again"))?;
+        .map_err(|_| GameError::Request(ErrorCode::AlreadyJoined, "trying to join table again"))?;
 
         {
             let mut tables = self.tables.write().unwrap();
             let loaded_table = tables.get_mut(table_token).expect("must be loaded now");
-            loaded_table.user_joined(user);
+            loaded_table.user_joined(user, allow_take_backs);
         }
 
         self.send_table_notifications(table_token).await;
@@ -241,6 +853,98 @@ impl ZingState {
         self.get_table_info(table_token).await
     }
 
+    /// Seats a computer player with the given [zing_game::zing_ai::ZingAI]
+    /// strategy (one of [AI_NAMES]) at the table.  Computer players are
+    /// represented by users that are never logged in; only the table owner
+    /// (the first player) may add them.
+    pub async fn add_bot(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+        strategy: &str,
+    ) -> Result<Json<TableInfo>, GameError> {
+        if self.user_index_at_table(user, table_token).await? != 0 {
+            return Err(GameError::Request(
+                ErrorCode::NotTableOwner,
+                "only the table owner may add computer players",
+            ));
+        }
+
+        if !AI_NAMES.contains(&strategy) {
+            return Err(GameError::Request(
+                ErrorCode::UnknownAiStrategy,
+                "unknown AI strategy",
+            ));
+        }
+
+        {
+            let tables = self.tables.read().unwrap();
+            let loaded_table = tables
+                .get(table_token)
+                .expect("user_index_at_table() has loaded the table");
+            if loaded_table.games_have_started() {
+                return Err(GameError::Request(
+                    ErrorCode::GameAlreadyStarted,
+                    "cannot join a table after games have started",
+                ));
+            }
+            if loaded_table.is_full() {
+                return Err(GameError::Request(ErrorCode::TableFull, "table is full"));
+            }
+        }
+
+        let bot = entities::user::ActiveModel {
+            name: Set(format!("Computer ({strategy})")),
+            token: Set(random_id()),
+            logged_in: Set(false),
+            ai_strategy: Set(Some(strategy.to_owned())),
+            last_seen: Set(now()),
+            ..Default::default()
+        }
+        .insert(&self.db_conn)
+        .await
+        .map_err(|err| GameError::DBError("DB insert failed unexpectedly", err))?;
+
+        self.join_table(&bot, table_token).await
+    }
+
+    /// Removes the computer player at the given position from the table.
+    pub async fn remove_bot(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+        player_index: usize,
+    ) -> Result<(), GameError> {
+        if self.user_index_at_table(user, table_token).await? != 0 {
+            return Err(GameError::Request(
+                ErrorCode::NotTableOwner,
+                "only the table owner may remove computer players",
+            ));
+        }
+
+        let bot = self
+            .tables
+            .read()
+            .unwrap()
+            .get(table_token)
+            .and_then(|table| table.player(player_index))
+            .filter(|player| player.ai_strategy.is_some())
+            .ok_or(GameError::Request(
+                ErrorCode::BotNotFound,
+                "no computer player at given position",
+            ))?;
+
+        self.leave_table(&bot, table_token).await?;
+
+        bot.delete(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (DELETE computer player)", err))?;
+
+        self.send_table_notifications(table_token).await;
+
+        Ok(())
+    }
+
     pub async fn leave_table(
         &self,
         user: &entities::user::Model,
@@ -251,12 +955,14 @@ impl ZingState {
         let table_id = {
             // scope for locked self.tables
             let mut tables = self.tables.write().unwrap();
-            let table = tables
-                .get_mut(table_token)
-                .ok_or(GameError::NotFound("table id not found"))?;
+            let table = tables.get_mut(table_token).ok_or(GameError::Request(
+                ErrorCode::TableNotFound,
+                "table id not found",
+            ))?;
 
             if table.games_have_started() {
-                return Err(GameError::Conflict(
+                return Err(GameError::Request(
+                    ErrorCode::GameAlreadyStarted,
                     "cannot leave a table after games have started",
                 ));
             }
@@ -275,7 +981,7 @@ impl ZingState {
             )
             .exec(&self.db_conn)
             .await
-            .map_err(|_| GameError::DBError("DB error (DELETE from table_join)"))?;
+            .map_err(|err| GameError::DBError("DB error (DELETE from table_join)", err))?;
 
         // correct table positions of other users if necessary
         // FIXME: could we use an auto_increment value in order to make this
@@ -294,13 +1000,13 @@ impl ZingState {
             )
             .all(&self.db_conn)
             .await
-            .map_err(|_| GameError::DBError("DB error (SELECT from table_join)"))?;
+            .map_err(|err| GameError::DBError("DB error (SELECT from table_join)", err))?;
         for table_join in positions_to_change {
             let old_pos = table_join.table_pos;
             let mut table_join: entities::table_join::ActiveModel = table_join.into();
             table_join.table_pos = Set(old_pos - 1);
-            table_join.update(&self.db_conn).await.map_err(|_| {
-                GameError::DBError("DB error (UPDATE table_join, decreasing table_pos)")
+            table_join.update(&self.db_conn).await.map_err(|err| {
+                GameError::DBError("DB error (UPDATE table_join, decreasing table_pos)", err)
             })?;
         }
 
@@ -315,9 +1021,8 @@ impl ZingState {
             .is_some();
 
         if !table_has_logged_in_users {
-            table.delete(&self.db_conn).await.map_err(|_| {
-                GameError::DBError("DB error (DELETE table without logged in users)")
-            })?;
+            // computer players do not stay at a table without humans
+            self.delete_table(table).await?;
         }
 
         Ok(())
@@ -330,28 +1035,23 @@ impl ZingState {
     ) -> Result<(), GameError> {
         self.user_index_at_table(user, table_token).await?;
 
+        self.start_next_game(table_token).await
+    }
+
+    /// Starts the next game of the match at a loaded table, dealing the cards
+    /// and notifying everybody.
+    async fn start_next_game(&self, table_token: &str) -> Result<(), GameError> {
         // start a game (sync code), collect initial game status notifications
         let (game_json, notifications) = {
-            {
-                // scope for locked self.tables
-                let tables = self.tables.read().unwrap();
-                let loaded = tables
-                    .get(table_token)
-                    .expect("we have just loaded the table");
-
-                loaded.player_index(&user.token).ok_or(GameError::NotFound(
-                    "user has not joined table at which game should start",
-                ))?;
-            }
-
-            {
-                // scope for locked self.tables
-                let mut tables = self.tables.write().unwrap();
-                let loaded = tables.get_mut(table_token).unwrap();
-                loaded.start_game()?;
+            // scope for locked self.tables
+            let mut tables = self.tables.write().unwrap();
+            let loaded = tables.get_mut(table_token).ok_or(GameError::Request(
+                ErrorCode::TableNotFound,
+                "table id not found",
+            ))?;
+            loaded.start_game()?;
 
-                (loaded.game_json(), loaded.initial_game_status_messages())
-            }
+            (loaded.game_json(), loaded.initial_game_status_messages())
         };
 
         // send initial card notifications
@@ -377,7 +1077,10 @@ impl ZingState {
             .filter(entities::table::Column::Token.eq(table_token))
             .exec(&self.db_conn)
             .await
-            .map_err(|_| GameError::DBError("DB error (UPDATE table.game)"))?;
+            .map_err(|err| GameError::DBError("DB error (UPDATE table.game)", err))?;
+
+        self.schedule_bot_move(table_token);
+        self.start_turn_timer(table_token).await;
 
         Ok(())
     }
@@ -431,27 +1134,54 @@ impl ZingState {
             .get(table_token)
             .expect("we have just loaded the table");
 
-        loaded
-            .player_index(&user.token)
-            .ok_or(GameError::NotFound("user has not joined table"))?;
+        if loaded.player_index(&user.token).is_none() && !loaded.allow_spectators() {
+            return Err(GameError::Request(
+                ErrorCode::NotAtTable,
+                "user has not joined table, which does not allow spectators",
+            ));
+        }
 
-        loaded
-            .game_status(&user.token)
-            .map_or(Err(GameError::NotFound("no game active")), |game| {
-                Ok(Json(game))
-            })
+        loaded.game_status(&user.token).map_or(
+            Err(GameError::Request(
+                ErrorCode::NoActiveGame,
+                "no game active",
+            )),
+            |game| Ok(Json(game)),
+        )
     }
 
-    pub async fn finish_game(
+    /// Lists the cards the user may play and what each would lead to, if the
+    /// table allows move hints.
+    pub async fn move_hints(
         &self,
         user: &entities::user::Model,
         table_token: &str,
-    ) -> Result<(), GameError> {
-        self.user_index_at_table(user, table_token).await?;
+    ) -> Result<Vec<MoveHint>, GameError> {
+        let player_index = self.user_index_at_table(user, table_token).await?;
 
-        // scope for locked self.tables
-        let result = {
-            let mut tables = self.tables.write().unwrap();
+        self.tables
+            .read()
+            .unwrap()
+            .get(table_token)
+            .ok_or(GameError::Request(
+                ErrorCode::TableNotFound,
+                "table id not found",
+            ))?
+            .move_hints(player_index)
+    }
+
+    /// Clears the finished game at the table, which also stops the match from
+    /// continuing automatically; the next game then starts on request.
+    pub async fn finish_game(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+    ) -> Result<(), GameError> {
+        self.user_index_at_table(user, table_token).await?;
+
+        // scope for locked self.tables
+        let result = {
+            let mut tables = self.tables.write().unwrap();
             let loaded = tables
                 .get_mut(table_token)
                 .expect("we have just loaded the table");
@@ -471,11 +1201,274 @@ impl ZingState {
             .filter(entities::table::Column::Token.eq(table_token))
             .exec(&self.db_conn)
             .await
-            .map_err(|_| GameError::DBError("DB error (UPDATE table.game)"))?;
+            .map_err(|err| GameError::DBError("DB error (UPDATE table.game)", err))?;
 
         result
     }
 
+    /// Sets whether the user agrees to allowing take-backs at the table (for
+    /// the table owner, also on behalf of the computer players).
+    pub async fn set_take_back_agreement(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+        allow_take_backs: bool,
+    ) -> Result<(), GameError> {
+        let player_index = self.user_index_at_table(user, table_token).await?;
+
+        let (table_id, user_ids) = {
+            let mut tables = self.tables.write().unwrap();
+            let table = tables.get_mut(table_token).ok_or(GameError::Request(
+                ErrorCode::TableNotFound,
+                "table id not found",
+            ))?;
+            let user_ids = table.set_take_back_agreement(player_index, allow_take_backs);
+            (table.table().id, user_ids)
+        };
+
+        TableJoin::update_many()
+            .col_expr(
+                entities::table_join::Column::AllowTakeBacks,
+                Expr::value(allow_take_backs),
+            )
+            .filter(
+                Condition::all()
+                    .add(entities::table_join::Column::TableId.eq(table_id))
+                    .add(entities::table_join::Column::UserId.is_in(user_ids)),
+            )
+            .exec(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (UPDATE table_join)", err))?;
+
+        self.send_table_notifications(table_token).await;
+
+        Ok(())
+    }
+
+    /// Takes back the user's last card (and all cards played after it), if
+    /// all players allow take-backs.  Clients are notified about the reverting
+    /// card actions like about any other actions.
+    pub async fn take_back(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+    ) -> Result<(), GameError> {
+        let player_index = self.user_index_at_table(user, table_token).await?;
+
+        let (notifications, game_json, phase_changed) = {
+            let mut tables = self.tables.write().unwrap();
+            let table = tables.get_mut(table_token).ok_or(GameError::Request(
+                ErrorCode::TableNotFound,
+                "table id not found",
+            ))?;
+
+            let old_phase = table.game.as_ref().map(|game| game.state().phase());
+            table.take_back(player_index)?;
+            let new_phase = table.game.as_ref().map(|game| game.state().phase());
+
+            (
+                table.action_notifications(),
+                table.game_json(),
+                new_phase != old_phase,
+            )
+        };
+
+        self.send_notifications(notifications, Some(table_token))
+            .await;
+        if phase_changed {
+            self.send_table_notifications(table_token).await;
+        }
+
+        Table::update_many()
+            .col_expr(entities::table::Column::Game, Expr::value(game_json))
+            .filter(entities::table::Column::Token.eq(table_token))
+            .exec(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (UPDATE table.game)", err))?;
+
+        self.schedule_bot_move(table_token);
+        self.start_turn_timer(table_token).await;
+
+        Ok(())
+    }
+
+    /// Replays of all finished games at the table (only available to the
+    /// players, and only for games finished after replays were introduced).
+    async fn game_replays(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+    ) -> Result<Vec<GameReplay>, GameError> {
+        self.user_index_at_table(user, table_token).await?;
+
+        let table = self.find_table_with_token(table_token).await?;
+        table
+            .find_related(GameResults)
+            .order_by_asc(entities::game_results::Column::Id)
+            .all(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (SELECT game_results)", err))?
+            .into_iter()
+            .map(|game_results| {
+                game_results
+                    .replay
+                    .and_then(|replay| serde_json::from_value(replay).ok())
+                    .ok_or(GameError::Request(
+                        ErrorCode::ReplayNotAvailable,
+                        "replay not available for game",
+                    ))
+            })
+            .collect()
+    }
+
+    /// Updates the Elo ratings of all players after a finished game.  Only
+    /// games between human players are rated.
+    async fn update_ratings(
+        &self,
+        player_ids: &[i32],
+        winner: Option<usize>,
+    ) -> Result<(), GameError> {
+        let players = User::find()
+            .filter(entities::user::Column::Id.is_in(player_ids.iter().copied()))
+            .all(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (SELECT players for rating)", err))?;
+        if players.iter().any(|user| user.ai_strategy.is_some()) {
+            return Ok(());
+        }
+
+        let team_ratings = |team| {
+            let ratings: Vec<f64> = player_ids
+                .iter()
+                .enumerate()
+                .filter(|(position, _)| ZingGame::team_of_player(*position) == team)
+                .filter_map(|(_, id)| players.iter().find(|user| user.id == *id))
+                .map(|user| user.rating)
+                .collect();
+            team_rating(&ratings)
+        };
+        let changes = rating_changes((team_ratings(0), team_ratings(1)), winner);
+
+        for (position, id) in player_ids.iter().enumerate() {
+            let Some(user) = players.iter().find(|user| user.id == *id) else {
+                continue;
+            };
+            let change = if ZingGame::team_of_player(position) == 0 {
+                changes.0
+            } else {
+                changes.1
+            };
+            let mut user: entities::user::ActiveModel = user.clone().into();
+            user.rating = Set(user.rating.unwrap() + change);
+            user.rated_games = Set(user.rated_games.unwrap() + 1);
+            user.update(&self.db_conn)
+                .await
+                .map_err(|err| GameError::DBError("DB error (UPDATE user.rating)", err))?;
+        }
+
+        Ok(())
+    }
+
+    /// Statistics over all finished games of the given user.
+    pub async fn user_stats(&self, user: &entities::user::Model) -> Result<UserStats, GameError> {
+        let mut stats = UserStats::new(user);
+
+        let table_joins = TableJoin::find()
+            .filter(entities::table_join::Column::UserId.eq(user.id))
+            .all(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (SELECT table_join)", err))?;
+
+        for table_join in table_joins {
+            let player_names: Vec<String> = TableJoin::find()
+                .filter(entities::table_join::Column::TableId.eq(table_join.table_id))
+                .order_by_asc(entities::table_join::Column::TablePos)
+                .find_also_related(User)
+                .all(&self.db_conn)
+                .await
+                .map_err(|err| GameError::DBError("DB error (SELECT players)", err))?
+                .into_iter()
+                .map(|(_, user)| user.map(|user| user.name).unwrap_or_default())
+                .collect();
+
+            let game_results = GameResults::find()
+                .filter(entities::game_results::Column::TableId.eq(table_join.table_id))
+                .order_by_asc(entities::game_results::Column::Id)
+                .all(&self.db_conn)
+                .await
+                .map_err(|err| GameError::DBError("DB error (SELECT game_results)", err))?;
+
+            stats.add_table(table_join.table_pos as usize, &player_names, &game_results);
+        }
+
+        Ok(stats.finish())
+    }
+
+    /// Statistics of the registered user with the given user name.
+    pub async fn registered_user_stats(&self, username: &str) -> Result<UserStats, GameError> {
+        let user = User::find()
+            .filter(entities::user::Column::Username.eq(username))
+            .one(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB query failed unexpectedly", err))?
+            .ok_or(GameError::Request(
+                ErrorCode::UserNotFound,
+                "no registered user with this name",
+            ))?;
+
+        self.user_stats(&user).await
+    }
+
+    /// Registered players with the highest ratings.
+    pub async fn leaderboard(&self) -> Result<Vec<LeaderboardEntry>, GameError> {
+        let users = User::find()
+            .filter(entities::user::Column::Username.is_not_null())
+            .filter(entities::user::Column::RatedGames.gt(0))
+            .order_by_desc(entities::user::Column::Rating)
+            .limit(LEADERBOARD_LENGTH)
+            .all(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (SELECT leaderboard)", err))?;
+
+        Ok(users
+            .into_iter()
+            .enumerate()
+            .map(|(index, user)| LeaderboardEntry {
+                rank: index + 1,
+                name: user.name,
+                rating: user.rating,
+                rated_games: user.rated_games as u32,
+            })
+            .collect())
+    }
+
+    pub async fn match_replay(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+    ) -> Result<Json<MatchReplay>, GameError> {
+        Ok(Json(MatchReplay::new(
+            self.game_replays(user, table_token).await?,
+        )))
+    }
+
+    pub async fn game_replay(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+        game_index: usize,
+    ) -> Result<Json<GameReplay>, GameError> {
+        self.game_replays(user, table_token)
+            .await?
+            .into_iter()
+            .nth(game_index)
+            .map(Json)
+            .ok_or(GameError::Request(
+                ErrorCode::GameNotFound,
+                "no finished game with this index",
+            ))
+    }
+
     pub async fn user_index_at_table(
         &self,
         user: &entities::user::Model,
@@ -490,9 +1483,118 @@ impl ZingState {
             .get(table_token)
             .expect("we have just loaded the table");
 
-        table
-            .player_index(&user.token)
-            .ok_or(GameError::NotFound("user has not joined table"))
+        table.player_index(&user.token).ok_or(GameError::Request(
+            ErrorCode::NotAtTable,
+            "user has not joined table",
+        ))
+    }
+
+    /// Checks that the user may watch the table, either as a player or as a
+    /// spectator; returns whether the user is a spectator.
+    pub async fn check_table_viewer(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+    ) -> Result<bool, GameError> {
+        let table = self.find_table_with_token(table_token).await?;
+
+        self.ensure_loaded_table(table).await;
+
+        let tables = self.tables.read().unwrap();
+        let table = tables
+            .get(table_token)
+            .expect("we have just loaded the table");
+
+        if table.player_index(&user.token).is_some() {
+            Ok(false)
+        } else if table.allow_spectators() {
+            Ok(true)
+        } else {
+            Err(GameError::Request(
+                ErrorCode::NotAtTable,
+                "user has not joined table, which does not allow spectators",
+            ))
+        }
+    }
+
+    /// Lets the table owner allow or forbid watching the table without
+    /// playing.
+    pub async fn set_allow_spectators(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+        allow_spectators: bool,
+    ) -> Result<(), GameError> {
+        if self.user_index_at_table(user, table_token).await? != 0 {
+            return Err(GameError::Request(
+                ErrorCode::NotTableOwner,
+                "only the table owner may change whether spectators are allowed",
+            ));
+        }
+
+        self.tables
+            .write()
+            .unwrap()
+            .get_mut(table_token)
+            .ok_or(GameError::Request(
+                ErrorCode::TableNotFound,
+                "table id not found",
+            ))?
+            .set_allow_spectators(allow_spectators);
+
+        Table::update_many()
+            .col_expr(
+                entities::table::Column::AllowSpectators,
+                Expr::value(allow_spectators),
+            )
+            .filter(entities::table::Column::Token.eq(table_token))
+            .exec(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (UPDATE table.allow_spectators)", err))?;
+
+        self.send_table_notifications(table_token).await;
+
+        Ok(())
+    }
+
+    /// Lets the table owner switch move hints for all players on or off.
+    pub async fn set_allow_move_hints(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+        allow_move_hints: bool,
+    ) -> Result<(), GameError> {
+        if self.user_index_at_table(user, table_token).await? != 0 {
+            return Err(GameError::Request(
+                ErrorCode::NotTableOwner,
+                "only the table owner may change whether move hints are given",
+            ));
+        }
+
+        let notifications = {
+            let mut tables = self.tables.write().unwrap();
+            let table = tables.get_mut(table_token).ok_or(GameError::Request(
+                ErrorCode::TableNotFound,
+                "table id not found",
+            ))?;
+            table.set_allow_move_hints(allow_move_hints)
+        };
+
+        Table::update_many()
+            .col_expr(
+                entities::table::Column::AllowMoveHints,
+                Expr::value(allow_move_hints),
+            )
+            .filter(entities::table::Column::Token.eq(table_token))
+            .exec(&self.db_conn)
+            .await
+            .map_err(|err| GameError::DBError("DB error (UPDATE table.allow_move_hints)", err))?;
+
+        self.send_table_notifications(table_token).await;
+        self.send_notifications(notifications, Some(table_token))
+            .await;
+
+        Ok(())
     }
 
     pub async fn add_user_global_connection(
@@ -508,17 +1610,195 @@ impl ZingState {
         user: entities::user::Model,
         table_id: String,
         sender: NotificationSenderHandle,
-    ) {
-        let mut notification = None;
+        resume_from: Option<u64>,
+    ) -> Option<String> {
+        let chat_history = self.chat_history(&table_id).await;
+
+        let mut notifications = Vec::new();
+        let mut is_spectator = false;
+        let mut connection_id = None;
         if let Some(table) = self.tables.write().unwrap().get_mut(&table_id) {
-            notification = table.connection_opened(user, sender);
+            is_spectator = table.player_index(&user.token).is_none();
+            notifications = table.connection_opened(user, sender, resume_from);
+            if let Some(connection) = table.connections.last() {
+                connection_id = Some(connection.connection_id.clone());
+                if !chat_history.is_empty() {
+                    let notification = ClientNotification::ChatHistory(chat_history);
+                    notifications
+                        .push(connection.client_notification(&notification, table.next_seq()));
+                }
+            }
+        }
+
+        // send current state to newly connected user
+        self.send_notifications(notifications, Some(&table_id))
+            .await;
+
+        if is_spectator {
+            // update list of spectators
+            self.send_table_notifications(&table_id).await;
+        }
+
+        connection_id
+    }
+
+    /// Serializes a notification for a single connection to the given table.
+    pub fn table_notification_json(
+        &self,
+        table_token: &str,
+        notification: &ClientNotification,
+    ) -> Option<String> {
+        self.tables
+            .read()
+            .unwrap()
+            .get(table_token)
+            .map(|table| table.notification_json(notification))
+    }
+
+    /// Forgets a table connection after the client has closed it.
+    pub async fn remove_table_connection(&self, table_id: &str, connection_id: String) {
+        let was_spectator = {
+            let mut tables = self.tables.write().unwrap();
+            let Some(table) = tables.get_mut(table_id) else {
+                return;
+            };
+            let was_spectator = table
+                .connections
+                .iter()
+                .find(|c| c.connection_id == connection_id)
+                .is_some_and(|c| table.player_index(c.client_login_token()).is_none());
+            table.connections.remove(connection_id);
+            was_spectator
+        };
+
+        if was_spectator {
+            // update list of spectators
+            self.send_table_notifications(table_id).await;
+        }
+    }
+
+    /// Executes a command received via the table WebSocket, with the same
+    /// effect as the corresponding REST API call.
+    pub async fn handle_command(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+        command: Command,
+    ) -> Result<(), GameError> {
+        match command {
+            Command::PlayCard { card_index } => self.play_card(user, table_token, card_index).await,
+            Command::PerformAction { action } => {
+                self.perform_action(user, table_token, &action).await
+            }
+            Command::StartGame => self.start_game(user, table_token).await,
+            Command::FinishGame => self.finish_game(user, table_token).await,
+            Command::Ping => Ok(()),
+            Command::Chat(content) => self.send_chat(user, table_token, content).await,
         }
+    }
+
+    /// Stores a chat message (or emote) of a player or spectator and sends it
+    /// to everybody at the table.
+    pub async fn send_chat(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+        content: ChatContent,
+    ) -> Result<(), GameError> {
+        self.check_table_viewer(user, table_token).await?;
+        content
+            .validate()
+            .map_err(|msg| GameError::Request(ErrorCode::InvalidChatMessage, msg))?;
+
+        let (table_id, player) = {
+            let mut tables = self.tables.write().unwrap();
+            let table = tables.get_mut(table_token).ok_or(GameError::Request(
+                ErrorCode::TableNotFound,
+                "table id not found",
+            ))?;
+            table.count_chat_message(user.id)?;
+            (table.table().id, table.player_index(&user.token))
+        };
 
-        if let Some(notification) = notification {
-            // send current state to newly connected user
-            self.send_notifications(vec![notification], Some(&table_id))
-                .await;
+        let (text, emote) = match &content {
+            ChatContent::Text(text) => (Some(text.clone()), None),
+            ChatContent::Emote(emote) => (None, Some(emote_name(*emote))),
+        };
+        let chat_message = entities::chat_message::ActiveModel {
+            table_id: Set(table_id),
+            user_id: Set(user.id),
+            text: Set(text),
+            emote: Set(emote),
+            created_at: Set(now()),
+            ..Default::default()
         }
+        .insert(&self.db_conn)
+        .await
+        .map_err(|err| GameError::DBError("DB error (INSERT chat_message)", err))?;
+
+        let message = ChatMessageInfo {
+            user_name: user.name.clone(),
+            player,
+            sent_at: chat_message.created_at.timestamp_millis() as u64,
+            content,
+        };
+        let notifications = self
+            .tables
+            .read()
+            .unwrap()
+            .get(table_token)
+            .map(|table| table.chat_notifications(message))
+            .unwrap_or_default();
+        self.send_notifications(notifications, Some(table_token))
+            .await;
+
+        Ok(())
+    }
+
+    /// Loads the most recent chat messages at the given (loaded) table, oldest
+    /// first.
+    async fn chat_history(&self, table_token: &str) -> Vec<ChatMessageInfo> {
+        let Ok(table) = self.find_table_with_token(table_token).await else {
+            return Vec::new();
+        };
+
+        let messages = match ChatMessage::find()
+            .find_also_related(User)
+            .filter(entities::chat_message::Column::TableId.eq(table.id))
+            .order_by_desc(entities::chat_message::Column::Id)
+            .limit(CHAT_HISTORY_LENGTH)
+            .all(&self.db_conn)
+            .await
+        {
+            Ok(messages) => messages,
+            Err(err) => {
+                warn!("could not load chat history: {err}");
+                return Vec::new();
+            }
+        };
+
+        let tables = self.tables.read().unwrap();
+        let loaded = tables.get(table_token);
+        messages
+            .into_iter()
+            .rev()
+            .filter_map(|(chat_message, user)| {
+                let user = user?;
+                let content = match (chat_message.text, chat_message.emote) {
+                    (Some(text), _) => ChatContent::Text(text),
+                    (None, Some(emote)) => ChatContent::Emote(
+                        serde_json::from_value(serde_json::Value::String(emote)).ok()?,
+                    ),
+                    (None, None) => return None,
+                };
+                Some(ChatMessageInfo {
+                    player: loaded.and_then(|table| table.player_index(&user.token)),
+                    user_name: user.name,
+                    sent_at: chat_message.created_at.timestamp_millis() as u64,
+                    content,
+                })
+            })
+            .collect()
     }
 
     pub async fn play_card(
@@ -526,37 +1806,106 @@ impl ZingState {
         user: &entities::user::Model,
         table_token: &str,
         card_index: usize,
+    ) -> Result<(), GameError> {
+        self.play_own_card(user, table_token, |game, player_index| {
+            game.play_card(player_index, card_index)
+        })
+        .await
+    }
+
+    /// Plays the card described by an action built by the client, after
+    /// checking it against the rules of the game (see [ActionRules]).
+    pub async fn perform_action(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+        action: &CardAction,
+    ) -> Result<(), GameError> {
+        self.play_own_card(user, table_token, |game, player_index| {
+            game.perform_action(player_index, action)
+        })
+        .await
+    }
+
+    async fn play_own_card(
+        &self,
+        user: &entities::user::Model,
+        table_token: &str,
+        play: impl FnOnce(&mut ZingGame, usize) -> Result<(), PlayError>,
+    ) -> Result<(), GameError> {
+        let player_index = self.user_index_at_table(user, table_token).await?;
+
+        self.play_card_at(table_token, |game| play(game, player_index))
+            .await?;
+
+        // a player who plays themselves is no longer idle
+        let idle_changed = self
+            .tables
+            .write()
+            .unwrap()
+            .get_mut(table_token)
+            .is_some_and(|table| table.set_idle(player_index, false));
+        if idle_changed {
+            self.send_table_notifications(table_token).await;
+        }
+
+        Ok(())
+    }
+
+    /// Plays a card (for a human player or a computer player) using the given
+    /// function, notifying all connected users.
+    async fn play_card_at(
+        &self,
+        table_token: &str,
+        play: impl FnOnce(&mut ZingGame) -> Result<(), PlayError>,
     ) -> Result<(), GameError> {
         let table_notifications;
+        let mut match_notifications = Vec::new();
+        let mut next_game = None;
         let result;
 
         let (game_json, phase_changed) = {
-            let player_index = self.user_index_at_table(user, table_token).await?;
-
             let table_id;
             let old_phase;
             let mut finished_points = None;
+            let mut finished_replay = None;
+            let mut zing_count = (0, 0);
+            let mut finished_events = None;
+            let player_ids;
             {
                 let mut tables = self.tables.write().unwrap();
-                let table = tables
-                    .get_mut(table_token)
-                    .ok_or(GameError::NotFound("table id not found"))?;
+                let table = tables.get_mut(table_token).ok_or(GameError::Request(
+                    ErrorCode::TableNotFound,
+                    "table id not found",
+                ))?;
                 table_id = table.table().id;
+                player_ids = table.player_ids();
 
-                let game = table
-                    .game
-                    .as_mut()
-                    .ok_or(GameError::Conflict("game not started yet"))?;
+                let game = table.game.as_mut().ok_or(GameError::Request(
+                    ErrorCode::GameNotStarted,
+                    "game not started yet",
+                ))?;
 
                 old_phase = game.state().phase;
 
-                result = game
-                    .play_card(player_index, card_index)
-                    .map_err(GameError::Conflict);
+                result = play(game).map_err(GameError::from);
 
                 if result.is_ok() && game.state().phase == GamePhase::Finished {
-                    finished_points = Some(game.points());
-                    table.game_results.push(finished_points.clone().unwrap());
+                    let points = game.points();
+                    table
+                        .zing_match
+                        .add_game_result(points.clone())
+                        .map_err(|msg| GameError::Request(ErrorCode::MatchDecided, msg))?;
+                    finished_points = Some(points);
+                    zing_count = game.zing_count();
+                    table
+                        .game_summaries
+                        .push(Some(ZingEventSummary::from_events(game.events())));
+                    finished_events =
+                        Some(serde_json::to_value(game.events()).expect("events are serializable"));
+                    finished_replay = GameReplay::new(game).ok().map(|replay| {
+                        serde_json::to_value(replay).expect("replays are serializable")
+                    });
                 }
             }
 
@@ -570,10 +1919,17 @@ impl ZingState {
                     card_count_points1: Set(finished_points.card_count_points.1 as i32),
                     zing_points0: Set(finished_points.zing_points.0 as i32),
                     zing_points1: Set(finished_points.zing_points.1 as i32),
+                    zings0: Set(Some(zing_count.0 as i32)),
+                    zings1: Set(Some(zing_count.1 as i32)),
+                    replay: Set(finished_replay),
+                    events: Set(finished_events),
                 }
                 .insert(&self.db_conn)
                 .await
-                .map_err(|_| GameError::DBError("DB error (INSERT game_results)"))?;
+                .map_err(|err| GameError::DBError("DB error (INSERT game_results)", err))?;
+
+                self.update_ratings(&player_ids, finished_points.winner())
+                    .await?;
             }
 
             let tables = self.tables.read().unwrap();
@@ -582,6 +1938,12 @@ impl ZingState {
             let new_phase = game.state().phase();
 
             table_notifications = table.action_notifications();
+            if new_phase != old_phase && new_phase == GamePhase::Finished {
+                match_notifications = table.match_status_notifications();
+                if !table.zing_match.finished() {
+                    next_game = Some(table.zing_match.games_played());
+                }
+            }
 
             (table.game_json(), new_phase != old_phase)
         };
@@ -589,6 +1951,8 @@ impl ZingState {
         // send notifications about performed actions
         self.send_notifications(table_notifications, Some(table_token))
             .await;
+        self.send_notifications(match_notifications, Some(table_token))
+            .await;
         if phase_changed {
             self.send_table_notifications(table_token).await;
         }
@@ -598,8 +1962,28 @@ impl ZingState {
             .filter(entities::table::Column::Token.eq(table_token))
             .exec(&self.db_conn)
             .await
-            .map_err(|_| GameError::DBError("DB error (UPDATE table.game)"))?;
+            .map_err(|err| GameError::DBError("DB error (UPDATE table.game)", err))?;
+
+        if result.is_ok() {
+            self.schedule_bot_move(table_token);
+            self.start_turn_timer(table_token).await;
+        }
+        if let Some(games_played) = next_game {
+            // sending only fails if run_next_games() has ended, i.e. during shutdown
+            let start_time = self.clock.now() + self.next_game_delay;
+            let _ = self
+                .next_games
+                .send((table_token.to_owned(), games_played, start_time));
+        }
 
         result
     }
 }
+
+/// Name under which emotes are stored in the DB (same as in JSON)
+fn emote_name(emote: Emote) -> String {
+    serde_json::to_value(emote)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_owned))
+        .expect("emotes serialize to strings")
+}
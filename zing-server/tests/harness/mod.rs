@@ -0,0 +1,248 @@
+//! In-process test server: runs [zing_server::app] on an ephemeral port with
+//! an in-memory SQLite database, so that the API can be tested without
+//! starting a server (or a database) first.  Every test gets its own server.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use futures::{SinkExt, StreamExt};
+use reqwest::{
+    cookie::{CookieStore, Jar},
+    RequestBuilder, Url,
+};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, http, Message};
+use zing_game::client_notification::{ClientNotification, NotificationMessage, PROTOCOL_VERSION};
+use zing_server::{
+    app, clock::Clock, maintenance::MaintenanceConfig, storage::open_database, ZingState,
+};
+
+/// Same as the server's default (tests rely on computer players not playing
+/// immediately).
+const TEST_BOT_DELAY: Duration = Duration::from_millis(1000);
+
+/// Pause before the next game of a match starts automatically.
+pub const TEST_NEXT_GAME_DELAY: Duration = Duration::from_millis(1000);
+
+/// Time to wait for a notification before giving up.
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct TestServer {
+    base_url: String,
+}
+
+impl TestServer {
+    pub async fn start() -> Result<Self> {
+        Self::start_with(TEST_BOT_DELAY, MaintenanceConfig::default()).await
+    }
+
+    pub async fn start_with(bot_delay: Duration, maintenance: MaintenanceConfig) -> Result<Self> {
+        Self::start_with_clock(bot_delay, maintenance, Clock::system()).await
+    }
+
+    /// Server whose timers run on the given clock (e.g. a manual one, so that
+    /// tests of time limits do not depend on how fast they run).
+    pub async fn start_with_clock(
+        bot_delay: Duration,
+        maintenance: MaintenanceConfig,
+        clock: Clock,
+    ) -> Result<Self> {
+        let db_conn = open_database("sqlite::memory:")
+            .await
+            .map_err(|err| anyhow!("cannot open test database: {err}"))?;
+        let state =
+            ZingState::new(db_conn, bot_delay, TEST_NEXT_GAME_DELAY, maintenance, clock).await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        // the server runs until the test (and with it, the runtime) ends
+        tokio::spawn(async move { axum::serve(listener, app(state)).await });
+
+        Ok(Self {
+            base_url: format!("http://{address}"),
+        })
+    }
+
+    /// Full URL of the given path (e.g. "/login").
+    pub fn url(&self, path: impl AsRef<str>) -> String {
+        format!("{}{}", self.base_url, path.as_ref())
+    }
+
+    /// New client with its own cookies (i.e. not logged in yet).
+    pub fn client(&self) -> TestClient {
+        let cookies = Arc::new(Jar::default());
+        let http = reqwest::Client::builder()
+            .cookie_provider(cookies.clone())
+            .build()
+            .expect("HTTP client with default settings");
+        TestClient {
+            http,
+            cookies,
+            base_url: self.base_url.clone(),
+        }
+    }
+
+    /// New client logged in as an anonymous user with the given name.
+    pub async fn login(&self, name: &str) -> Result<TestClient> {
+        let client = self.client();
+        client
+            .post("/login")
+            .json(&json!({ "name": name }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(client)
+    }
+
+    pub async fn login_clients(&self, names: &[&str]) -> Result<Vec<TestClient>> {
+        let mut clients = Vec::new();
+        for name in names {
+            clients.push(self.login(name).await?);
+        }
+        Ok(clients)
+    }
+}
+
+pub struct TestClient {
+    http: reqwest::Client,
+    cookies: Arc<Jar>,
+    base_url: String,
+}
+
+impl TestClient {
+    pub fn get(&self, path: impl AsRef<str>) -> RequestBuilder {
+        self.http.get(self.url(path))
+    }
+
+    pub fn post(&self, path: impl AsRef<str>) -> RequestBuilder {
+        self.http.post(self.url(path))
+    }
+
+    pub fn put(&self, path: impl AsRef<str>) -> RequestBuilder {
+        self.http.put(self.url(path))
+    }
+
+    pub fn delete(&self, path: impl AsRef<str>) -> RequestBuilder {
+        self.http.delete(self.url(path))
+    }
+
+    fn url(&self, path: impl AsRef<str>) -> String {
+        format!("{}{}", self.base_url, path.as_ref())
+    }
+
+    /// Value of the Cookie header the client sends (i.e. the login token).
+    pub fn login_cookie(&self) -> Option<String> {
+        let url = Url::parse(&self.base_url).expect("server URL is valid");
+        self.cookies
+            .cookies(&url)
+            .and_then(|header| header.to_str().ok().map(str::to_owned))
+    }
+
+    /// Creates a table and returns its id.
+    pub async fn create_table(&self) -> Result<String> {
+        let table_info = self
+            .post("/table")
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+        Ok(table_info["id"]
+            .as_str()
+            .context("table status should have id")?
+            .to_owned())
+    }
+
+    /// Joins the table and returns its table info.
+    pub async fn join_table(&self, table_id: &str) -> Result<Value> {
+        Ok(self
+            .post(format!("/table/{}", table_id))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?)
+    }
+
+    /// Seats a computer player at the table and returns its table info.
+    pub async fn add_bot(&self, table_id: &str, strategy: &str) -> Result<Value> {
+        Ok(self
+            .post(format!("/table/{}/bot", table_id))
+            .json(&json!({ "strategy": strategy }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?)
+    }
+
+    /// Opens the table's WebSocket; `query` is appended to the URL (e.g.
+    /// "?version=1").
+    pub async fn connect_table_ws(&self, table_id: &str, query: &str) -> Result<TestWebSocket> {
+        let ws_url = self.base_url.replacen("http://", "ws://", 1);
+        let mut request =
+            format!("{}/table/{}/ws{}", ws_url, table_id, query).into_client_request()?;
+        if let Some(cookie) = self.login_cookie() {
+            request
+                .headers_mut()
+                .insert(http::header::COOKIE, cookie.parse()?);
+        }
+        let (ws, _response) = tokio_tungstenite::connect_async(request).await?;
+        Ok(TestWebSocket { ws })
+    }
+}
+
+type WebSocketStream =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+pub struct TestWebSocket {
+    ws: WebSocketStream,
+}
+
+impl TestWebSocket {
+    /// Sends a client command (JSON with "request_id" and "command").
+    pub async fn send(&mut self, command: Value) -> Result<()> {
+        self.ws.send(Message::text(command.to_string())).await?;
+        Ok(())
+    }
+
+    pub async fn next_notification(&mut self) -> Result<NotificationMessage> {
+        tokio::time::timeout(NOTIFICATION_TIMEOUT, async {
+            while let Some(msg) = self.ws.next().await {
+                if let Message::Text(json) = msg? {
+                    let message: NotificationMessage = serde_json::from_str(&json)?;
+                    assert_eq!(message.version, PROTOCOL_VERSION);
+                    return Ok(message);
+                }
+            }
+            bail!("WebSocket closed unexpectedly")
+        })
+        .await
+        .context("no notification received in time")?
+    }
+
+    /// Sends a command and collects all notifications up to its result.
+    pub async fn send_command(&mut self, command: Value) -> Result<Vec<ClientNotification>> {
+        let request_id = command["request_id"]
+            .as_u64()
+            .context("command should have a request id")?;
+        self.send(command).await?;
+
+        let mut notifications = Vec::new();
+        loop {
+            let notification = self.next_notification().await?.notification;
+            let done = matches!(notification,
+                ClientNotification::CommandResult(id, _) if id == request_id);
+            notifications.push(notification);
+            if done {
+                return Ok(notifications);
+            }
+        }
+    }
+
+    pub async fn close(&mut self) -> Result<()> {
+        self.ws.close(None).await?;
+        Ok(())
+    }
+}
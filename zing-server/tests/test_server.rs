@@ -1,29 +1,42 @@
 use anyhow::{Context, Result};
 use reqwest::StatusCode;
 use serde_json::{json, Value};
+use std::time::Duration;
+use zing_game::chat::{ChatContent, Emote};
+use zing_game::client_command::CommandErrorKind;
+use zing_game::client_notification::{ClientNotification, TurnTimer, PROTOCOL_VERSION};
+use zing_game::error_code::{ErrorBody, ErrorCode};
+use zing_game::zing_game::MoveHint;
+
+mod harness;
+
+use harness::{TestClient, TestServer, TestWebSocket, TEST_NEXT_GAME_DELAY};
+use zing_server::clock::Clock;
+use zing_server::maintenance::MaintenanceConfig;
 
 #[tokio::test]
 async fn test_login_logout() -> Result<()> {
-    let client = reqwest::Client::builder().cookie_store(true).build()?;
+    let server = TestServer::start().await?;
+    let client = server.client();
 
     let login_response = client
-        .post("http://localhost:8000/login")
+        .post("/login")
         .json(&json!({ "name": "John Doe" }))
         .send()
         .await?;
     assert_eq!(login_response.status(), StatusCode::OK);
 
-    let status_response = client.get("http://localhost:8000/login").send().await?;
+    let status_response = client.get("/login").send().await?;
     assert_eq!(status_response.status(), StatusCode::OK);
     // TODO: check JSON output?
 
-    let status_response = client.delete("http://localhost:8000/login").send().await?;
+    let status_response = client.delete("/login").send().await?;
     assert_eq!(status_response.status(), StatusCode::OK);
 
-    let status_response = client.get("http://localhost:8000/login").send().await?;
+    let status_response = client.get("/login").send().await?;
     assert_eq!(status_response.status(), StatusCode::UNAUTHORIZED);
 
-    let status_response = client.delete("http://localhost:8000/login").send().await?;
+    let status_response = client.delete("/login").send().await?;
     assert_eq!(status_response.status(), StatusCode::UNAUTHORIZED);
 
     Ok(())
@@ -31,21 +44,17 @@ async fn test_login_logout() -> Result<()> {
 
 #[tokio::test]
 async fn test_create_table() -> Result<()> {
-    let client = reqwest::Client::builder().cookie_store(true).build()?;
+    let server = TestServer::start().await?;
+    let client = server.client();
 
     let login_response = client
-        .post("http://localhost:8000/login")
+        .post("/login")
         .json(&json!({ "name": "Jane Doe" }))
         .send()
         .await?;
     assert_eq!(login_response.status(), StatusCode::OK);
 
-    let tables_status = client
-        .get("http://localhost:8000/table")
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
+    let tables_status = client.get("/table").send().await?.json::<Value>().await?;
     assert_eq!(
         tables_status
             .as_array()
@@ -54,22 +63,12 @@ async fn test_create_table() -> Result<()> {
         0
     );
 
-    let create_response = client
-        .post("http://localhost:8000/table")
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
+    let create_response = client.post("/table").send().await?.json::<Value>().await?;
     let table_id = create_response["id"]
         .as_str()
         .context("table status should have id")?;
 
-    let tables_status = client
-        .get("http://localhost:8000/table")
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
+    let tables_status = client.get("/table").send().await?.json::<Value>().await?;
     assert_eq!(
         tables_status
             .as_array()
@@ -79,26 +78,18 @@ async fn test_create_table() -> Result<()> {
     );
 
     let table_status = client
-        .get(format!("http://localhost:8000/table/{}", table_id))
+        .get(format!("/table/{}", table_id))
         .send()
         .await?
         .json::<Value>()
         .await?;
     assert_eq!(table_status["id"], table_id);
 
-    let join_response = client
-        .post(format!("http://localhost:8000/table/{}", table_id))
-        .send()
-        .await?;
+    let join_response = client.post(format!("/table/{}", table_id)).send().await?;
     // must not be able to join table again
     assert_eq!(join_response.status(), StatusCode::CONFLICT);
 
-    let tables_status = client
-        .get("http://localhost:8000/table")
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
+    let tables_status = client.get("/table").send().await?.json::<Value>().await?;
     assert_eq!(
         tables_status
             .as_array()
@@ -112,59 +103,52 @@ async fn test_create_table() -> Result<()> {
 
 #[tokio::test]
 async fn test_game_starting() -> Result<()> {
-    let client1 = reqwest::Client::builder().cookie_store(true).build()?;
+    let server = TestServer::start().await?;
+    let client1 = server.client();
 
     client1
-        .post("http://localhost:8000/login")
+        .post("/login")
         .json(&json!({ "name": "Player 1" }))
         .send()
         .await?;
 
-    let create_response = client1
-        .post("http://localhost:8000/table")
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
+    let create_response = client1.post("/table").send().await?.json::<Value>().await?;
     let table_id = create_response["id"]
         .as_str()
         .context("table status should have id")?;
 
     let start_response = client1
-        .post(format!("http://localhost:8000/table/{}/game", table_id))
+        .post(format!("/table/{}/game", table_id))
         .send()
         .await?;
     // must not be able to start game with single player at table
     assert_eq!(start_response.status(), StatusCode::CONFLICT);
 
-    let client2 = reqwest::Client::builder().cookie_store(true).build()?;
+    let client2 = server.client();
 
     client2
-        .post("http://localhost:8000/login")
+        .post("/login")
         .json(&json!({ "name": "Player 2" }))
         .send()
         .await?;
 
-    let join_response = client2
-        .post(format!("http://localhost:8000/table/{}", table_id))
-        .send()
-        .await?;
+    let join_response = client2.post(format!("/table/{}", table_id)).send().await?;
     assert_eq!(join_response.status(), StatusCode::OK);
 
     let inactive_response = client2
-        .get(format!("http://localhost:8000/table/{}/game", table_id))
+        .get(format!("/table/{}/game", table_id))
         .send()
         .await?;
     assert_eq!(inactive_response.status(), StatusCode::NOT_FOUND);
 
     let start_response = client2
-        .post(format!("http://localhost:8000/table/{}/game", table_id))
+        .post(format!("/table/{}/game", table_id))
         .send()
         .await?;
     assert_eq!(start_response.status(), StatusCode::OK);
 
     let active_response = client2
-        .get(format!("http://localhost:8000/table/{}/game", table_id))
+        .get(format!("/table/{}/game", table_id))
         .send()
         .await?
         .json::<Value>()
@@ -177,7 +161,7 @@ async fn test_game_starting() -> Result<()> {
     );
 
     let active_response = client1
-        .get(format!("http://localhost:8000/table/{}/game", table_id))
+        .get(format!("/table/{}/game", table_id))
         .send()
         .await?
         .json::<Value>()
@@ -190,7 +174,7 @@ async fn test_game_starting() -> Result<()> {
     );
 
     let start_response = client2
-        .post(format!("http://localhost:8000/table/{}/game", table_id))
+        .post(format!("/table/{}/game", table_id))
         .send()
         .await?;
     assert_eq!(start_response.status(), StatusCode::CONFLICT);
@@ -200,57 +184,44 @@ async fn test_game_starting() -> Result<()> {
 
 #[tokio::test]
 async fn test_playing_cards() -> Result<()> {
-    let client2 = reqwest::Client::builder().cookie_store(true).build()?;
+    let server = TestServer::start().await?;
+    let client2 = server.client();
 
     client2
-        .post("http://localhost:8000/login")
+        .post("/login")
         .json(&json!({ "name": "Player 2" }))
         .send()
         .await?;
 
-    let client1 = reqwest::Client::builder().cookie_store(true).build()?;
+    let client1 = server.client();
 
     client1
-        .post("http://localhost:8000/login")
+        .post("/login")
         .json(&json!({ "name": "Player 1" }))
         .send()
         .await?;
 
-    let create_response = client1
-        .post("http://localhost:8000/table")
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
+    let create_response = client1.post("/table").send().await?.json::<Value>().await?;
     let table_id = create_response["id"]
         .as_str()
         .context("table status should have id")?;
 
-    client2
-        .post(format!("http://localhost:8000/table/{}", table_id))
-        .send()
-        .await?;
+    client2.join_table(table_id).await?;
 
     client1
-        .post(format!("http://localhost:8000/table/{}/game", table_id))
+        .post(format!("/table/{}/game", table_id))
         .send()
         .await?;
 
     let play_response = client2
-        .post(format!(
-            "http://localhost:8000/table/{}/game/play",
-            table_id
-        ))
+        .post(format!("/table/{}/game/play", table_id))
         .json(&json!({ "card_index": 2 }))
         .send()
         .await?;
     assert_eq!(play_response.status(), StatusCode::OK);
 
     let play_response = client2
-        .post(format!(
-            "http://localhost:8000/table/{}/game/play",
-            table_id
-        ))
+        .post(format!("/table/{}/game/play", table_id))
         .json(&json!({ "card_index": 1 }))
         .send()
         .await?;
@@ -258,20 +229,14 @@ async fn test_playing_cards() -> Result<()> {
     assert_eq!(play_response.status(), StatusCode::CONFLICT);
 
     let play_response = client1
-        .post(format!(
-            "http://localhost:8000/table/{}/game/play",
-            table_id
-        ))
+        .post(format!("/table/{}/game/play", table_id))
         .json(&json!({ "card_index": 3 }))
         .send()
         .await?;
     assert_eq!(play_response.status(), StatusCode::OK);
 
     let play_response = client1
-        .post(format!(
-            "http://localhost:8000/table/{}/game/play",
-            table_id
-        ))
+        .post(format!("/table/{}/game/play", table_id))
         .json(&json!({ "card_index": 0 }))
         .send()
         .await?;
@@ -279,10 +244,7 @@ async fn test_playing_cards() -> Result<()> {
     assert_eq!(play_response.status(), StatusCode::CONFLICT);
 
     let play_response = client2
-        .post(format!(
-            "http://localhost:8000/table/{}/game/play",
-            table_id
-        ))
+        .post(format!("/table/{}/game/play", table_id))
         .json(&json!({ "card_index": 3 }))
         .send()
         .await?;
@@ -290,7 +252,7 @@ async fn test_playing_cards() -> Result<()> {
     assert_eq!(play_response.status(), StatusCode::CONFLICT);
 
     let ended_response = client2
-        .get(format!("http://localhost:8000/table/{}/game", table_id))
+        .get(format!("/table/{}/game", table_id))
         .send()
         .await?
         .json::<Value>()
@@ -305,20 +267,14 @@ async fn test_playing_cards() -> Result<()> {
     // echo "players have 48 cards in total; 2 have been played already, 2*23 to go..."
     for _ in 0..23 {
         let play_response = client2
-            .post(format!(
-                "http://localhost:8000/table/{}/game/play",
-                table_id
-            ))
+            .post(format!("/table/{}/game/play", table_id))
             .json(&json!({ "card_index": 0 }))
             .send()
             .await?;
         assert_eq!(play_response.status(), StatusCode::OK);
 
         let play_response = client1
-            .post(format!(
-                "http://localhost:8000/table/{}/game/play",
-                table_id
-            ))
+            .post(format!("/table/{}/game/play", table_id))
             .json(&json!({ "card_index": 0 }))
             .send()
             .await?;
@@ -326,27 +282,21 @@ async fn test_playing_cards() -> Result<()> {
     }
 
     let play_response = client2
-        .post(format!(
-            "http://localhost:8000/table/{}/game/play",
-            table_id
-        ))
+        .post(format!("/table/{}/game/play", table_id))
         .json(&json!({ "card_index": 0 }))
         .send()
         .await?;
     assert_eq!(play_response.status(), StatusCode::CONFLICT);
 
     let play_response = client1
-        .post(format!(
-            "http://localhost:8000/table/{}/game/play",
-            table_id
-        ))
+        .post(format!("/table/{}/game/play", table_id))
         .json(&json!({ "card_index": 0 }))
         .send()
         .await?;
     assert_eq!(play_response.status(), StatusCode::CONFLICT);
 
     let ended_response = client2
-        .get(format!("http://localhost:8000/table/{}/game", table_id))
+        .get(format!("/table/{}/game", table_id))
         .send()
         .await?
         .json::<Value>()
@@ -359,16 +309,1739 @@ async fn test_playing_cards() -> Result<()> {
     );
 
     let finish_response = client1
-        .delete(format!("http://localhost:8000/table/{}/game", table_id))
+        .delete(format!("/table/{}/game", table_id))
         .send()
         .await?;
     assert_eq!(finish_response.status(), StatusCode::OK);
 
     let inactive_response = client2
-        .get(format!("http://localhost:8000/table/{}/game", table_id))
+        .get(format!("/table/{}/game", table_id))
         .send()
         .await?;
     assert_eq!(inactive_response.status(), StatusCode::NOT_FOUND);
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_bot_opponent() -> Result<()> {
+    let server = TestServer::start().await?;
+    let client = server.client();
+
+    client
+        .post("/login")
+        .json(&json!({ "name": "Bot Owner" }))
+        .send()
+        .await?;
+
+    let strategies = client.get("/bot").send().await?.json::<Value>().await?;
+    assert!(strategies
+        .as_array()
+        .context("expected array of strategies")?
+        .contains(&json!("random")));
+
+    let create_response = client.post("/table").send().await?.json::<Value>().await?;
+    let table_id = create_response["id"]
+        .as_str()
+        .context("table status should have id")?;
+
+    let other_client = server.client();
+    other_client
+        .post("/login")
+        .json(&json!({ "name": "Someone Else" }))
+        .send()
+        .await?;
+    let bot_response = other_client
+        .post(format!("/table/{}/bot", table_id))
+        .json(&json!({ "strategy": "random" }))
+        .send()
+        .await?;
+    // only players at the table (in fact, only its owner) may add bots
+    assert_eq!(bot_response.status(), StatusCode::NOT_FOUND);
+
+    let bot_response = client
+        .post(format!("/table/{}/bot", table_id))
+        .json(&json!({ "strategy": "no such strategy" }))
+        .send()
+        .await?;
+    assert_eq!(bot_response.status(), StatusCode::BAD_REQUEST);
+
+    let bot_response = client
+        .post(format!("/table/{}/bot", table_id))
+        .json(&json!({ "strategy": "random" }))
+        .send()
+        .await?;
+    assert_eq!(bot_response.status(), StatusCode::OK);
+    let table_status = bot_response.json::<Value>().await?;
+    assert_eq!(
+        table_status["user_names"]
+            .as_array()
+            .context("table status should have user names")?
+            .len(),
+        2
+    );
+
+    let start_response = client
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?;
+    assert_eq!(start_response.status(), StatusCode::OK);
+
+    // the owner deals the first game, so the bot plays first (after some delay)
+    let mut bot_cards = 0;
+    for _ in 0..100 {
+        let game_status = client
+            .get(format!("/table/{}/game", table_id))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+        bot_cards = game_status["players"][1]["hand"]
+            .as_array()
+            .context("game state should contain bot's hand")?
+            .len();
+        if bot_cards == 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(bot_cards, 3);
+
+    let play_response = client
+        .post(format!("/table/{}/game/play", table_id))
+        .json(&json!({ "card_index": 0 }))
+        .send()
+        .await?;
+    assert_eq!(play_response.status(), StatusCode::OK);
+
+    let remove_response = client
+        .delete(format!("/table/{}/bot/1", table_id))
+        .send()
+        .await?;
+    // cannot leave a table after games have started
+    assert_eq!(remove_response.status(), StatusCode::CONFLICT);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_take_backs() -> Result<()> {
+    let server = TestServer::start().await?;
+    let client1 = server.client();
+    client1
+        .post("/login")
+        .json(&json!({ "name": "Player 1" }))
+        .send()
+        .await?;
+
+    let client2 = server.client();
+    client2
+        .post("/login")
+        .json(&json!({ "name": "Player 2" }))
+        .send()
+        .await?;
+
+    let create_response = client1.post("/table").send().await?.json::<Value>().await?;
+    let table_id = create_response["id"]
+        .as_str()
+        .context("table status should have id")?;
+
+    client2.join_table(table_id).await?;
+
+    client1
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?;
+
+    let play_response = client2
+        .post(format!("/table/{}/game/play", table_id))
+        .json(&json!({ "card_index": 1 }))
+        .send()
+        .await?;
+    assert_eq!(play_response.status(), StatusCode::OK);
+
+    let undo_response = client2
+        .post(format!("/table/{}/game/undo", table_id))
+        .send()
+        .await?;
+    // take-backs are not allowed by default
+    assert_eq!(undo_response.status(), StatusCode::CONFLICT);
+
+    for client in [&client1, &client2] {
+        let agreement_response = client
+            .put(format!("/table/{}/take_backs", table_id))
+            .json(&json!({ "allow": true }))
+            .send()
+            .await?;
+        assert_eq!(agreement_response.status(), StatusCode::OK);
+    }
+
+    let table_status = client1
+        .get(format!("/table/{}", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(table_status["allow_take_backs"], json!([true, true]));
+
+    let undo_response = client1
+        .post(format!("/table/{}/game/undo", table_id))
+        .send()
+        .await?;
+    // player 1 has not played a card yet
+    assert_eq!(undo_response.status(), StatusCode::CONFLICT);
+
+    let undo_response = client2
+        .post(format!("/table/{}/game/undo", table_id))
+        .send()
+        .await?;
+    assert_eq!(undo_response.status(), StatusCode::OK);
+
+    let game_status = client2
+        .get(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(game_status["phase"], "Prepared");
+    assert_eq!(
+        game_status["players"][1]["hand"]
+            .as_array()
+            .context("game state should contain own hand")?
+            .len(),
+        4
+    );
+
+    // it's player 2's turn again
+    let play_response = client2
+        .post(format!("/table/{}/game/play", table_id))
+        .json(&json!({ "card_index": 0 }))
+        .send()
+        .await?;
+    assert_eq!(play_response.status(), StatusCode::OK);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_replay_export() -> Result<()> {
+    let server = TestServer::start().await?;
+    let client2 = server.client();
+    client2
+        .post("/login")
+        .json(&json!({ "name": "Replayer 2" }))
+        .send()
+        .await?;
+
+    let client1 = server.client();
+    client1
+        .post("/login")
+        .json(&json!({ "name": "Replayer 1" }))
+        .send()
+        .await?;
+
+    let create_response = client1.post("/table").send().await?.json::<Value>().await?;
+    let table_id = create_response["id"]
+        .as_str()
+        .context("table status should have id")?;
+
+    client2.join_table(table_id).await?;
+
+    client1
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?;
+
+    // no game has been finished yet
+    let replay_response = client1
+        .get(format!("/table/{}/replay/0", table_id))
+        .send()
+        .await?;
+    assert_eq!(replay_response.status(), StatusCode::NOT_FOUND);
+
+    for _ in 0..24 {
+        for client in [&client2, &client1] {
+            let play_response = client
+                .post(format!("/table/{}/game/play", table_id))
+                .json(&json!({ "card_index": 0 }))
+                .send()
+                .await?;
+            assert_eq!(play_response.status(), StatusCode::OK);
+        }
+    }
+
+    let match_replay = client2
+        .get(format!("/table/{}/replay", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(match_replay["version"], 1);
+    assert_eq!(match_replay["games"].as_array().map(Vec::len), Some(1));
+
+    let game_replay = client1
+        .get(format!("/table/{}/replay/0", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(game_replay, match_replay["games"][0]);
+    assert_eq!(
+        game_replay["player_names"],
+        json!(["Replayer 1", "Replayer 2"])
+    );
+    assert_eq!(game_replay["moves"].as_array().map(Vec::len), Some(48));
+
+    // only players of the table may export replays
+    let other_client = server.client();
+    other_client
+        .post("/login")
+        .json(&json!({ "name": "Onlooker" }))
+        .send()
+        .await?;
+    let replay_response = other_client
+        .get(format!("/table/{}/replay", table_id))
+        .send()
+        .await?;
+    assert_eq!(replay_response.status(), StatusCode::NOT_FOUND);
+
+    let import_response = other_client
+        .post("/replay/match")
+        .json(&match_replay)
+        .send()
+        .await?;
+    assert_eq!(import_response.status(), StatusCode::OK);
+    let imported_match = import_response.json::<Value>().await?;
+    assert_eq!(
+        imported_match["game_results"].as_array().map(Vec::len),
+        Some(1)
+    );
+
+    let import_response = other_client
+        .post("/replay/game")
+        .json(&game_replay)
+        .send()
+        .await?;
+    assert_eq!(import_response.status(), StatusCode::OK);
+    let imported_game = import_response.json::<Value>().await?;
+    assert_eq!(imported_game["phase"], "Finished");
+
+    // edited replays are rejected
+    let mut edited_replay = game_replay.clone();
+    edited_replay["points"]["zing_points"][0] = json!(100);
+    let import_response = other_client
+        .post("/replay/game")
+        .json(&edited_replay)
+        .send()
+        .await?;
+    assert_eq!(import_response.status(), StatusCode::BAD_REQUEST);
+
+    let import_response = other_client
+        .post("/replay/game")
+        .body("not a replay")
+        .send()
+        .await?;
+    assert_eq!(import_response.status(), StatusCode::BAD_REQUEST);
+
+    // uploads require a login and are limited in size
+    let import_response = server
+        .client()
+        .post("/replay/match")
+        .json(&match_replay)
+        .send()
+        .await?;
+    assert_eq!(import_response.status(), StatusCode::UNAUTHORIZED);
+
+    let mut huge_replay = match_replay.clone();
+    huge_replay["games"] = json!(vec![game_replay.clone(); 100]);
+    let import_response = other_client
+        .post("/replay/match")
+        .json(&huge_replay)
+        .send()
+        .await?;
+    assert_eq!(import_response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_turn_time_limit() -> Result<()> {
+    let server = TestServer::start().await?;
+    let client = server.client();
+
+    client
+        .post("/login")
+        .json(&json!({ "name": "Slow Player" }))
+        .send()
+        .await?;
+
+    let create_response = client
+        .post("/table")
+        .json(&json!({ "turn_time_limit": 0 }))
+        .send()
+        .await?;
+    assert_eq!(create_response.status(), StatusCode::BAD_REQUEST);
+
+    let table_status = client
+        .post("/table")
+        .json(&json!({ "turn_time_limit": 1 }))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(table_status["turn_time_limit"], json!(1));
+    let table_id = table_status["id"]
+        .as_str()
+        .context("table status should have id")?;
+
+    client
+        .post(format!("/table/{}/bot", table_id))
+        .json(&json!({ "strategy": "random" }))
+        .send()
+        .await?;
+    let start_response = client
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?;
+    assert_eq!(start_response.status(), StatusCode::OK);
+
+    let hand_sizes = || async {
+        let game_status = client
+            .get(format!("/table/{}/game", table_id))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+        let hand_size = |player: usize| {
+            game_status["players"][player]["hand"]
+                .as_array()
+                .map(|hand| hand.len())
+                .context("game state should contain hands")
+        };
+        Ok::<_, anyhow::Error>((hand_size(0)?, hand_size(1)?))
+    };
+    let table_info = || async {
+        client
+            .get(format!("/table/{}", table_id))
+            .send()
+            .await?
+            .json::<Value>()
+            .await
+    };
+
+    // the bot plays first, then the server plays for us after our time is up
+    let mut own_cards = 4;
+    for _ in 0..50 {
+        own_cards = hand_sizes().await?.0;
+        if own_cards == 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(own_cards, 3);
+    assert_eq!(table_info().await?["idle_players"], json!([true, false]));
+
+    // playing ourselves again marks us as active
+    let mut bot_cards = 3;
+    for _ in 0..50 {
+        bot_cards = hand_sizes().await?.1;
+        if bot_cards == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(bot_cards, 2);
+    let play_response = client
+        .post(format!("/table/{}/game/play", table_id))
+        .json(&json!({ "card_index": 0 }))
+        .send()
+        .await?;
+    assert_eq!(play_response.status(), StatusCode::OK);
+    assert_eq!(table_info().await?["idle_players"], json!([false, false]));
+
+    Ok(())
+}
+
+/// Waits for the next turn timer that is started for a human player.
+async fn next_turn_timer(ws: &mut TestWebSocket) -> Result<TurnTimer> {
+    loop {
+        if let ClientNotification::TurnTimer(Some(timer)) =
+            ws.next_notification().await?.notification
+        {
+            return Ok(timer);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_turn_timer_after_bot_move() -> Result<()> {
+    let clock = Clock::manual();
+    let server = TestServer::start_with_clock(
+        Duration::from_millis(100),
+        MaintenanceConfig::default(),
+        clock.clone(),
+    )
+    .await?;
+    let client = server.login("Careful Player").await?;
+    let table_id = client
+        .post("/table")
+        .json(&json!({ "turn_time_limit": 3 }))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?["id"]
+        .as_str()
+        .context("table status should have id")?
+        .to_owned();
+    client.add_bot(&table_id, "random").await?;
+    let mut ws = client.connect_table_ws(&table_id, "").await?;
+    client
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let own_hand_size = || async {
+        let game_status = client
+            .get(format!("/table/{}/game", table_id))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+        game_status["players"][0]["hand"]
+            .as_array()
+            .map(|hand| hand.len())
+            .context("game state should contain hands")
+    };
+
+    // the bot plays first; we take our time, but play before our time is up
+    clock.advance(Duration::from_millis(100));
+    assert_eq!(next_turn_timer(&mut ws).await?.player, 0);
+    clock.advance(Duration::from_millis(1400));
+    let play_response = client
+        .post(format!("/table/{}/game/play", table_id))
+        .json(&json!({ "card_index": 0 }))
+        .send()
+        .await?;
+    assert_eq!(play_response.status(), StatusCode::OK);
+    assert_eq!(own_hand_size().await?, 3);
+
+    // after the bot's next move, our new time limit must not be cut short by
+    // the timer of our first turn (which expires after 3.1 seconds)
+    clock.advance(Duration::from_millis(100));
+    assert_eq!(next_turn_timer(&mut ws).await?.player, 0);
+    clock.advance(Duration::from_millis(2200));
+    assert_eq!(own_hand_size().await?, 3);
+
+    // ... but the new one still runs out (after 4.6 seconds)
+    clock.advance(Duration::from_millis(800));
+    while !matches!(
+        ws.next_notification().await?.notification,
+        ClientNotification::TurnTimer(None)
+    ) {}
+    assert_eq!(own_hand_size().await?, 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_spectators() -> Result<()> {
+    let server = TestServer::start().await?;
+    let client = server.client();
+    client
+        .post("/login")
+        .json(&json!({ "name": "Table Owner" }))
+        .send()
+        .await?;
+
+    let table_id = client.post("/table").send().await?.json::<Value>().await?["id"]
+        .as_str()
+        .context("table status should have id")?
+        .to_owned();
+    client
+        .post(format!("/table/{}/bot", table_id))
+        .json(&json!({ "strategy": "random" }))
+        .send()
+        .await?;
+    client
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?;
+
+    let spectator = server.client();
+    spectator
+        .post("/login")
+        .json(&json!({ "name": "Spectator" }))
+        .send()
+        .await?;
+
+    // spectators see all hands covered
+    let game_response = spectator
+        .get(format!("/table/{}/game", table_id))
+        .send()
+        .await?;
+    assert_eq!(game_response.status(), StatusCode::OK);
+    let game_status = game_response.json::<Value>().await?;
+    for player in 0..2 {
+        let hand = game_status["players"][player]["hand"]
+            .as_array()
+            .context("game state should contain hands")?;
+        assert_eq!(hand.len(), 4);
+        // covered cards are indistinguishable
+        assert_eq!(hand[0]["face_up"], json!(false));
+        assert!(hand.iter().all(|card| *card == hand[0]));
+    }
+
+    // ...but cannot play
+    let play_response = spectator
+        .post(format!("/table/{}/game/play", table_id))
+        .json(&json!({ "card_index": 0 }))
+        .send()
+        .await?;
+    assert_eq!(play_response.status(), StatusCode::NOT_FOUND);
+
+    // only the owner may disable spectating
+    let setting_response = spectator
+        .put(format!("/table/{}/spectators", table_id))
+        .json(&json!({ "allow": false }))
+        .send()
+        .await?;
+    assert_eq!(setting_response.status(), StatusCode::NOT_FOUND);
+    let setting_response = client
+        .put(format!("/table/{}/spectators", table_id))
+        .json(&json!({ "allow": false }))
+        .send()
+        .await?;
+    assert_eq!(setting_response.status(), StatusCode::OK);
+
+    let table_info = client
+        .get(format!("/table/{}", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(table_info["allow_spectators"], json!(false));
+    assert_eq!(table_info["spectator_names"], json!([]));
+
+    let game_response = spectator
+        .get(format!("/table/{}/game", table_id))
+        .send()
+        .await?;
+    assert_eq!(game_response.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+/// Logs in and creates a table with a computer player as opponent; returns
+/// the client and the table id.
+async fn create_bot_table(server: &TestServer, user_name: &str) -> Result<(TestClient, String)> {
+    let client = server.login(user_name).await?;
+    let table_id = client.create_table().await?;
+    client.add_bot(&table_id, "random").await?;
+    Ok((client, table_id))
+}
+
+#[tokio::test]
+async fn test_bots_follow_take_back_agreement() -> Result<()> {
+    let server = TestServer::start().await?;
+    let (client, table_id) = create_bot_table(&server, "Careful Player").await?;
+    let take_back_agreement = || async {
+        let table_info = client
+            .get(format!("/table/{}", table_id))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+        anyhow::Ok(table_info["allow_take_backs"].clone())
+    };
+    assert_eq!(take_back_agreement().await?, json!([false, false]));
+
+    for allow in [true, false] {
+        client
+            .put(format!("/table/{}/take_backs", table_id))
+            .json(&json!({ "allow": allow }))
+            .send()
+            .await?
+            .error_for_status()?;
+        assert_eq!(take_back_agreement().await?, json!([allow, allow]));
+    }
+
+    // computer players joining later adopt the owner's agreement
+    let table_id = client.create_table().await?;
+    client
+        .put(format!("/table/{}/take_backs", table_id))
+        .json(&json!({ "allow": true }))
+        .send()
+        .await?
+        .error_for_status()?;
+    let table_info = client.add_bot(&table_id, "random").await?;
+    assert_eq!(table_info["allow_take_backs"], json!([true, true]));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_websocket_commands() -> Result<()> {
+    let server = TestServer::start().await?;
+    let (client, table_id) = create_bot_table(&server, "WebSocket Player").await?;
+
+    let mut ws = client.connect_table_ws(&table_id, "").await?;
+
+    let notifications = ws
+        .send_command(json!({ "request_id": 1, "command": "Ping" }))
+        .await?;
+    assert!(matches!(
+        notifications[..],
+        [ClientNotification::CommandResult(1, Ok(()))]
+    ));
+
+    // the game status and dealing actions arrive before the acknowledgement
+    let notifications = ws
+        .send_command(json!({ "request_id": 2, "command": "StartGame" }))
+        .await?;
+    assert!(matches!(
+        notifications[0],
+        ClientNotification::GameStatus(_, 0, _)
+    ));
+    assert!(notifications
+        .iter()
+        .any(|n| matches!(n, ClientNotification::CardActions(_, _))));
+    assert!(matches!(
+        notifications.last(),
+        Some(ClientNotification::CommandResult(2, Ok(())))
+    ));
+
+    let notifications = ws
+        .send_command(json!({ "request_id": 3, "command": "StartGame" }))
+        .await?;
+    assert!(matches!(
+        notifications.last(),
+        Some(ClientNotification::CommandResult(3, Err(err)))
+            if err.kind == CommandErrorKind::Conflict && err.code == ErrorCode::GameAlreadyStarted
+    ));
+
+    let notifications = ws
+        .send_command(json!({ "request_id": 4, "command": "Dance" }))
+        .await?;
+    assert!(matches!(
+        notifications.last(),
+        Some(ClientNotification::CommandResult(4, Err(err)))
+            if err.kind == CommandErrorKind::BadRequest && err.code == ErrorCode::InvalidRequest
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_websocket_resume() -> Result<()> {
+    let server = TestServer::start().await?;
+    let (client, table_id) = create_bot_table(&server, "Reconnecting Player").await?;
+
+    // clients speaking another protocol version are rejected
+    assert!(client
+        .connect_table_ws(&table_id, "?version=999")
+        .await
+        .is_err());
+
+    let mut ws = client
+        .connect_table_ws(&table_id, &format!("?version={}", PROTOCOL_VERSION))
+        .await?;
+    ws.send(json!({ "request_id": 1, "command": "StartGame" }))
+        .await?;
+    let mut last_seq = 0;
+    loop {
+        let message = ws.next_notification().await?;
+        assert!(message.seq > last_seq);
+        last_seq = message.seq;
+        if matches!(
+            message.notification,
+            ClientNotification::CommandResult(1, _)
+        ) {
+            break;
+        }
+    }
+    ws.close().await?;
+
+    // wait for the computer player's move while we are disconnected
+    for _ in 0..50 {
+        let game_status = client
+            .get(format!("/table/{}/game", table_id))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+        if game_status["players"][1]["hand"]
+            .as_array()
+            .is_some_and(|hand| hand.len() == 3)
+        {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // resuming only sends the missing card actions
+    let mut ws = client
+        .connect_table_ws(
+            &table_id,
+            &format!("?version={}&resume_from={}", PROTOCOL_VERSION, last_seq),
+        )
+        .await?;
+    let message = ws.next_notification().await?;
+    assert!(message.seq > last_seq);
+    let ClientNotification::CardActions(actions, Some(0)) = message.notification else {
+        panic!("expected card actions, got {:?}", message.notification);
+    };
+    assert!(!actions.is_empty());
+
+    // an unknown sequence number results in the full game status
+    let mut ws = client.connect_table_ws(&table_id, "?resume_from=1").await?;
+    assert!(matches!(
+        ws.next_notification().await?.notification,
+        ClientNotification::GameStatus(_, 0, Some(0))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_table_chat() -> Result<()> {
+    let server = TestServer::start().await?;
+    let (client, table_id) = create_bot_table(&server, "Chatty Player").await?;
+
+    let mut ws = client.connect_table_ws(&table_id, "").await?;
+
+    // sends a chat command and collects all notifications up to its result
+    let mut send_chat = async |request_id: u64, content: Value| {
+        ws.send_command(json!({ "request_id": request_id, "command": { "Chat": content } }))
+            .await
+    };
+
+    let notifications = send_chat(1, json!({ "Text": "Hello table!" })).await?;
+    match &notifications[..] {
+        [ClientNotification::Chat(message), ClientNotification::CommandResult(1, Ok(()))] => {
+            assert_eq!(message.user_name, "Chatty Player");
+            assert_eq!(message.player, Some(0));
+            assert_eq!(message.content, ChatContent::Text("Hello table!".into()));
+        }
+        _ => panic!("unexpected notifications {notifications:?}"),
+    }
+
+    let notifications = send_chat(2, json!({ "Emote": "WellPlayed" })).await?;
+    assert!(matches!(
+        &notifications[..],
+        [ClientNotification::Chat(message), ClientNotification::CommandResult(2, Ok(()))]
+            if message.content == ChatContent::Emote(Emote::WellPlayed)
+    ));
+
+    for (request_id, text) in [(3, " ".to_owned()), (4, "x".repeat(1000))] {
+        let notifications = send_chat(request_id, json!({ "Text": text })).await?;
+        assert!(matches!(
+            &notifications[..],
+            [ClientNotification::CommandResult(_, Err(err))] if err.kind == CommandErrorKind::BadRequest
+        ));
+    }
+
+    // sending too many messages in a short time is not allowed
+    let mut rate_limited = false;
+    for request_id in 5..15 {
+        let notifications = send_chat(request_id, json!({ "Emote": "Zing" })).await?;
+        if matches!(
+            notifications.last(),
+            Some(ClientNotification::CommandResult(_, Err(err))) if err.kind == CommandErrorKind::RateLimited
+        ) {
+            rate_limited = true;
+            break;
+        }
+    }
+    assert!(rate_limited);
+
+    // reconnecting clients receive the recent messages
+    let mut ws = client.connect_table_ws(&table_id, "").await?;
+    match ws.next_notification().await?.notification {
+        ClientNotification::ChatHistory(messages) => {
+            assert!(messages.len() >= 3);
+            assert_eq!(
+                messages[0].content,
+                ChatContent::Text("Hello table!".into())
+            );
+            assert_eq!(messages[1].content, ChatContent::Emote(Emote::WellPlayed));
+        }
+        notification => panic!("expected chat history, got {notification:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_user_accounts() -> Result<()> {
+    let server = TestServer::start().await?;
+    let username = "Registered Player";
+
+    let client = server.client();
+    let login_response = client
+        .post("/login")
+        .json(&json!({ "name": "Soon Registered" }))
+        .send()
+        .await?;
+    assert_eq!(login_response.status(), StatusCode::OK);
+
+    let table_id = client.post("/table").send().await?.json::<Value>().await?["id"]
+        .as_str()
+        .context("table id should be a string")?
+        .to_owned();
+
+    let register_response = client
+        .post("/account")
+        .json(&json!({ "name": username, "password": "short" }))
+        .send()
+        .await?;
+    assert_eq!(register_response.status(), StatusCode::BAD_REQUEST);
+
+    let register_response = client
+        .post("/account")
+        .json(&json!({ "name": username, "password": "correct horse" }))
+        .send()
+        .await?;
+    assert_eq!(register_response.status(), StatusCode::OK);
+    let table_info = client
+        .get(format!("/table/{}", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(table_info["user_names"], json!([username]));
+
+    let register_response = client
+        .post("/account")
+        .json(&json!({ "name": format!("{username} again"), "password": "correct horse" }))
+        .send()
+        .await?;
+    assert_eq!(register_response.status(), StatusCode::CONFLICT);
+
+    let status_response = client.get("/login").send().await?;
+    assert_eq!(status_response.text().await?, username);
+
+    let status_response = client.delete("/login").send().await?;
+    assert_eq!(status_response.status(), StatusCode::OK);
+
+    // another user cannot register the same name
+    let other_client = server.client();
+    other_client
+        .post("/login")
+        .json(&json!({ "name": "Name Thief" }))
+        .send()
+        .await?;
+    let register_response = other_client
+        .post("/account")
+        .json(&json!({ "name": username, "password": "another password" }))
+        .send()
+        .await?;
+    assert_eq!(register_response.status(), StatusCode::CONFLICT);
+
+    // logging in again requires the right password
+    let client = server.client();
+    let login_response = client
+        .post("/login")
+        .json(&json!({ "name": username, "password": "wrong horse" }))
+        .send()
+        .await?;
+    assert_eq!(login_response.status(), StatusCode::UNAUTHORIZED);
+
+    let login_response = client
+        .post("/login")
+        .json(&json!({ "name": username, "password": "correct horse" }))
+        .send()
+        .await?;
+    assert_eq!(login_response.status(), StatusCode::OK);
+    assert_eq!(login_response.text().await?, username);
+
+    // ... and leads to the same user as before
+    let tables = client.get("/table").send().await?.json::<Value>().await?;
+    assert!(tables
+        .as_array()
+        .context("table list should be an array")?
+        .iter()
+        .any(|table| table["id"] == table_id.as_str()));
+
+    // logging in on a second device keeps the first session alive
+    let mut ws = client.connect_table_ws(&table_id, "").await?;
+    let second_client = server.client();
+    let login_response = second_client
+        .post("/login")
+        .json(&json!({ "name": username, "password": "correct horse" }))
+        .send()
+        .await?;
+    assert_eq!(login_response.status(), StatusCode::OK);
+    for client in [&client, &second_client] {
+        assert_eq!(client.get("/login").send().await?.status(), StatusCode::OK);
+    }
+    let notifications = ws
+        .send_command(json!({ "request_id": 1, "command": "Ping" }))
+        .await?;
+    assert!(matches!(
+        notifications.last(),
+        Some(ClientNotification::CommandResult(1, Ok(())))
+    ));
+    // ... and both own the table
+    let setting_response = second_client
+        .put(format!("/table/{}/spectators", table_id))
+        .json(&json!({ "allow": false }))
+        .send()
+        .await?;
+    assert_eq!(setting_response.status(), StatusCode::OK);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_session_end() -> Result<()> {
+    let server = TestServer::start().await?;
+    let client = reqwest::Client::new();
+    let login_response = client
+        .post(server.url("/login"))
+        .json(&json!({ "name": "Short Session" }))
+        .send()
+        .await?;
+    assert_eq!(login_response.status(), StatusCode::OK);
+    let login_cookie = login_response
+        .headers()
+        .get(reqwest::header::SET_COOKIE)
+        .context("login should set a cookie")?
+        .to_str()?
+        .split(';')
+        .next()
+        .context("cookie should have a value")?
+        .to_owned();
+
+    let status_response = client
+        .get(server.url("/login"))
+        .header(reqwest::header::COOKIE, &login_cookie)
+        .send()
+        .await?;
+    assert_eq!(status_response.status(), StatusCode::OK);
+
+    let logout_response = client
+        .delete(server.url("/login"))
+        .header(reqwest::header::COOKIE, &login_cookie)
+        .send()
+        .await?;
+    assert_eq!(logout_response.status(), StatusCode::OK);
+
+    // the login token must not be usable after logging out
+    let status_response = client
+        .get(server.url("/login"))
+        .header(reqwest::header::COOKIE, &login_cookie)
+        .send()
+        .await?;
+    assert_eq!(status_response.status(), StatusCode::UNAUTHORIZED);
+
+    let metrics = client
+        .get(server.url("/metrics"))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    for key in [
+        "maintenance_runs",
+        "sessions_expired",
+        "tables_unloaded",
+        "tables_deleted",
+        "users_deleted",
+        "loaded_tables",
+        "connections",
+    ] {
+        assert!(metrics[key].is_u64(), "missing metric {key}");
+    }
+
+    // sessions without requests expire after the configured time
+    let server = TestServer::start_with(
+        Duration::from_millis(100),
+        MaintenanceConfig {
+            interval: Duration::from_millis(100),
+            session_timeout: Duration::from_secs(1),
+            ..Default::default()
+        },
+    )
+    .await?;
+    let client = server.login("Idle Player").await?;
+    assert_eq!(client.get("/login").send().await?.status(), StatusCode::OK);
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(
+        client.get("/login").send().await?.status(),
+        StatusCode::UNAUTHORIZED
+    );
+    let metrics = client.get("/metrics").send().await?.json::<Value>().await?;
+    assert_eq!(metrics["sessions_expired"], 1);
+
+    Ok(())
+}
+
+/// Plays a whole game of two players (the second of which starts), always
+/// playing the first card.
+async fn play_two_player_game(clients: &[TestClient], table_id: &str) -> Result<()> {
+    for _ in 0..24 {
+        for client in [&clients[1], &clients[0]] {
+            let play_response = client
+                .post(format!("/table/{}/game/play", table_id))
+                .json(&json!({ "card_index": 0 }))
+                .send()
+                .await?;
+            assert_eq!(play_response.status(), StatusCode::OK);
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stats_and_leaderboard() -> Result<()> {
+    let server = TestServer::start().await?;
+    let usernames = ["Stats One", "Stats Two"];
+
+    let clients = server.login_clients(&["Guest", "Guest"]).await?;
+    for (client, username) in clients.iter().zip(&usernames) {
+        let register_response = client
+            .post("/account")
+            .json(&json!({ "name": username, "password": "stats password" }))
+            .send()
+            .await?;
+        assert_eq!(register_response.status(), StatusCode::OK);
+    }
+
+    let table_id = clients[0]
+        .post("/table")
+        .send()
+        .await?
+        .json::<Value>()
+        .await?["id"]
+        .as_str()
+        .context("table status should have id")?
+        .to_owned();
+    clients[1].join_table(&table_id).await?;
+    clients[0]
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?;
+
+    play_two_player_game(&clients, &table_id).await?;
+
+    let own_stats = clients[0]
+        .get("/stats")
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    let other_stats = clients[0]
+        .get(format!("/stats/{}", usernames[1]))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    for (stats, opponent) in [(&own_stats, &usernames[1]), (&other_stats, &usernames[0])] {
+        assert_eq!(stats["games_played"], 1);
+        assert_eq!(stats["rated_games"], 1);
+        assert_eq!(stats["matches_played"], 0);
+        assert_eq!(stats["head_to_head"][0]["opponent"], *opponent);
+        assert_eq!(stats["head_to_head"][0]["games_played"], 1);
+        assert!(stats["average_card_points"].as_f64().is_some());
+        assert!(stats["zings"].as_u64() >= stats["jack_zings"].as_u64());
+    }
+    let won = |stats: &Value| stats["games_won"].as_u64().unwrap_or_default();
+    let rating = |stats: &Value| stats["rating"].as_f64().unwrap_or_default();
+    assert!(won(&own_stats) + won(&other_stats) <= 1);
+    // Elo ratings are zero-sum
+    assert!((rating(&own_stats) + rating(&other_stats) - 3000.).abs() < 1e-6);
+    if won(&own_stats) == 1 {
+        assert!(rating(&own_stats) > rating(&other_stats));
+    }
+
+    // the table info breaks down the Zing points into Zings and Jack Zings
+    let table_info = clients[0]
+        .get(format!("/table/{}", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    let summary = &table_info["game_summaries"][0];
+    for team in 0..2 {
+        let zings = summary["zings"][team].as_u64().context("zings per team")?;
+        let jack_zings = summary["jack_zings"][team]
+            .as_u64()
+            .context("Jack Zings per team")?;
+        assert_eq!(
+            table_info["game_results"][0]["zing_points"][team],
+            10 * zings + 20 * jack_zings
+        );
+        assert!(summary["tricks"][team].as_u64().is_some());
+    }
+    assert!(own_stats["tricks"].as_u64().is_some());
+
+    let missing_response = clients[0].get("/stats/no%20such%20user").send().await?;
+    assert_eq!(missing_response.status(), StatusCode::NOT_FOUND);
+
+    // anonymous players are rated, but not listed on the leaderboard
+    let anonymous_clients = server.login_clients(&["Anonymous", "Anonymous"]).await?;
+    let anonymous_table_id = anonymous_clients[0].create_table().await?;
+    anonymous_clients[1].join_table(&anonymous_table_id).await?;
+    anonymous_clients[0]
+        .post(format!("/table/{}/game", anonymous_table_id))
+        .send()
+        .await?
+        .error_for_status()?;
+    play_two_player_game(&anonymous_clients, &anonymous_table_id).await?;
+
+    let leaderboard = server
+        .client()
+        .get("/leaderboard")
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    let leaderboard = leaderboard
+        .as_array()
+        .context("leaderboard should be an array")?;
+    let mut names: Vec<_> = leaderboard.iter().map(|entry| &entry["name"]).collect();
+    names.sort_by_key(|name| name.as_str());
+    assert_eq!(names, usernames);
+    for (index, entry) in leaderboard.iter().enumerate() {
+        assert_eq!(entry["rank"], index + 1);
+        if index > 0 {
+            assert!(entry["rating"].as_f64() <= leaderboard[index - 1]["rating"].as_f64());
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bot_games_are_not_rated() -> Result<()> {
+    let server = TestServer::start_with(Duration::ZERO, MaintenanceConfig::default()).await?;
+    let (client, table_id) = create_bot_table(&server, "Bot Beater").await?;
+    client
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let mut cards_played = 0;
+    for _ in 0..1000 {
+        let play_response = client
+            .post(format!("/table/{}/game/play", table_id))
+            .json(&json!({ "card_index": 0 }))
+            .send()
+            .await?;
+        if play_response.status() == StatusCode::OK {
+            cards_played += 1;
+            if cards_played == 24 {
+                break;
+            }
+        } else {
+            // the bot is still thinking
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+    assert_eq!(cards_played, 24);
+
+    let stats = client.get("/stats").send().await?.json::<Value>().await?;
+    assert_eq!(stats["games_played"], 1);
+    assert_eq!(stats["rated_games"], 0);
+    assert_eq!(stats["rating"], 1500.0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_next_game_starts_automatically() -> Result<()> {
+    let server = TestServer::start().await?;
+    let clients = server
+        .login_clients(&["Match Player 1", "Match Player 2"])
+        .await?;
+    let table_id = clients[0].create_table().await?;
+    clients[1].join_table(&table_id).await?;
+    let mut ws = clients[0].connect_table_ws(&table_id, "").await?;
+    clients[0]
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .error_for_status()?;
+    play_two_player_game(&clients, &table_id).await?;
+
+    // the finished game remains visible for a moment
+    let game_status = clients[0]
+        .get(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(game_status["phase"], "Finished");
+
+    let mut games_started = 0;
+    let mut match_status_seen = false;
+    while games_started < 2 {
+        match ws.next_notification().await?.notification {
+            ClientNotification::GameStatus(..) => {
+                // the result of the first game comes before the second game
+                assert_eq!(match_status_seen, games_started == 1);
+                games_started += 1;
+            }
+            ClientNotification::MatchStatus(points, winner) => {
+                assert!(points.0 + points.1 > 0);
+                assert_eq!(winner, None);
+                match_status_seen = true;
+            }
+            _ => {}
+        }
+    }
+
+    let game_status = clients[0]
+        .get(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(game_status["phase"], "Prepared");
+    assert_eq!(
+        game_status["players"][0]["hand"].as_array().map(Vec::len),
+        Some(4)
+    );
+    let table_info = clients[0]
+        .get(format!("/table/{}", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(table_info["game_results"].as_array().map(Vec::len), Some(1));
+
+    // the second player deals, so the first one leads
+    let play_response = clients[0]
+        .post(format!("/table/{}/game/play", table_id))
+        .json(&json!({ "card_index": 0 }))
+        .send()
+        .await?;
+    assert_eq!(play_response.status(), StatusCode::OK);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_clearing_finished_game_stops_match() -> Result<()> {
+    let server = TestServer::start().await?;
+    let clients = server
+        .login_clients(&["Match Player 1", "Match Player 2"])
+        .await?;
+    let table_id = clients[0].create_table().await?;
+    clients[1].join_table(&table_id).await?;
+    clients[0]
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .error_for_status()?;
+    play_two_player_game(&clients, &table_id).await?;
+
+    let finish_response = clients[1]
+        .delete(format!("/table/{}/game", table_id))
+        .send()
+        .await?;
+    assert_eq!(finish_response.status(), StatusCode::OK);
+
+    // no game follows automatically
+    tokio::time::sleep(TEST_NEXT_GAME_DELAY * 2).await;
+    let game_response = clients[0]
+        .get(format!("/table/{}/game", table_id))
+        .send()
+        .await?;
+    assert_eq!(game_response.status(), StatusCode::NOT_FOUND);
+
+    // but the players may continue the match themselves
+    clients[0]
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .error_for_status()?;
+    let table_info = clients[0]
+        .get(format!("/table/{}", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(table_info["game_results"].as_array().map(Vec::len), Some(1));
+    assert_eq!(table_info["game"], "Prepared");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stats_survive_maintenance() -> Result<()> {
+    let server = TestServer::start_with(
+        Duration::from_millis(100),
+        MaintenanceConfig {
+            interval: Duration::from_millis(100),
+            session_timeout: Duration::from_secs(1),
+            ..Default::default()
+        },
+    )
+    .await?;
+    let usernames = ["Lasting One", "Lasting Two"];
+    let clients = server.login_clients(&["Guest", "Guest"]).await?;
+    for (client, username) in clients.iter().zip(&usernames) {
+        client
+            .post("/account")
+            .json(&json!({ "name": username, "password": "stats password" }))
+            .send()
+            .await?
+            .error_for_status()?;
+    }
+    let table_id = clients[0].create_table().await?;
+    clients[1].join_table(&table_id).await?;
+    clients[0]
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .error_for_status()?;
+    play_two_player_game(&clients, &table_id).await?;
+
+    // both sessions expire, leaving the table without logged-in players
+    let observer = server.client();
+    let mut metrics = Value::Null;
+    for _ in 0..50 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        metrics = observer
+            .get("/metrics")
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+        if metrics["sessions_expired"] == 2 {
+            break;
+        }
+    }
+    assert_eq!(metrics["sessions_expired"], 2);
+    // give the maintenance task another run for cleaning up
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    for (username, opponent) in [(usernames[0], usernames[1]), (usernames[1], usernames[0])] {
+        let stats = observer
+            .get(format!("/stats/{username}"))
+            .send()
+            .await?
+            .json::<Value>()
+            .await?;
+        assert_eq!(stats["games_played"], 1);
+        assert_eq!(stats["rated_games"], 1);
+        assert_eq!(stats["head_to_head"][0]["opponent"], opponent);
+        assert_eq!(stats["head_to_head"][0]["games_played"], 1);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_error_responses() -> Result<()> {
+    let server = TestServer::start().await?;
+    let clients = server
+        .login_clients(&["Player 1", "Player 2", "Player 3", "Player 4", "Player 5"])
+        .await?;
+
+    let missing_response = clients[0].post("/table/no_such_table").send().await?;
+    assert_eq!(missing_response.status(), StatusCode::NOT_FOUND);
+    let header_id = missing_response
+        .headers()
+        .get("x-request-id")
+        .context("responses should have a request id")?
+        .to_str()?
+        .to_owned();
+    let error = missing_response.json::<ErrorBody>().await?;
+    assert_eq!(error.code, ErrorCode::TableNotFound);
+    assert_eq!(error.message, "table not found by token");
+    assert_eq!(error.request_id, Some(header_id));
+
+    // request ids sent by clients (or proxies) are taken over
+    let error = server
+        .client()
+        .get("/login")
+        .header("x-request-id", "my-request-42")
+        .send()
+        .await?
+        .json::<ErrorBody>()
+        .await?;
+    assert_eq!(error.code, ErrorCode::NotLoggedIn);
+    assert_eq!(error.request_id.as_deref(), Some("my-request-42"));
+
+    // errors not produced by the game logic get a JSON body, too
+    let invalid_response = clients[0]
+        .post("/login")
+        .header("content-type", "application/json")
+        .body("{")
+        .send()
+        .await?;
+    assert_eq!(invalid_response.status(), StatusCode::BAD_REQUEST);
+    let error = invalid_response.json::<ErrorBody>().await?;
+    assert_eq!(error.code, ErrorCode::InvalidRequest);
+    assert!(error.request_id.is_some());
+
+    let table_id = clients[0].create_table().await?;
+    for client in &clients[1..4] {
+        client.join_table(&table_id).await?;
+    }
+    let full_response = clients[4]
+        .post(format!("/table/{}", table_id))
+        .send()
+        .await?;
+    assert_eq!(full_response.status(), StatusCode::CONFLICT);
+    assert_eq!(
+        full_response.json::<ErrorBody>().await?.code,
+        ErrorCode::TableFull
+    );
+
+    clients[0]
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .error_for_status()?;
+    let error = clients[0]
+        .post(format!("/table/{}/game/play", table_id))
+        .json(&json!({ "card_index": 0 }))
+        .send()
+        .await?
+        .json::<ErrorBody>()
+        .await?;
+    assert_eq!(error.code, ErrorCode::NotYourTurn);
+    assert_eq!(error.message, "not player's turn");
+
+    let error = clients[1]
+        .post(format!("/table/{}/game/play", table_id))
+        .json(&json!({ "card_index": 4 }))
+        .send()
+        .await?
+        .json::<ErrorBody>()
+        .await?;
+    assert_eq!(error.code, ErrorCode::InvalidCardIndex);
+
+    let error = clients[2]
+        .post(format!("/table/{}", table_id))
+        .send()
+        .await?
+        .json::<ErrorBody>()
+        .await?;
+    assert_eq!(error.code, ErrorCode::GameAlreadyStarted);
+
+    Ok(())
+}
+
+/// Action playing the given card of the player's hand onto the table.
+fn play_action(player: usize, card_index: usize, game_status: &Value) -> Value {
+    let table_size = game_status["stacks"][1]["cards"]
+        .as_array()
+        .map_or(0, Vec::len);
+    json!({
+        "source_location": "PlayerHand",
+        "source_index": player,
+        "source_card_indices": [card_index],
+        "dest_location": "Stack",
+        "dest_index": 1,
+        "dest_card_indices": [table_size],
+        "rotation": "FaceUp",
+        "resulting_card_states": [],
+    })
+}
+
+#[tokio::test]
+async fn test_card_actions() -> Result<()> {
+    let server = TestServer::start().await?;
+    let clients = server.login_clients(&["Player 1", "Player 2"]).await?;
+    let table_id = clients[0].create_table().await?;
+    clients[1].join_table(&table_id).await?;
+    clients[0]
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let game_path = format!("/table/{}/game", table_id);
+    let action_path = format!("/table/{}/game/action", table_id);
+    let game_status = clients[1]
+        .get(&game_path)
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    let perform =
+        |client: &TestClient, action: Value| client.post(&action_path).json(&action).send();
+
+    // players may only play their own cards
+    let response = perform(&clients[0], play_action(1, 0, &game_status)).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json::<ErrorBody>().await?.code,
+        ErrorCode::IllegalAction
+    );
+
+    let mut to_score_stack = play_action(1, 0, &game_status);
+    to_score_stack["dest_index"] = json!(2);
+    let mut forged_card = play_action(1, 0, &game_status);
+    forged_card["resulting_card_states"] = json!([game_status["stacks"][1]["cards"][0]]);
+    let mut two_cards = play_action(1, 0, &game_status);
+    two_cards["source_card_indices"] = json!([0, 1]);
+    for action in [to_score_stack, forged_card, two_cards] {
+        let error = perform(&clients[1], action)
+            .await?
+            .json::<ErrorBody>()
+            .await?;
+        assert_eq!(error.code, ErrorCode::IllegalAction);
+    }
+
+    let error = perform(&clients[1], play_action(1, 7, &game_status))
+        .await?
+        .json::<ErrorBody>()
+        .await?;
+    assert_eq!(error.code, ErrorCode::InvalidCardIndex);
+
+    let malformed_response = perform(&clients[1], json!({ "source_index": "first" })).await?;
+    assert!(malformed_response.status().is_client_error());
+
+    // nothing has been played so far
+    let unchanged_status = clients[1]
+        .get(&game_path)
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(unchanged_status, game_status);
+
+    let response = perform(&clients[1], play_action(1, 0, &game_status)).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let error = perform(&clients[1], play_action(1, 0, &game_status))
+        .await?
+        .json::<ErrorBody>()
+        .await?;
+    assert_eq!(error.code, ErrorCode::NotYourTurn);
+
+    // the same works via WebSocket commands
+    let game_status = clients[0]
+        .get(&game_path)
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    let mut ws = clients[0].connect_table_ws(&table_id, "").await?;
+    let notifications = ws
+        .send_command(json!({
+            "request_id": 1,
+            "command": { "PerformAction": { "action": play_action(0, 0, &game_status) } },
+        }))
+        .await?;
+    assert!(matches!(
+        notifications.last(),
+        Some(ClientNotification::CommandResult(1, Ok(())))
+    ));
+    let game_status = clients[0]
+        .get(&game_path)
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(
+        game_status["players"][0]["hand"].as_array().map(Vec::len),
+        Some(3)
+    );
+
+    Ok(())
+}
+
+/// Waits for the next move hints sent via the WebSocket.
+async fn next_move_hints(ws: &mut TestWebSocket) -> Result<Vec<MoveHint>> {
+    loop {
+        if let ClientNotification::MoveHints(hints) = ws.next_notification().await?.notification {
+            return Ok(hints);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_move_hints() -> Result<()> {
+    let server = TestServer::start().await?;
+    let clients = server.login_clients(&["Player 1", "Player 2"]).await?;
+    let table_id = clients[0].create_table().await?;
+    clients[1].join_table(&table_id).await?;
+    clients[0]
+        .post(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let hints_path = format!("/table/{}/game/hints", table_id);
+    let mut all_hints = Vec::new();
+    for client in &clients {
+        let response = client.get(&hints_path).send().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        all_hints.push(response.json::<Vec<MoveHint>>().await?);
+    }
+    // only the player at turn gets hints, one for each card
+    let player = all_hints
+        .iter()
+        .position(|hints| !hints.is_empty())
+        .context("player at turn should get hints")?;
+    assert!(all_hints[1 - player].is_empty());
+    let game_status = clients[player]
+        .get(format!("/table/{}/game", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    let hand = game_status["players"][player]["hand"]
+        .as_array()
+        .context("game state should contain hands")?;
+    assert_eq!(all_hints[player].len(), hand.len());
+    for (card_index, hint) in all_hints[player].iter().enumerate() {
+        assert_eq!(hint.card_index, card_index);
+        assert_eq!(serde_json::to_value(hint.card)?, hand[card_index]["card"]);
+    }
+
+    let outsider = server.login("Outsider").await?;
+    let error = outsider
+        .get(&hints_path)
+        .send()
+        .await?
+        .json::<ErrorBody>()
+        .await?;
+    assert_eq!(error.code, ErrorCode::NotAtTable);
+
+    // the player at turn also gets the hints via WebSocket
+    let mut ws = clients[player].connect_table_ws(&table_id, "").await?;
+    assert_eq!(next_move_hints(&mut ws).await?, all_hints[player]);
+
+    // only the owner may switch off hints, which clears them
+    let setting_path = format!("/table/{}/hints", table_id);
+    let error = clients[1]
+        .put(&setting_path)
+        .json(&json!({ "allow": false }))
+        .send()
+        .await?
+        .json::<ErrorBody>()
+        .await?;
+    assert_eq!(error.code, ErrorCode::NotTableOwner);
+    clients[0]
+        .put(&setting_path)
+        .json(&json!({ "allow": false }))
+        .send()
+        .await?
+        .error_for_status()?;
+    assert!(next_move_hints(&mut ws).await?.is_empty());
+
+    let table_info = clients[0]
+        .get(format!("/table/{}", table_id))
+        .send()
+        .await?
+        .json::<Value>()
+        .await?;
+    assert_eq!(table_info["allow_move_hints"], json!(false));
+
+    let response = clients[player].get(&hints_path).send().await?;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(
+        response.json::<ErrorBody>().await?.code,
+        ErrorCode::MoveHintsDisabled
+    );
+
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! Table chat overlay: shows the recent chat messages at the table, lets the
+//! user type messages (Enter to start / send, Escape to cancel) and offers
+//! buttons for sending emotes.  Commands rejected by the server are briefly
+//! explained above the chat messages.
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use zing_game::chat::{ChatContent, Emote, MAX_CHAT_MESSAGE_CHARS};
+
+use crate::constants::*;
+use crate::game_logic::{GameLogic, StateChanges};
+
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChatInput>()
+            .add_systems(Startup, spawn_chat_panel)
+            .add_systems(
+                Update,
+                (
+                    handle_chat_input,
+                    handle_emote_buttons,
+                    update_chat_log,
+                    update_command_error,
+                    update_chat_input.after(handle_chat_input),
+                ),
+            );
+    }
+}
+
+/// Message currently being typed (if any)
+#[derive(Resource, Default)]
+pub struct ChatInput {
+    typing: bool,
+    text: String,
+}
+
+/// Run condition for keyboard shortcuts, which must be ignored while the user
+/// is typing a chat message.
+pub fn not_typing_chat(chat_input: Option<Res<ChatInput>>) -> bool {
+    chat_input.is_none_or(|chat_input| !chat_input.typing)
+}
+
+#[derive(Component)]
+struct ChatLogText {
+    /// revision of the displayed chat messages (see [StateChanges::chat_revision])
+    revision: u64,
+}
+
+#[derive(Component)]
+struct ChatInputText;
+
+#[derive(Component)]
+struct CommandErrorText {
+    /// see [StateChanges::command_error_revision]
+    revision: u64,
+    /// elapsed time at which the message is hidden again
+    hide_at: f64,
+}
+
+#[derive(Component)]
+struct EmoteButton(Emote);
+
+fn spawn_chat_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font =
+        TextFont::from_font(asset_server.load(PLAYER_NAME_FONT)).with_font_size(CHAT_FONT_SIZE);
+    let shadow = TextShadow {
+        color: PLAYER_NAME_SHADOW_COLOR,
+        offset: Vec2::new(1., 1.),
+    };
+
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(PLAYER_NAME_MARGIN),
+            bottom: Val::Px(PLAYER_NAME_MARGIN),
+            width: Val::Px(CHAT_PANEL_WIDTH),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(PLAYER_NAME_MARGIN * 0.5),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                font.clone(),
+                TextColor(COMMAND_ERROR_COLOR),
+                shadow,
+                CommandErrorText {
+                    revision: 0,
+                    hide_at: 0.,
+                },
+            ));
+            parent.spawn((
+                Text::new(""),
+                font.clone(),
+                TextColor(PLAYER_NAME_COLOR),
+                shadow,
+                ChatLogText { revision: 0 },
+            ));
+            parent.spawn((
+                Text::new(""),
+                font.clone(),
+                TextColor(CHAT_INPUT_COLOR),
+                shadow,
+                ChatInputText,
+            ));
+            parent
+                .spawn(Node {
+                    flex_wrap: FlexWrap::Wrap,
+                    column_gap: Val::Px(PLAYER_NAME_MARGIN * 0.5),
+                    row_gap: Val::Px(PLAYER_NAME_MARGIN * 0.5),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for emote in Emote::ALL {
+                        parent
+                            .spawn((
+                                Button,
+                                Node {
+                                    padding: UiRect::axes(
+                                        Val::Px(PLAYER_NAME_MARGIN * 0.5),
+                                        Val::Px(PLAYER_NAME_MARGIN * 0.25),
+                                    ),
+                                    ..default()
+                                },
+                                BackgroundColor(CHAT_BUTTON_COLOR),
+                                BorderRadius::all(Val::Px(PLAYER_NAME_ROUNDING)),
+                                EmoteButton(emote),
+                            ))
+                            .with_child((
+                                Text::new(emote.text()),
+                                font.clone(),
+                                TextColor(PLAYER_NAME_COLOR),
+                            ));
+                    }
+                });
+        });
+}
+
+fn handle_chat_input(
+    mut chat_input: ResMut<ChatInput>,
+    mut game_logic: ResMut<GameLogic>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+) {
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        if !chat_input.typing {
+            if event.logical_key == Key::Enter {
+                chat_input.typing = true;
+            }
+            continue;
+        }
+        match &event.logical_key {
+            Key::Enter => {
+                let text = std::mem::take(&mut chat_input.text);
+                if !text.trim().is_empty() {
+                    game_logic.chat(ChatContent::Text(text));
+                }
+                chat_input.typing = false;
+            }
+            Key::Escape => {
+                chat_input.text.clear();
+                chat_input.typing = false;
+            }
+            Key::Backspace => {
+                chat_input.text.pop();
+            }
+            _ => {
+                if let Some(text) = &event.text {
+                    let typed: String = text.chars().filter(|c| !c.is_control()).collect();
+                    if chat_input.text.chars().count() + typed.chars().count()
+                        <= MAX_CHAT_MESSAGE_CHARS
+                    {
+                        chat_input.text.push_str(&typed);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn handle_emote_buttons(
+    mut game_logic: ResMut<GameLogic>,
+    mut query_buttons: Query<
+        (&Interaction, &EmoteButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+) {
+    for (interaction, button, mut background) in &mut query_buttons {
+        match interaction {
+            Interaction::Pressed => game_logic.chat(ChatContent::Emote(button.0)),
+            Interaction::Hovered => background.0 = CHAT_BUTTON_HOVER_COLOR,
+            Interaction::None => background.0 = CHAT_BUTTON_COLOR,
+        }
+    }
+}
+
+fn update_chat_log(
+    state_changes: Res<StateChanges>,
+    mut query_text: Query<(&mut Text, &mut ChatLogText)>,
+) {
+    for (mut text, mut chat_log) in &mut query_text {
+        if chat_log.revision == state_changes.chat_revision() {
+            continue;
+        }
+        chat_log.revision = state_changes.chat_revision();
+        text.0 = state_changes
+            .chat_messages()
+            .map(|message| {
+                format!(
+                    "{}{}: {}",
+                    message.user_name,
+                    if message.player.is_none() {
+                        " (spectator)"
+                    } else {
+                        ""
+                    },
+                    message.content.text()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+}
+
+fn update_command_error(
+    state_changes: Res<StateChanges>,
+    time: Res<Time>,
+    mut query_text: Query<(&mut Text, &mut CommandErrorText)>,
+) {
+    let now = time.elapsed_secs_f64();
+    for (mut text, mut error_text) in &mut query_text {
+        if error_text.revision != state_changes.command_error_revision() {
+            error_text.revision = state_changes.command_error_revision();
+            error_text.hide_at = now + COMMAND_ERROR_SECONDS;
+            text.0 = state_changes.command_error().unwrap_or_default().into();
+        } else if now >= error_text.hide_at && !text.0.is_empty() {
+            text.0.clear();
+        }
+    }
+}
+
+fn update_chat_input(
+    chat_input: Res<ChatInput>,
+    mut query_text: Query<&mut Text, With<ChatInputText>>,
+) {
+    if !chat_input.is_changed() {
+        return;
+    }
+    for mut text in &mut query_text {
+        text.0 = if chat_input.typing {
+            format!("> {}_", chat_input.text)
+        } else {
+            "Enter: chat".into()
+        };
+    }
+}
@@ -55,3 +55,28 @@ pub const PLAYER_NAME_SHADOW_COLOR: Color = Color::BLACK;
 pub const ACTIVE_PLAYER_BORDER_WIDTH: f32 = 3.0;
 pub const ACTIVE_PLAYER_BLINK_SPEED: f32 = 4.0;
 pub const ACTIVE_PLAYER_BORDER_COLOR: Color = Color::srgb(1.0, 0.9, 0.3);
+
+/// pause between two moves when playing back a replay at normal speed
+pub const REPLAY_STEP_MILLIS: u64 = 1200;
+/// available replay speeds (factors applied to the pause between moves)
+pub const REPLAY_SPEEDS: [f32; 5] = [0.25, 0.5, 1., 2., 4.];
+pub const REPLAY_STATUS_FONT_SIZE: f32 = 18.0;
+
+/// number of chat messages kept for display
+pub const CHAT_LOG_LENGTH: usize = 8;
+pub const CHAT_FONT_SIZE: f32 = 16.0;
+pub const CHAT_PANEL_WIDTH: f32 = 280.0;
+pub const CHAT_INPUT_COLOR: Color = Color::srgb(1.0, 0.9, 0.3);
+pub const CHAT_BUTTON_COLOR: Color = Color::srgba(0.2, 0.2, 0.2, 2. / 3.);
+pub const CHAT_BUTTON_HOVER_COLOR: Color = Color::srgba(0.4, 0.4, 0.4, 2. / 3.);
+
+/// how long the message about a rejected command (e.g. playing out of turn)
+/// stays visible
+pub const COMMAND_ERROR_SECONDS: f64 = 4.0;
+pub const COMMAND_ERROR_COLOR: Color = Color::srgb(1.0, 0.45, 0.35);
+
+/// tints of our cards that take a trick, make a Zing, or leave a single card
+/// on the table that the next player could take as a Zing (see move hints)
+pub const MOVE_HINT_TRICK_COLOR: Color = Color::srgb(0.75, 1.0, 0.75);
+pub const MOVE_HINT_ZING_COLOR: Color = Color::srgb(1.0, 0.9, 0.5);
+pub const MOVE_HINT_OPENS_ZING_COLOR: Color = Color::srgb(1.0, 0.75, 0.75);
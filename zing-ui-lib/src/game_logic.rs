@@ -1,9 +1,15 @@
 use bevy::prelude::*;
 use std::collections::VecDeque;
 use tracing::{debug, error, info, warn};
+
+use crate::constants::CHAT_LOG_LENGTH;
 use zing_game::card_action::CardAction;
-use zing_game::client_notification::ClientNotification;
+use zing_game::chat::{ChatContent, ChatMessage};
+use zing_game::client_command::{ClientCommand, Command, CommandError};
+use zing_game::client_notification::{ClientNotification, NotificationMessage, PROTOCOL_VERSION};
+use zing_game::error_code::ErrorCode;
 use zing_game::game::GameState;
+use zing_game::zing_game::MoveHint;
 
 const MAX_RETRY_DELAY: u16 = 30;
 
@@ -12,8 +18,7 @@ use {
     bevy_tokio_tasks::{TokioTasksPlugin, TokioTasksRuntime},
     futures_util::SinkExt,
     futures_util::StreamExt,
-    reqwest::cookie,
-    std::sync::Arc,
+    tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
     tungstenite::client::IntoClientRequest,
 };
 
@@ -22,13 +27,18 @@ pub type TasksRuntime = TokioTasksRuntime;
 
 #[cfg(target_family = "wasm")]
 use {
+    std::cell::{Cell, RefCell},
     std::sync::{atomic::Ordering, mpsc, mpsc::Receiver, Mutex},
     wasm_bindgen::prelude::*,
-    wasm_bindgen_futures::JsFuture,
     web_sys::{ErrorEvent, MessageEvent, WebSocket},
-    web_sys::{Request, RequestInit, Response},
 };
 
+#[cfg(target_family = "wasm")]
+thread_local! {
+    /// currently open table WebSocket, used for sending commands
+    static CURRENT_WEBSOCKET: RefCell<Option<WebSocket>> = const { RefCell::new(None) };
+}
+
 #[cfg(target_family = "wasm")]
 #[derive(Resource)]
 pub struct TasksRuntime {
@@ -57,11 +67,13 @@ impl Plugin for GameLogicPlugin {
 
         #[cfg(not(target_family = "wasm"))]
         app.insert_resource(game_logic)
+            .init_resource::<StateChanges>()
             .add_plugins(TokioTasksPlugin::default())
             .add_systems(Startup, spawn_websocket_handler);
 
         #[cfg(target_family = "wasm")]
         app.insert_resource(game_logic)
+            .init_resource::<StateChanges>()
             .insert_resource(TasksRuntime::new())
             .add_systems(Startup, spawn_websocket_handler)
             .add_systems(Update, receive_client_notifications);
@@ -70,17 +82,16 @@ impl Plugin for GameLogicPlugin {
 
 #[derive(Resource)]
 pub struct GameLogic {
-    notifications: VecDeque<StateChange>,
-
-    #[cfg(not(target_family = "wasm"))]
-    client: reqwest::Client,
-    play_uri: String,
-    #[cfg(not(target_family = "wasm"))]
-    ws_uri: http::Uri,
-    #[cfg(target_family = "wasm")]
     ws_uri: String,
     #[cfg(not(target_family = "wasm"))]
     login_cookie: String,
+    /// commands to be sent via the WebSocket (picked up by the WebSocket task)
+    #[cfg(not(target_family = "wasm"))]
+    command_sender: UnboundedSender<ClientCommand>,
+    #[cfg(not(target_family = "wasm"))]
+    command_receiver: Option<UnboundedReceiver<ClientCommand>>,
+    /// id of the last command sent, for matching the server's replies
+    last_request_id: u64,
 }
 
 pub enum StateChange {
@@ -89,6 +100,169 @@ pub enum StateChange {
     ActivePlayer(Option<usize>),
 }
 
+/// Queue of state changes to be displayed, fed either from the server (see
+/// [GameLogic]) or from a replay (see [crate::replay]).
+#[derive(Resource, Default)]
+pub struct StateChanges {
+    notifications: VecDeque<StateChange>,
+    /// recent chat messages at the table (oldest first)
+    chat_messages: VecDeque<ChatMessage>,
+    /// incremented whenever chat_messages changes
+    chat_revision: u64,
+    /// message about the last command rejected by the server
+    command_error: Option<String>,
+    /// incremented whenever a command is rejected
+    command_error_revision: u64,
+    /// outcomes of the cards we may play, if it is our turn and the table
+    /// allows move hints
+    move_hints: Vec<MoveHint>,
+}
+
+/// Message for the user explaining why the server rejected a command.
+fn command_error_text(err: &CommandError) -> String {
+    match err.code {
+        ErrorCode::NotYourTurn => "It's not your turn.".into(),
+        ErrorCode::InvalidCardIndex => "That card is not in your hand.".into(),
+        ErrorCode::GameNotStarted => "The game has not started yet.".into(),
+        ErrorCode::GameAlreadyStarted => "The game has already started.".into(),
+        ErrorCode::InvalidPlayerCount => "A game needs exactly two or four players.".into(),
+        ErrorCode::MatchDecided => "The match is already decided.".into(),
+        ErrorCode::NotAtTable => "You have not joined this table.".into(),
+        ErrorCode::NotLoggedIn | ErrorCode::SessionExpired => {
+            "Your session has expired, please log in again.".into()
+        }
+        ErrorCode::RateLimited => "Too many messages, please slow down.".into(),
+        _ => err.message.clone(),
+    }
+}
+
+impl StateChanges {
+    pub fn handle_client_notification(&mut self, notification: ClientNotification) {
+        match notification {
+            ClientNotification::GameStatus(initial_state, we_are_player, active_player) => {
+                self.move_hints.clear();
+                // the next game of a match starts right after the previous one,
+                // whose final card actions should still be shown first
+                self.notifications
+                    .push_back(StateChange::GameStarted(initial_state, we_are_player));
+                self.notifications
+                    .push_back(StateChange::ActivePlayer(active_player));
+            }
+            ClientNotification::CardActions(actions, active_player) => {
+                self.move_hints.clear();
+                self.notifications
+                    .extend(actions.into_iter().map(StateChange::CardAction));
+                self.notifications
+                    .push_back(StateChange::ActivePlayer(active_player));
+            }
+            ClientNotification::MatchStatus(match_points, match_winner) => {
+                info!(
+                    "match points: {:?}, winner: {:?}",
+                    match_points, match_winner
+                );
+            }
+            ClientNotification::TurnTimer(turn_timer) => {
+                debug!("turn timer: {:?}", turn_timer);
+            }
+            ClientNotification::MoveHints(move_hints) => {
+                self.move_hints = move_hints;
+            }
+            ClientNotification::CommandResult(request_id, result) => match result {
+                Ok(()) => debug!("command {} succeeded", request_id),
+                Err(err) => {
+                    warn!("command {} failed: {}", request_id, err);
+                    self.command_error = Some(command_error_text(&err));
+                    self.command_error_revision += 1;
+                }
+            },
+            ClientNotification::Chat(message) => {
+                self.chat_messages.push_back(message);
+                if self.chat_messages.len() > CHAT_LOG_LENGTH {
+                    self.chat_messages.pop_front();
+                }
+                self.chat_revision += 1;
+            }
+            ClientNotification::ChatHistory(messages) => {
+                let skip = messages.len().saturating_sub(CHAT_LOG_LENGTH);
+                self.chat_messages = messages.into_iter().skip(skip).collect();
+                self.chat_revision += 1;
+            }
+        }
+    }
+
+    pub fn chat_messages(&self) -> impl Iterator<Item = &ChatMessage> {
+        self.chat_messages.iter()
+    }
+
+    pub fn chat_revision(&self) -> u64 {
+        self.chat_revision
+    }
+
+    pub fn command_error(&self) -> Option<&str> {
+        self.command_error.as_deref()
+    }
+
+    pub fn command_error_revision(&self) -> u64 {
+        self.command_error_revision
+    }
+
+    pub fn move_hints(&self) -> &[MoveHint] {
+        &self.move_hints
+    }
+
+    pub fn push(&mut self, state_change: StateChange) {
+        self.notifications.push_back(state_change);
+    }
+
+    pub fn clear(&mut self) {
+        self.notifications.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notifications.is_empty()
+    }
+
+    pub fn get_next_state_change(&mut self) -> Option<StateChange> {
+        self.notifications.pop_front()
+    }
+}
+
+/// URI for connecting to the table WebSocket, resuming after the last
+/// notification received (if any).
+fn table_ws_uri(ws_uri: &str, last_seq: Option<u64>) -> String {
+    match last_seq {
+        None => format!("{}?version={}", ws_uri, PROTOCOL_VERSION),
+        Some(seq) => format!(
+            "{}?version={}&resume_from={}",
+            ws_uri, PROTOCOL_VERSION, seq
+        ),
+    }
+}
+
+/// Parses a notification received via the table WebSocket and remembers its
+/// sequence number.
+fn unwrap_notification(json: &str, last_seq: &mut Option<u64>) -> Option<ClientNotification> {
+    // check the version before trying to parse the actual notification
+    let message = match serde_json::from_str::<NotificationMessage<serde_json::Value>>(json) {
+        Ok(message) => message,
+        Err(err) => {
+            warn!("could not parse notification: {}", err);
+            return None;
+        }
+    };
+    if message.version != PROTOCOL_VERSION {
+        error!(
+            "server speaks notification protocol version {}, but we only support version {}",
+            message.version, PROTOCOL_VERSION
+        );
+        return None;
+    }
+    *last_seq = Some(message.seq);
+    serde_json::from_value(message.notification)
+        .inspect_err(|err| warn!("could not parse notification: {}", err))
+        .ok()
+}
+
 impl GameLogic {
     #[cfg(not(target_family = "wasm"))]
     pub fn new(
@@ -96,24 +270,19 @@ impl GameLogic {
         login_id: &str,
         table_id: &str,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let jar = cookie::Jar::default();
-        jar.add_cookie_str(&format!("login_id={}", login_id), &base_url.parse()?);
-        let client = reqwest::Client::builder()
-            .cookie_provider(Arc::new(jar))
-            .build()?;
-
-        let play_uri = format!("{}/table/{}/game/play", base_url, table_id);
-
-        let ws_uri = format!("{}/table/{}/ws", base_url.replace("http", "ws"), table_id).parse()?;
+        let ws_uri = format!("{}/table/{}/ws", base_url.replace("http", "ws"), table_id);
+        ws_uri.parse::<http::Uri>()?;
 
         let login_cookie = format!("login_id={}", login_id);
 
+        let (command_sender, command_receiver) = mpsc::unbounded_channel();
+
         Ok(Self {
-            notifications: VecDeque::new(),
-            client,
-            play_uri,
             ws_uri,
             login_cookie,
+            command_sender,
+            command_receiver: Some(command_receiver),
+            last_request_id: 0,
         })
     }
 
@@ -123,30 +292,34 @@ impl GameLogic {
         _login_id: &str,
         table_id: &str,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let play_uri = format!("{}/table/{}/game/play", base_url, table_id);
-
         let ws_uri = format!("{}/table/{}/ws", base_url.replace("http", "ws"), table_id);
 
         Ok(Self {
-            notifications: VecDeque::new(),
-            play_uri,
             ws_uri,
+            last_request_id: 0,
         })
     }
 
     #[cfg(not(target_family = "wasm"))]
     fn spawn_websocket_handler(
-        &self,
+        &mut self,
         runtime: ResMut<TasksRuntime>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let ws_uri = self.ws_uri.clone();
         let login_cookie = self.login_cookie.clone();
+        let mut command_receiver = self
+            .command_receiver
+            .take()
+            .ok_or("WebSocket handler already running")?;
 
         runtime.spawn_background_task(|mut ctx| async move {
             let mut retry_delay = 1;
+            let mut last_seq = None;
 
             loop {
-                let mut request = ws_uri.clone().into_client_request().unwrap();
+                let mut request = table_ws_uri(&ws_uri, last_seq)
+                    .into_client_request()
+                    .unwrap();
                 request.headers_mut().insert(
                     http::header::COOKIE,
                     http::HeaderValue::from_str(&login_cookie)
@@ -160,17 +333,32 @@ impl GameLogic {
 
                         let mut stream = ws_stream;
                         loop {
-                            match stream.next().await {
+                            let message = tokio::select! {
+                                message = stream.next() => message,
+                                Some(command) = command_receiver.recv() => {
+                                    let json = serde_json::to_string(&command)
+                                        .expect("commands are serializable");
+                                    if let Err(e) =
+                                        stream.send(tungstenite::Message::text(json)).await
+                                    {
+                                        error!("Failed to send command: {}", e);
+                                        break;
+                                    }
+                                    continue;
+                                }
+                            };
+                            match message {
                                 Some(Ok(message)) => match message {
                                     tungstenite::Message::Text(json) => {
-                                        if let Ok(client_notification) = serde_json::from_str(&json)
+                                        if let Some(client_notification) =
+                                            unwrap_notification(&json, &mut last_seq)
                                         {
                                             ctx.run_on_main_thread(move |ctx| {
-                                                let mut game_logic = ctx
+                                                let mut state_changes = ctx
                                                     .world
-                                                    .get_resource_mut::<GameLogic>()
+                                                    .get_resource_mut::<StateChanges>()
                                                     .unwrap();
-                                                game_logic.handle_client_notification(
+                                                state_changes.handle_client_notification(
                                                     client_notification,
                                                 );
                                             })
@@ -232,8 +420,9 @@ impl GameLogic {
 
         let ws_uri = self.ws_uri.clone();
         let retry_delay_ms = std::rc::Rc::new(AtomicU32::new(1000)); // Start with 1 second in milliseconds
+        let last_seq = std::rc::Rc::new(Cell::new(None));
 
-        Self::create_websocket_connection(ws_uri, sender, retry_delay_ms);
+        Self::create_websocket_connection(ws_uri, sender, retry_delay_ms, last_seq);
     }
 
     #[cfg(target_family = "wasm")]
@@ -241,38 +430,35 @@ impl GameLogic {
         ws_uri: String,
         sender: mpsc::Sender<ClientNotification>,
         retry_delay_ms: std::rc::Rc<std::sync::atomic::AtomicU32>,
+        last_seq: std::rc::Rc<Cell<Option<u64>>>,
     ) {
-        let ws = match WebSocket::new(&ws_uri) {
+        let ws = match WebSocket::new(&table_ws_uri(&ws_uri, last_seq.get())) {
             Ok(ws) => ws,
             Err(e) => {
                 error!("Failed to create WebSocket: {:?}", e);
-                Self::schedule_reconnect(ws_uri, sender, retry_delay_ms);
+                Self::schedule_reconnect(ws_uri, sender, retry_delay_ms, last_seq);
                 return;
             }
         };
 
         info!("WebSocket connecting...");
+        CURRENT_WEBSOCKET.set(Some(ws.clone()));
 
         {
             let sender = sender.clone();
+            let last_seq = last_seq.clone();
             let onmessage_callback = Closure::<dyn FnMut(_)>::new(move |e: MessageEvent| {
-                if let Ok(client_notification) =
-                    serde_wasm_bindgen::from_value::<ClientNotification>(e.data())
-                {
-                    debug!(
-                        "message event, received ClientNotification: {:?}",
-                        client_notification
-                    );
-                    if sender.send(client_notification).is_err() {
-                        error!("could not send ClientNotification via mspc channel");
-                    }
-                } else if let Ok(txt) = e.data().dyn_into::<js_sys::JsString>() {
+                if let Ok(txt) = e.data().dyn_into::<js_sys::JsString>() {
                     debug!("message event, received Text: {:?}", txt);
-                    if let Ok(client_notification) = serde_json::from_str(&String::from(txt)) {
+                    let mut seq = last_seq.get();
+                    if let Some(client_notification) =
+                        unwrap_notification(&String::from(txt), &mut seq)
+                    {
                         if sender.send(client_notification).is_err() {
                             error!("could not send ClientNotification via mspc channel");
                         }
                     }
+                    last_seq.set(seq);
                 } else {
                     debug!("message event, received: {:?}", e.data());
                 }
@@ -293,9 +479,16 @@ impl GameLogic {
             let ws_uri = ws_uri.clone();
             let sender = sender.clone();
             let retry_delay = retry_delay_ms.clone();
+            let last_seq = last_seq.clone();
             let onclose_callback = Closure::<dyn FnMut(_)>::new(move |e: web_sys::CloseEvent| {
                 warn!("WebSocket closed: code={}, reason={}", e.code(), e.reason());
-                Self::schedule_reconnect(ws_uri.clone(), sender.clone(), retry_delay.clone());
+                CURRENT_WEBSOCKET.set(None);
+                Self::schedule_reconnect(
+                    ws_uri.clone(),
+                    sender.clone(),
+                    retry_delay.clone(),
+                    last_seq.clone(),
+                );
             });
             ws.set_onclose(Some(onclose_callback.as_ref().unchecked_ref()));
             onclose_callback.forget();
@@ -317,6 +510,7 @@ impl GameLogic {
         ws_uri: String,
         sender: mpsc::Sender<ClientNotification>,
         retry_delay_ms: std::rc::Rc<std::sync::atomic::AtomicU32>,
+        last_seq: std::rc::Rc<Cell<Option<u64>>>,
     ) {
         let current_delay_ms = retry_delay_ms.load(Ordering::Relaxed);
         warn!("Scheduling WebSocket reconnect in {}ms", current_delay_ms);
@@ -331,6 +525,7 @@ impl GameLogic {
                 ws_uri_clone.clone(),
                 sender_clone.clone(),
                 retry_delay_clone.clone(),
+                last_seq.clone(),
             );
         });
 
@@ -347,92 +542,59 @@ impl GameLogic {
         retry_delay_ms.store(new_delay, Ordering::Relaxed);
     }
 
-    pub fn handle_client_notification(&mut self, notification: ClientNotification) {
-        match notification {
-            ClientNotification::GameStatus(initial_state, we_are_player, active_player) => {
-                self.notifications.clear();
-                self.notifications
-                    .push_back(StateChange::GameStarted(initial_state, we_are_player));
-                self.notifications
-                    .push_back(StateChange::ActivePlayer(active_player));
-            }
-            ClientNotification::CardActions(actions, active_player) => {
-                self.notifications
-                    .extend(actions.into_iter().map(StateChange::CardAction));
-                self.notifications
-                    .push_back(StateChange::ActivePlayer(active_player));
-            }
+    fn next_command(&mut self, command: Command) -> ClientCommand {
+        self.last_request_id += 1;
+        ClientCommand {
+            request_id: self.last_request_id,
+            command,
         }
     }
 
-    pub fn get_next_state_change(&mut self) -> Option<StateChange> {
-        self.notifications.pop_front()
+    #[cfg(not(target_family = "wasm"))]
+    fn send_command(&mut self, command: Command) {
+        let command = self.next_command(command);
+        // the receiver is only dropped when the app exits
+        let _ = self.command_sender.send(command);
     }
 
-    #[cfg(not(target_family = "wasm"))]
-    pub fn play_card(&mut self, runtime: ResMut<TasksRuntime>, card_index: usize) {
-        let request = self
-            .client
-            .post(&self.play_uri)
-            .header(http::header::CONTENT_TYPE, "application/json")
-            .body(format!("{{ \"card_index\": {} }}", card_index));
-        runtime.spawn_background_task(|_ctx| async move {
-            match request.send().await {
-                Err(err) => error!("Rest API error trying to play card: {}", err),
-                Ok(response) => {
-                    debug!("{} {}", response.status(), response.text().await.unwrap());
+    #[cfg(target_family = "wasm")]
+    fn send_command(&mut self, command: Command) {
+        let json =
+            serde_json::to_string(&self.next_command(command)).expect("commands are serializable");
+        CURRENT_WEBSOCKET.with_borrow(|ws| match ws {
+            Some(ws) if ws.ready_state() == WebSocket::OPEN => {
+                if let Err(e) = ws.send_with_str(&json) {
+                    error!("Failed to send command: {:?}", e);
                 }
-            };
+            }
+            _ => error!("cannot send command while WebSocket is not connected"),
         });
     }
 
-    #[cfg(target_family = "wasm")]
-    pub fn play_card(&mut self, _runtime: ResMut<TasksRuntime>, card_index: usize) {
-        use bevy::tasks::AsyncComputeTaskPool;
-
-        let opts = RequestInit::new();
-        opts.set_method("POST");
-        opts.set_body(&JsValue::from_str(&format!(
-            "{{ \"card_index\": {} }}",
-            card_index
-        )));
-
-        let request = Request::new_with_str_and_init(&self.play_uri, &opts).unwrap();
-
-        request
-            .headers()
-            .set(http::header::CONTENT_TYPE.as_str(), "application/json")
-            .unwrap();
-
-        let thread_pool = AsyncComputeTaskPool::get();
-        let task = thread_pool.spawn(async move {
-            let window = web_sys::window().unwrap();
-            let resp_value = JsFuture::from(window.fetch_with_request(&request))
-                .await
-                .unwrap();
-
-            let resp: Response = resp_value.dyn_into().unwrap();
-
-            let json = JsFuture::from(resp.json().unwrap()).await;
+    pub fn play_card(&mut self, card_index: usize) {
+        self.send_command(Command::PlayCard { card_index });
+    }
 
-            debug!("API response from playing card: {:?}", json);
-        });
-        task.detach();
+    pub fn chat(&mut self, content: ChatContent) {
+        self.send_command(Command::Chat(content));
     }
 }
 
 //#[cfg(not(target_family = "wasm"))]
-pub fn spawn_websocket_handler(game_logic: Res<GameLogic>, runtime: ResMut<TasksRuntime>) {
+pub fn spawn_websocket_handler(mut game_logic: ResMut<GameLogic>, runtime: ResMut<TasksRuntime>) {
     let _ = game_logic.spawn_websocket_handler(runtime);
 }
 
 #[cfg(target_family = "wasm")]
-pub fn receive_client_notifications(mut game_logic: ResMut<GameLogic>, runtime: Res<TasksRuntime>) {
+pub fn receive_client_notifications(
+    mut state_changes: ResMut<StateChanges>,
+    runtime: Res<TasksRuntime>,
+) {
     if let Ok(receiver) = runtime.notification_receiver.lock() {
         let r = receiver.try_recv();
         if let Ok(client_notification) = r {
             debug!("received client notification: {:?}", client_notification);
-            game_logic.handle_client_notification(client_notification);
+            state_changes.handle_client_notification(client_notification);
         }
     } else {
         error!("could not access receiver");
@@ -2,12 +2,15 @@ use bevy::prelude::*;
 use bevy_tweening::TweeningPlugin;
 #[cfg(target_family = "wasm")]
 use wasm_bindgen::prelude::*;
+use zing_game::replay::GameReplay;
 
 mod app_state;
 mod card_sprite;
+mod chat;
 mod constants;
 mod debug;
 mod game_logic;
+mod replay;
 mod zing_layout;
 
 // The login_id is actually unused in wasm builds (because the .wasm re-uses the
@@ -15,8 +18,44 @@ mod zing_layout;
 // just pass some random string from JS for now).
 #[cfg_attr(target_family = "wasm", wasm_bindgen)]
 pub fn start_remote_game(login_id: String, table_id: String, base_url: String) {
-    App::new()
-        .insert_resource(ClearColor(Color::srgb_u8(0x33, 0x69, 0x1d)))
+    new_app()
+        .add_plugins(game_logic::GameLogicPlugin {
+            base_url,
+            login_id,
+            table_id,
+        })
+        .add_plugins(chat::ChatPlugin)
+        .run();
+}
+
+/// Watch a recorded game (see [zing_game::replay]) instead of playing at a
+/// live table.  The replay is given as JSON and verified before display.
+/// `viewer` is the index of the player displayed in front; other players'
+/// cards are only visible if `show_hands` is set.
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+pub fn start_replay(replay_json: String, viewer: usize, show_hands: bool) -> Result<(), String> {
+    let replay = GameReplay::from_json(&replay_json).map_err(|err| err.to_string())?;
+    // TODO: the layout does not support four players yet
+    if replay.player_names.len() != 2 {
+        return Err("only replays of two-player games can be displayed".into());
+    }
+    if viewer >= replay.player_names.len() {
+        return Err(format!("invalid player index {viewer}"));
+    }
+
+    new_app()
+        .add_plugins(replay::ReplayPlugin {
+            replay,
+            viewer,
+            show_hands,
+        })
+        .run();
+    Ok(())
+}
+
+fn new_app() -> App {
+    let mut app = App::new();
+    app.insert_resource(ClearColor(Color::srgb_u8(0x33, 0x69, 0x1d)))
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Zing".to_string(),
@@ -28,11 +67,6 @@ pub fn start_remote_game(login_id: String, table_id: String, base_url: String) {
         }))
         .add_plugins(TweeningPlugin)
         .add_plugins(zing_layout::LayoutPlugin)
-        .add_plugins(game_logic::GameLogicPlugin {
-            base_url,
-            login_id,
-            table_id,
-        })
-        .add_plugins(debug::DebugPlugin)
-        .run();
+        .add_plugins(debug::DebugPlugin);
+    app
 }
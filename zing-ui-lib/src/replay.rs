@@ -0,0 +1,299 @@
+//! Replay mode: instead of attaching to a live table, the layout is fed with
+//! the state changes of a recorded game (see [zing_game::replay]), which can
+//! be played back, paused, stepped through and sped up or slowed down.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use zing_game::card_action::CardAction;
+use zing_game::game::{GamePhase, GameState};
+use zing_game::replay::GameReplay;
+use zing_game::zing_game::{ZingGame, ZingMove};
+
+use crate::constants::*;
+use crate::game_logic::{StateChange, StateChanges};
+
+pub struct ReplayPlugin {
+    /// verified replay (see [GameReplay::verify])
+    pub replay: GameReplay,
+    /// index of the player displayed in front
+    pub viewer: usize,
+    /// whether the cards of all players are shown, not just the viewer's
+    pub show_hands: bool,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ReplayState::new(&self.replay, self.viewer, self.show_hands))
+            .add_systems(Startup, (start_replay, spawn_replay_status))
+            .add_systems(
+                Update,
+                (
+                    handle_replay_input,
+                    play_back_replay.after(handle_replay_input),
+                    update_replay_status.after(play_back_replay),
+                ),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct ReplayState {
+    /// full game state before the first and after each step
+    states: Vec<GameState>,
+    /// player whose turn it is before the first and after each step
+    active_players: Vec<Option<usize>>,
+    /// actions of each step: dealing, then one step per move
+    steps: Vec<Vec<CardAction>>,
+    /// number of steps displayed so far
+    position: usize,
+    playing: bool,
+    /// index into [REPLAY_SPEEDS]
+    speed_index: usize,
+    step_timer: Timer,
+    viewer: usize,
+    show_hands: bool,
+}
+
+impl ReplayState {
+    fn new(replay: &GameReplay, viewer: usize, show_hands: bool) -> Self {
+        let mut game = ZingGame::new_with_deck(
+            replay.player_names.clone(),
+            replay.dealer,
+            replay.deck.clone(),
+        )
+        .expect("replay has been verified");
+        let mut states = vec![game.state().clone()];
+        let mut active_players = vec![None];
+        let mut step_ends = Vec::new();
+
+        game.setup_game().expect("replay has been verified");
+        let mut end_step = |game: &ZingGame| {
+            step_ends.push(game.history().len());
+            states.push(game.state().clone());
+            active_players.push(game.current_player());
+        };
+        end_step(&game);
+
+        for zing_move in &replay.moves {
+            match *zing_move {
+                ZingMove::PlayCard {
+                    player, card_index, ..
+                } => game
+                    .play_card(player, card_index)
+                    .expect("replay has been verified"),
+                ZingMove::TakeBack => {
+                    game.undo_last_turn().expect("replay has been verified");
+                }
+            }
+            end_step(&game);
+        }
+
+        let mut step_start = 0;
+        let steps = step_ends
+            .into_iter()
+            .map(|step_end| {
+                let step = game.history()[step_start..step_end].to_vec();
+                step_start = step_end;
+                step
+            })
+            .collect();
+
+        Self {
+            states,
+            active_players,
+            steps,
+            position: 0,
+            playing: false,
+            speed_index: REPLAY_SPEEDS.iter().position(|speed| *speed == 1.).unwrap(),
+            step_timer: Timer::new(Duration::from_millis(REPLAY_STEP_MILLIS), TimerMode::Once),
+            viewer,
+            show_hands,
+        }
+    }
+
+    fn speed(&self) -> f32 {
+        REPLAY_SPEEDS[self.speed_index]
+    }
+
+    fn change_speed(&mut self, faster: bool) {
+        self.speed_index = if faster {
+            (self.speed_index + 1).min(REPLAY_SPEEDS.len() - 1)
+        } else {
+            self.speed_index.saturating_sub(1)
+        };
+        self.step_timer.set_duration(Duration::from_secs_f32(
+            REPLAY_STEP_MILLIS as f32 / 1000. / self.speed(),
+        ));
+    }
+
+    fn view_state(&self, state: &GameState) -> GameState {
+        if self.show_hands {
+            state.clone()
+        } else {
+            state.new_view_for_player(self.viewer)
+        }
+    }
+
+    fn view_action(&self, action: &CardAction) -> CardAction {
+        if self.show_hands {
+            action.clone()
+        } else {
+            action
+                .new_view_for_player(self.viewer)
+                .expect("replay has been verified")
+        }
+    }
+
+    /// Replaces the displayed state with the one at the current position
+    /// (without animation).
+    fn show_current_state(&self, state_changes: &mut StateChanges) {
+        state_changes.clear();
+        state_changes.push(StateChange::GameStarted(
+            self.view_state(&self.states[self.position]),
+            self.viewer,
+        ));
+        state_changes.push(StateChange::ActivePlayer(
+            self.active_players[self.position],
+        ));
+    }
+
+    fn step_forward(&mut self, state_changes: &mut StateChanges) -> bool {
+        let Some(step) = self.steps.get(self.position) else {
+            return false;
+        };
+        for action in step {
+            state_changes.push(StateChange::CardAction(self.view_action(action)));
+        }
+        self.position += 1;
+        state_changes.push(StateChange::ActivePlayer(
+            self.active_players[self.position],
+        ));
+        true
+    }
+
+    fn step_back(&mut self, state_changes: &mut StateChanges) {
+        if self.position == 0 {
+            return;
+        }
+        self.position -= 1;
+        for action in self.steps[self.position].iter().rev() {
+            state_changes.push(StateChange::CardAction(
+                self.view_action(&action.invert().expect("replay has been verified")),
+            ));
+        }
+        state_changes.push(StateChange::ActivePlayer(
+            self.active_players[self.position],
+        ));
+        if self.states[self.position].phase() != GamePhase::InGame {
+            // the layout spreads out the table cards only before the first
+            // card is played, so we need to start over
+            self.show_current_state(state_changes);
+        }
+    }
+}
+
+#[derive(Component)]
+struct ReplayStatusText;
+
+fn start_replay(replay_state: Res<ReplayState>, mut state_changes: ResMut<StateChanges>) {
+    replay_state.show_current_state(&mut state_changes);
+}
+
+fn spawn_replay_status(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(PLAYER_NAME_MARGIN),
+            left: Val::Px(PLAYER_NAME_MARGIN),
+            ..default()
+        },
+        Text::new(""),
+        TextFont::from_font(asset_server.load(PLAYER_NAME_FONT))
+            .with_font_size(REPLAY_STATUS_FONT_SIZE),
+        TextColor(PLAYER_NAME_COLOR),
+        TextShadow {
+            color: PLAYER_NAME_SHADOW_COLOR,
+            offset: Vec2::new(1., 1.),
+        },
+        ReplayStatusText,
+    ));
+}
+
+fn handle_replay_input(
+    mut replay_state: ResMut<ReplayState>,
+    mut state_changes: ResMut<StateChanges>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        replay_state.playing = !replay_state.playing;
+        if replay_state.playing && replay_state.position == replay_state.steps.len() {
+            // play again from the beginning
+            replay_state.position = 0;
+            replay_state.show_current_state(&mut state_changes);
+        }
+        replay_state.step_timer.reset();
+    } else if keyboard_input.just_pressed(KeyCode::ArrowRight) {
+        replay_state.playing = false;
+        replay_state.step_forward(&mut state_changes);
+    } else if keyboard_input.just_pressed(KeyCode::ArrowLeft) {
+        replay_state.playing = false;
+        replay_state.step_back(&mut state_changes);
+    } else if keyboard_input.just_pressed(KeyCode::ArrowUp) {
+        replay_state.change_speed(true);
+    } else if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+        replay_state.change_speed(false);
+    } else if keyboard_input.just_pressed(KeyCode::KeyH) {
+        replay_state.show_hands = !replay_state.show_hands;
+        replay_state.show_current_state(&mut state_changes);
+    }
+}
+
+fn play_back_replay(
+    mut replay_state: ResMut<ReplayState>,
+    mut state_changes: ResMut<StateChanges>,
+    time: Res<Time>,
+) {
+    if !replay_state.playing {
+        return;
+    }
+    // wait until the animations of the previous step are done
+    if !state_changes.is_empty() {
+        replay_state.step_timer.reset();
+        return;
+    }
+
+    replay_state.step_timer.tick(time.delta());
+    if replay_state.step_timer.finished() {
+        replay_state.playing = replay_state.step_forward(&mut state_changes);
+        replay_state.step_timer.reset();
+    }
+}
+
+fn update_replay_status(
+    replay_state: Res<ReplayState>,
+    mut query_text: Query<&mut Text, With<ReplayStatusText>>,
+) {
+    if !replay_state.is_changed() {
+        return;
+    }
+    for mut text in &mut query_text {
+        text.0 = format!(
+            "{}: step {}/{}, speed {}x\n\
+             Space: play/pause, Left/Right: step, Up/Down: speed, H: {} hands",
+            if replay_state.playing {
+                "Playing"
+            } else {
+                "Paused"
+            },
+            replay_state.position,
+            replay_state.steps.len(),
+            replay_state.speed(),
+            if replay_state.show_hands {
+                "hide"
+            } else {
+                "show"
+            },
+        );
+    }
+}
@@ -1,15 +1,16 @@
 use std::time::Duration;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use crate::app_state::AppState;
 use crate::card_sprite::CardSprite;
+use crate::chat::not_typing_chat;
 use crate::constants::*;
-use crate::game_logic::{GameLogic, StateChange, TasksRuntime};
+use crate::game_logic::{GameLogic, StateChange, StateChanges};
 use bevy::{prelude::*, render::camera::ScalingMode};
 use bevy_tweening::*;
 use zing_game::card_action::CardAction;
 use zing_game::game::{GamePhase, GameState};
-use zing_game::zing_game::ZingGame;
+use zing_game::zing_game::{MoveOutcome, ZingGame};
 use zing_game::{card_action::CardLocation, game::CardState};
 
 #[derive(Resource)]
@@ -87,6 +88,7 @@ impl Plugin for LayoutPlugin {
         app.add_event::<CardActionEvent>();
         app.init_state::<AppState>();
         app.insert_resource(LayoutState::new());
+        app.init_resource::<StateChanges>();
 
         app.add_systems(Startup, (setup_camera, setup_card_stacks));
 
@@ -98,10 +100,13 @@ impl Plugin for LayoutPlugin {
                     .before(update_cards_from_action),
                 spawn_cards_for_initial_state,
                 update_active_player_border,
+                highlight_move_hints,
                 zoom_on_hover.run_if(in_state(AppState::Interaction)),
                 unzoom_after_hover.run_if(in_state(AppState::Interaction)),
                 handle_keyboard_input
                     .run_if(in_state(AppState::Interaction))
+                    .run_if(resource_exists::<GameLogic>)
+                    .run_if(not_typing_chat)
                     .before(update_cards_from_action),
                 update_cards_from_action,
             ),
@@ -444,7 +449,7 @@ fn card_offsets_for_stack<'a>(
 }
 
 fn get_next_action_after_animation_finished(
-    mut game_logic: ResMut<GameLogic>,
+    mut state_changes: ResMut<StateChanges>,
     mut layout_state: ResMut<LayoutState>,
     mut initial_state_events: EventWriter<InitialGameStateEvent>,
     mut card_events: EventWriter<CardActionEvent>,
@@ -456,7 +461,7 @@ fn get_next_action_after_animation_finished(
         return;
     }
 
-    match game_logic.get_next_state_change() {
+    match state_changes.get_next_state_change() {
         Some(StateChange::GameStarted(game_state, we_are_player)) => {
             let table_stack_spread_out = game_state.phase() != GamePhase::InGame;
             initial_state_events.write(InitialGameStateEvent {
@@ -553,12 +558,16 @@ fn update_cards_from_action(
     for card_action_event in action_events.read() {
         let action = &card_action_event.action;
 
-        action.apply(
+        if let Err(err) = action.apply(
             layout_state
                 .displayed_state
                 .as_mut()
                 .expect("can only update cards if displayed state is not None"),
-        );
+        ) {
+            // the displayed state no longer matches the server's
+            warn!("ignoring card action that cannot be applied: {}", err);
+            continue;
+        }
 
         // not very nice, but we currently have no direct access to the game phase
         if action.source_location == Some(CardLocation::PlayerHand) {
@@ -620,11 +629,14 @@ fn update_cards_from_action(
             transform.scale *= source_scale;
         }
 
-        // add below target_parent, reposition stack accordingly
-        commands
-            .entity(target_parent)
-            .insert_children(*action.dest_card_indices.first().unwrap(), &source_cards)
-            .insert(StackRepositioning);
+        // add below target_parent one after another (like CardAction::apply()),
+        // reposition stack accordingly
+        for (dest_index, card) in action.dest_card_indices.iter().zip(&source_cards) {
+            commands
+                .entity(target_parent)
+                .insert_children(*dest_index, &[*card]);
+        }
+        commands.entity(target_parent).insert(StackRepositioning);
 
         layout_state.step_animation_timer.reset();
         next_state.set(AppState::AnimationActive);
@@ -698,7 +710,6 @@ fn reposition_cards_after_action(
 
 pub fn handle_keyboard_input(
     mut game_logic: ResMut<GameLogic>,
-    runtime: ResMut<TasksRuntime>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
 ) {
     let mut play_card = None;
@@ -713,7 +724,7 @@ pub fn handle_keyboard_input(
     }
 
     if let Some(card_index) = play_card {
-        game_logic.play_card(runtime, card_index);
+        game_logic.play_card(card_index);
     }
 }
 
@@ -737,13 +748,52 @@ fn update_active_player_border(
     }
 }
 
+/// Tints the cards of our hand according to the move hints sent by the server
+/// (if any), once all pending state changes have been displayed.
+fn highlight_move_hints(
+    layout_state: Res<LayoutState>,
+    state_changes: Res<StateChanges>,
+    query_stacks: Query<(&CardStack, &Children)>,
+    mut query_cards: Query<(&CardSprite, &mut Sprite)>,
+) {
+    let up_to_date = layout_state.step_animation_timer.finished() && state_changes.is_empty();
+
+    for (stack, children) in &query_stacks {
+        if stack.location != CardLocation::PlayerHand || stack.index != layout_state.we_are_player {
+            continue;
+        }
+        for (card_index, card) in children.iter().enumerate() {
+            let Ok((card_sprite, mut sprite)) = query_cards.get_mut(card) else {
+                continue;
+            };
+            let outcome = state_changes
+                .move_hints()
+                .get(card_index)
+                .filter(|hint| up_to_date && hint.card == card_sprite.0.card)
+                .map(|hint| hint.outcome);
+            let color = match outcome {
+                Some(MoveOutcome::Trick { .. }) => MOVE_HINT_TRICK_COLOR,
+                Some(MoveOutcome::Zing { .. }) => MOVE_HINT_ZING_COLOR,
+                Some(MoveOutcome::OpensZing) => MOVE_HINT_OPENS_ZING_COLOR,
+                Some(MoveOutcome::Discard) | None => Color::WHITE,
+            };
+            if sprite.color != color {
+                sprite.color = color;
+            }
+        }
+    }
+}
+
 pub fn card_clicked(
     click: Trigger<Pointer<Click>>,
     layout_state: ResMut<LayoutState>,
     query_stacks: Query<(Entity, &Children)>,
-    mut game_logic: ResMut<GameLogic>,
-    runtime: ResMut<TasksRuntime>,
+    game_logic: Option<ResMut<GameLogic>>,
 ) {
+    // cards cannot be played when watching a replay
+    let Some(mut game_logic) = game_logic else {
+        return;
+    };
     if !layout_state.step_animation_timer.finished() {
         return;
     }
@@ -763,7 +813,7 @@ pub fn card_clicked(
 
     if let Some(card_index) = play_card {
         debug!("clicked card {}", card_index);
-        game_logic.play_card(runtime, card_index);
+        game_logic.play_card(card_index);
     }
 }
 
@@ -1,15 +1,29 @@
+use std::{path::PathBuf, process::ExitCode};
+
 use clap::Parser;
 use tracing_subscriber::EnvFilter;
 
 #[derive(Parser)]
 struct Cli {
-    login_id: String,
-    table_id: String,
+    #[arg(required_unless_present = "replay")]
+    login_id: Option<String>,
+    #[arg(required_unless_present = "replay")]
+    table_id: Option<String>,
     #[arg(default_value = "http://localhost:8000")]
     base_url: String,
+    /// Watch a game replay file (as exported by the server) instead of
+    /// joining a table
+    #[arg(long, conflicts_with_all = ["login_id", "table_id"])]
+    replay: Option<PathBuf>,
+    /// Player displayed in front when watching a replay
+    #[arg(long, default_value_t = 0)]
+    player: usize,
+    /// Show the cards of all players when watching a replay
+    #[arg(long)]
+    show_hands: bool,
 }
 
-fn main() {
+fn main() -> ExitCode {
     tracing_subscriber::fmt()
         .with_env_filter(
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
@@ -17,5 +31,22 @@ fn main() {
         .init();
 
     let args = Cli::parse();
-    zing_ui_lib::start_remote_game(args.login_id, args.table_id, args.base_url)
+    match (args.replay, args.login_id, args.table_id) {
+        (Some(replay_path), _, _) => {
+            let result = std::fs::read_to_string(&replay_path)
+                .map_err(|err| format!("could not read {}: {err}", replay_path.display()))
+                .and_then(|replay_json| {
+                    zing_ui_lib::start_replay(replay_json, args.player, args.show_hands)
+                });
+            if let Err(msg) = result {
+                eprintln!("{msg}");
+                return ExitCode::FAILURE;
+            }
+        }
+        (None, Some(login_id), Some(table_id)) => {
+            zing_ui_lib::start_remote_game(login_id, table_id, args.base_url)
+        }
+        _ => unreachable!("clap ensures that either a replay or a table is given"),
+    }
+    ExitCode::SUCCESS
 }